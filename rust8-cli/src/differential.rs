@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use crate::demo::{self, Demo, DemoEvent, DemoPlayer};
+use crate::hardware::Chip8;
+use crate::headless::{handle_events, CYCLES_PER_TIMER_TICK};
+use crate::reference::Reference;
+use crate::rom_file::read_rom;
+
+/// Describes every way the core's state differs from the reference's
+fn compare(chip8: &Chip8, reference: &Reference) -> Vec<String> {
+    let mut differences = vec![];
+    let mut check = |name: String, core: String, expected: String| {
+        if core != expected {
+            differences.push(format!("{}: core {}, reference {}", name, core, expected));
+        }
+    };
+
+    check(
+        "PC".to_string(),
+        format!("{:#05X}", chip8.program_counter()),
+        format!("{:#05X}", reference.pc),
+    );
+    check(
+        "I".to_string(),
+        format!("{:#05X}", chip8.i()),
+        format!("{:#05X}", reference.i),
+    );
+    for (register, (core, expected)) in chip8.registers().iter().zip(&reference.v).enumerate() {
+        check(
+            format!("V{:X}", register),
+            format!("{:#04X}", core),
+            format!("{:#04X}", expected),
+        );
+    }
+    check(
+        "stack".to_string(),
+        format!("{:X?}", chip8.stack()),
+        format!("{:X?}", reference.stack),
+    );
+    check(
+        "DT".to_string(),
+        chip8.delay_timer().to_string(),
+        reference.delay.to_string(),
+    );
+    check(
+        "ST".to_string(),
+        chip8.sound_timer().to_string(),
+        reference.sound.to_string(),
+    );
+
+    let memory = chip8.memory().iter().zip(reference.memory.iter());
+    if let Some((address, (core, expected))) = memory.enumerate().find(|(_, (a, b))| a != b) {
+        check(
+            format!("memory at {:#05X}", address),
+            format!("{:#04X}", core),
+            format!("{:#04X}", expected),
+        );
+    }
+
+    let (width, height) = chip8.display_size();
+    let lit = (0..height).flat_map(|y| (0..width).map(move |x| chip8.pixel(x, y) != 0));
+    let mismatched = lit.zip(reference.display.iter()).filter(|(a, b)| a != *b);
+    let count = mismatched.count();
+    if count > 0 {
+        differences.push(format!("display: {} pixels differ", count));
+    }
+
+    differences
+}
+
+/// Runs a ROM through both the core and the reference interpreter, optionally
+/// driven by a recorded demo, and reports the first frame where they diverge.
+/// Returns the process exit code.
+pub fn run_diff(path: &Path, script: Option<&Path>, cycles: u64) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let script = match script.map(Demo::load).transpose() {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    match diff(&rom, script.as_ref(), cycles) {
+        Ok((agreed, report)) => {
+            for line in report {
+                println!("{}", line);
+            }
+            if agreed {
+                0
+            } else {
+                1
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+/// Runs `rom` through both interpreters, returning whether they agreed and
+/// the lines to report, or why the core couldn't load it
+fn diff(rom: &[u8], script: Option<&Demo>, cycles: u64) -> Result<(bool, Vec<String>), String> {
+    let seed = script.map_or(0, |demo| demo.seed);
+
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(seed);
+    chip8.load_rom(rom).map_err(|e| e.to_string())?;
+
+    let mut reference = Reference::new(rom, seed);
+
+    let mut player = DemoPlayer::new();
+    let mut key_states = [false; 16];
+    let mut frame = 0;
+
+    for cycle in 0..cycles {
+        let mut tick = false;
+        match script {
+            Some(script) if player.is_finished(script) => break,
+            Some(script) => {
+                for (_, event) in player.events_until(script, cycle) {
+                    match *event {
+                        DemoEvent::Keys(mask) => key_states = demo::key_states(mask),
+                        DemoEvent::TimerTick => tick = true,
+                    }
+                }
+            }
+            None => tick = cycle % CYCLES_PER_TIMER_TICK == 0,
+        }
+
+        // Compare at every frame boundary, before the timers move on
+        if tick {
+            let differences = compare(&chip8, &reference);
+            if !differences.is_empty() {
+                let heading = format!("Diverged by frame {} (cycle {})", frame, cycle);
+                return Ok((false, describe(heading, differences)));
+            }
+
+            chip8.update_timers();
+            reference.tick_timers();
+            frame += 1;
+        }
+
+        let address = chip8.program_counter();
+        let core = chip8.step(&key_states);
+        handle_events(&mut chip8);
+
+        let outcome = match (core, reference.step(&key_states)) {
+            (Ok(()), Ok(())) => continue,
+            (Err(e), Err(expected)) => (
+                true,
+                format!("Both stopped at cycle {}: {} / {}", cycle, e, expected),
+            ),
+            (Err(e), Ok(())) => (
+                false,
+                format!(
+                    "Core stopped at cycle {} but reference did not: {}",
+                    cycle, e
+                ),
+            ),
+            (Ok(()), Err(expected)) => (
+                false,
+                format!(
+                    "Reference stopped at {:#05X} on cycle {} but core did not: {}",
+                    address, cycle, expected
+                ),
+            ),
+        };
+        return Ok((outcome.0, vec![outcome.1]));
+    }
+
+    let differences = compare(&chip8, &reference);
+    if !differences.is_empty() {
+        let heading = format!("Diverged by the end of frame {}", frame);
+        return Ok((false, describe(heading, differences)));
+    }
+
+    Ok((true, vec![format!("No divergence across {} frames", frame)]))
+}
+
+/// A divergence report, `heading` followed by each difference indented
+fn describe(heading: String, differences: Vec<String>) -> Vec<String> {
+    let indented = differences.into_iter().map(|d| format!("  {}", d));
+    std::iter::once(heading).chain(indented).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_rom_does_not_diverge() {
+        // V0 = 5, I at its glyph, draw it, then spin
+        let rom = [0x60, 0x05, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06];
+        let (agreed, report) = diff(&rom, None, 100).unwrap();
+        assert!(agreed, "{:?}", report);
+        assert!(report[0].starts_with("No divergence"), "{:?}", report);
+    }
+
+    #[test]
+    fn run_diff_reports_the_reference_running_off_the_end() {
+        // Jumps to 0xFFF, where the core wraps the fetch but the reference stops
+        let path = std::env::temp_dir().join("rust8-diff-off-the-end.ch8");
+        std::fs::write(&path, [0x60, 0x01, 0xBF, 0xFE]).unwrap();
+        let code = run_diff(&path, None, 100);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(code, 1);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::demo::{self, DemoRecorder};
+use crate::hardware::{Chip8, Chip8Error, Chip8Event};
+use crate::headless::CYCLES_PER_TIMER_TICK;
+use crate::rom_file::read_rom;
+
+/// Instructions between changes to the key states
+const INPUT_INTERVAL: u64 = 64;
+
+/// One run in this many gets fresh random inputs rather than mutated ones
+const FRESH_SCHEDULE_RATIO: u32 = 4;
+
+/// Most blocks of a corpus schedule changed to make a new one
+const MAX_MUTATIONS: u32 = 4;
+
+enum Fault {
+    /// The core stopped the ROM, e.g. on an unknown opcode or a stack
+    /// overflow
+    Error(Chip8Error),
+    /// The core panicked, which is a bug in the core rather than the ROM
+    Crash(String),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Error(error) => write!(f, "error ({})", error),
+            Fault::Crash(message) => write!(f, "crash ({})", message),
+        }
+    }
+}
+
+/// Keys held for each block of `INPUT_INTERVAL` instructions of a run, as
+/// bitmasks with key 0 in the lowest bit
+type Schedule = Vec<u16>;
+
+/// Outcome of a single seed, with its inputs recorded as a demo. The seed
+/// drives `CXNN`, but the inputs come from the corpus as it was then, so the
+/// demo is what reproduces the run.
+struct Run {
+    new_addresses: usize,
+    fault: Option<(u64, u16, Fault)>,
+    inputs: DemoRecorder,
+}
+
+/// Plays the ROM headlessly once per seed, reporting seeds that reach new
+/// code or trigger faults. The inputs of runs that reach new code are kept
+/// and mutated to make the inputs of later runs, so the search builds on
+/// the paths that got furthest. With `demos` set the inputs of each reported
+/// run are saved there. Returns the process exit code.
+pub fn run_fuzz(
+    path: &Path,
+    seeds: u64,
+    first_seed: u64,
+    cycles: u64,
+    demos: Option<&Path>,
+) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if let Some(directory) = demos {
+        if let Err(e) = fs::create_dir_all(directory) {
+            eprintln!("Failed to create {}: {}", directory.display(), e);
+            return 2;
+        }
+    }
+
+    // Faults are reported below, so keep the default hook from spamming stderr
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let blocks = cycles.div_ceil(INPUT_INTERVAL) as usize;
+    let mut corpus: Vec<Schedule> = vec![];
+    let mut coverage = HashSet::new();
+    let mut reported = HashSet::new();
+    let mut faulting_seeds = 0;
+
+    for seed in first_seed..(first_seed + seeds) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let schedule = next_schedule(&mut rng, &corpus, blocks);
+        let run = fuzz_seed(&rom, seed, &schedule, cycles, &mut coverage, &mut reported);
+
+        if run.new_addresses > 0 {
+            println!(
+                "seed {}: reached {} new addresses (coverage {})",
+                seed,
+                run.new_addresses,
+                coverage.len()
+            );
+            corpus.push(schedule);
+        }
+
+        if let Some((cycle, address, fault)) = &run.fault {
+            faulting_seeds += 1;
+            println!(
+                "seed {}: {} at {:#05X} after {} cycles",
+                seed, fault, address, cycle
+            );
+        }
+
+        if let (Some(directory), true) = (demos, run.new_addresses > 0 || run.fault.is_some()) {
+            let path = directory.join(format!("seed-{}.demo", seed));
+            if let Err(e) = run.inputs.demo().save(&path) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    panic::set_hook(default_hook);
+
+    println!(
+        "{} seeds, {} addresses covered, {} faulting seeds",
+        seeds,
+        coverage.len(),
+        faulting_seeds
+    );
+
+    if faulting_seeds > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Inputs for the next run: a schedule from the corpus with a few blocks
+/// changed, or a fresh random one while the corpus is empty and now and
+/// then after, so the search doesn't stay on the paths found so far
+fn next_schedule(rng: &mut StdRng, corpus: &[Schedule], blocks: usize) -> Schedule {
+    if corpus.is_empty() || blocks == 0 || rng.gen_ratio(1, FRESH_SCHEDULE_RATIO) {
+        return (0..blocks).map(|_| random_keys(rng)).collect();
+    }
+
+    let mut schedule = corpus[rng.gen_range(0..corpus.len())].clone();
+    for _ in 0..rng.gen_range(1..=MAX_MUTATIONS) {
+        let block = rng.gen_range(0..blocks);
+        schedule[block] = if rng.gen() {
+            random_keys(rng)
+        } else {
+            schedule[block] ^ 1 << rng.gen_range(0..16)
+        };
+    }
+    schedule
+}
+
+/// Keys held for one block, each with a 1 in 8 chance
+fn random_keys(rng: &mut StdRng) -> u16 {
+    (0..16).fold(0, |mask, key| mask | (rng.gen_ratio(1, 8) as u16) << key)
+}
+
+fn fuzz_seed(
+    rom: &[u8],
+    seed: u64,
+    schedule: &[u16],
+    cycles: u64,
+    coverage: &mut HashSet<u16>,
+    reported: &mut HashSet<String>,
+) -> Run {
+    let mut run = Run {
+        new_addresses: 0,
+        fault: None,
+        inputs: DemoRecorder::new(seed),
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(seed);
+    if let Err(error) = chip8.load_rom(rom) {
+        run.fault = Some((0, chip8.program_counter(), Fault::Error(error)));
+        return run;
+    }
+
+    let mut key_states = [false; 16];
+
+    for cycle in 0..cycles {
+        if cycle % INPUT_INTERVAL == 0 {
+            key_states = demo::key_states(schedule[(cycle / INPUT_INTERVAL) as usize]);
+        }
+        run.inputs.record_keys(cycle, &key_states);
+
+        let address = chip8.program_counter();
+        if coverage.insert(address) {
+            run.new_addresses += 1;
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| chip8.step(&key_states)));
+
+        match result {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => {
+                run.fault = Some((cycle, address, Fault::Error(error)));
+                break;
+            }
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                run.fault = Some((cycle, address, Fault::Crash(message)));
+                break;
+            }
+        }
+
+        report_events(&mut chip8, reported);
+
+        if cycle % CYCLES_PER_TIMER_TICK == 0 {
+            chip8.update_timers();
+            // Played back, the tick comes before the next instruction
+            run.inputs.record_timer_tick(cycle + 1);
+        }
+    }
+
+    run
+}
+
+/// Prints the core's warnings, each only the first time any run gives it.
+/// Every seed starts a new machine, which would warn about the same things
+/// all over again.
+fn report_events(chip8: &mut Chip8, reported: &mut HashSet<String>) {
+    while let Some(event) = chip8.poll_event() {
+        let message = match event {
+            Chip8Event::StrictViolation(violation) => violation.to_string(),
+            Chip8Event::Diagnostic(diagnostic) => diagnostic.to_string(),
+            Chip8Event::UnknownOpcode { address, opcode } => {
+                Chip8Error::UnknownOpcode { address, opcode }.to_string()
+            }
+            Chip8Event::ResolutionChanged { .. } | Chip8Event::FlagsStored | Chip8Event::Exited => {
+                continue
+            }
+        };
+        if reported.insert(message.clone()) {
+            eprintln!("{}", message);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use crate::hardware::{Chip8, Chip8Error, Chip8Event, TestResult};
+use crate::input::KeyProvider;
+use crate::rom_file::read_rom;
+
+/// Instructions executed per 60hz timer tick, matching the windowed 500hz rate
+pub const CYCLES_PER_TIMER_TICK: u64 = 8;
+
+/// Allocates an RGBA frame buffer matching the current display resolution
+pub fn frame_buffer(chip8: &Chip8) -> Vec<u8> {
+    let (width, height) = chip8.display_size();
+    vec![0u8; 4 * width as usize * height as usize]
+}
+
+/// Drains the core's events, reporting the ones worth knowing about
+pub fn handle_events(chip8: &mut Chip8) {
+    while let Some(event) = chip8.poll_event() {
+        match event {
+            Chip8Event::ResolutionChanged { .. } => (),
+            Chip8Event::StrictViolation(violation) => eprintln!("{}", violation),
+            Chip8Event::Diagnostic(diagnostic) => eprintln!("{}", diagnostic),
+            Chip8Event::UnknownOpcode { address, opcode } => {
+                eprintln!("{}", Chip8Error::UnknownOpcode { address, opcode })
+            }
+            Chip8Event::FlagsStored | Chip8Event::Exited => (),
+        }
+    }
+}
+
+/// Runs a test ROM without a window until it reports a result through the
+/// `00FA` extension opcode or fails an `00F9` assertion, returning the
+/// process exit code.
+pub fn run_test(path: &Path, max_cycles: u64, strict: bool, keys: &mut dyn KeyProvider) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.set_test_extensions(true);
+    chip8.set_strict(strict);
+    if let Err(e) = chip8.load_rom(&rom) {
+        eprintln!("{}", e);
+        return 2;
+    }
+
+    for cycle in 0..max_cycles {
+        if let Err(e) = chip8.step(&keys.keys(cycle)) {
+            println!("{} after {} cycles", e, cycle);
+            return 2;
+        }
+        handle_events(&mut chip8);
+
+        if cycle % CYCLES_PER_TIMER_TICK == 0 {
+            chip8.update_timers();
+        }
+
+        match chip8.test_result() {
+            Some(TestResult::Pass) => {
+                println!("PASS after {} cycles", cycle + 1);
+                return 0;
+            }
+            Some(TestResult::Fail(code)) => {
+                println!("FAIL with code {} after {} cycles", code, cycle + 1);
+                return 1;
+            }
+            Some(TestResult::AssertionFailed {
+                address,
+                register,
+                expected,
+                actual,
+            }) => {
+                println!(
+                    "FAIL at {:#05X}: V{:X} is {:#04X}, expected {:#04X}, after {} cycles",
+                    address,
+                    register,
+                    actual,
+                    expected,
+                    cycle + 1
+                );
+                return 1;
+            }
+            None => (),
+        }
+    }
+
+    println!("No result reported within {} cycles", max_cycles);
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Runs `program` as a test ROM with no keys held
+    fn run_program(name: &str, program: &[u16]) -> i32 {
+        let rom: Vec<u8> = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, rom).unwrap();
+        let code = run_test(&path, 100, false, &mut [false; 16]);
+        fs::remove_file(&path).unwrap();
+        code
+    }
+
+    #[test]
+    fn passing_assertions_run_on_to_the_report() {
+        // V3 = 5, assert V3 == 5, V0 = 0, report
+        let program = [0x6305, 0x00F9, 0x3305, 0x6000, 0x00FA];
+        assert_eq!(run_program("rust8-assert-pass.ch8", &program), 0);
+    }
+
+    #[test]
+    fn failed_assertion_fails_the_test() {
+        // V3 = 5, assert V3 == 6, V0 = 0, report
+        let program = [0x6305, 0x00F9, 0x3306, 0x6000, 0x00FA];
+        assert_eq!(run_program("rust8-assert-fail.ch8", &program), 1);
+    }
+}
@@ -0,0 +1,244 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::hardware::{opcode_pattern, Instruction, Platform};
+use crate::json::json_string;
+use crate::rom_file::read_rom;
+use crate::sha1::sha1;
+
+/// Instructions searched back from a key check for the `6XNN` that loaded its key
+const KEY_LOAD_LOOKBACK: usize = 4;
+
+/// The interpreter a ROM needs, judging by the newest opcodes it contains.
+/// Sprite data can look like any opcode, so this is a guess.
+fn detect_platform(instructions: &[Instruction]) -> Option<Platform> {
+    let xochip = instructions.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::ScrollUp(_)
+                | Instruction::SaveRange(..)
+                | Instruction::LoadRange(..)
+                | Instruction::SelectPlanes(_)
+                | Instruction::SetILong
+                | Instruction::LoadAudio
+                | Instruction::SetPitch(_)
+        )
+    });
+    // MEGA-CHIP's other opcodes look too much like data to count
+    let megachip = instructions
+        .iter()
+        .any(|instruction| matches!(instruction, Instruction::MegaOn));
+    let chip8x = instructions.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::CycleBackground
+                | Instruction::AddNibbles(..)
+                | Instruction::SecondKeyDown(_)
+                | Instruction::SecondKeyUp(_)
+                | Instruction::OutputPort(_)
+                | Instruction::InputPort(_)
+        )
+    });
+    let schip = instructions.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::ScrollDown(_)
+                | Instruction::ScrollRight
+                | Instruction::ScrollLeft
+                | Instruction::Exit
+                | Instruction::LowRes
+                | Instruction::HighRes
+                | Instruction::SetIBigSpriteReg(_)
+                | Instruction::StoreFlags(_)
+                | Instruction::LoadFlags(_)
+        )
+    });
+
+    if megachip {
+        Some(Platform::MegaChip)
+    } else if xochip {
+        Some(Platform::XoChip)
+    } else if schip {
+        Some(Platform::Schip)
+    } else if chip8x {
+        Some(Platform::Chip8x)
+    } else {
+        None
+    }
+}
+
+/// Keys the ROM checks with `EX9E`/`EXA1` after loading the key into the
+/// register with `6XNN` shortly before. Keys computed at runtime are missed.
+fn referenced_keys(instructions: &[Instruction]) -> BTreeSet<u8> {
+    let mut keys = BTreeSet::new();
+
+    for (i, instruction) in instructions.iter().enumerate() {
+        let register = match instruction {
+            Instruction::KeyDown(register) | Instruction::KeyUp(register) => *register,
+            _ => continue,
+        };
+
+        let loaded = instructions[i.saturating_sub(KEY_LOAD_LOOKBACK)..i]
+            .iter()
+            .rev()
+            .find_map(|instruction| match instruction {
+                Instruction::SetRegVal(target, key) if *target == register => Some(*key),
+                _ => None,
+            });
+        if let Some(key) = loaded {
+            keys.insert(key & 0xF);
+        }
+    }
+
+    keys
+}
+
+/// What can be learned about a ROM without running it
+struct RomInfo {
+    size: usize,
+    digest: String,
+    platform: Option<Platform>,
+    keys: BTreeSet<u8>,
+    waits: bool,
+    words: usize,
+    /// How often each opcode pattern appears, most common first
+    counts: Vec<(&'static str, u64)>,
+}
+
+impl RomInfo {
+    fn new(rom: &[u8]) -> Self {
+        let digest: String = sha1(rom)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let opcodes: Vec<u16> = rom
+            .chunks_exact(2)
+            .map(|word| (word[0] as u16) << 8 | word[1] as u16)
+            .collect();
+        let instructions: Vec<Instruction> =
+            opcodes.iter().map(|&op| Instruction::decode(op)).collect();
+
+        let platform = detect_platform(&instructions);
+        let keys = referenced_keys(&instructions);
+        let waits = instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::SetRegKey(_)));
+
+        // Every aligned word is counted, so sprite data shows up as whatever
+        // opcode it happens to look like
+        let mut counts: HashMap<&str, u64> = HashMap::new();
+        for &opcode in &opcodes {
+            *counts
+                .entry(opcode_pattern(opcode).unwrap_or("data"))
+                .or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by_key(|&(pattern, count)| (Reverse(count), pattern));
+
+        RomInfo {
+            size: rom.len(),
+            digest,
+            platform,
+            keys,
+            waits,
+            words: opcodes.len(),
+            counts,
+        }
+    }
+
+    /// The report as JSON, naming the ROM by `path`
+    fn json(&self, path: &Path) -> String {
+        let keys: Vec<String> = self.keys.iter().map(u8::to_string).collect();
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(pattern, count)| format!("    {}: {}", json_string(pattern), count))
+            .collect();
+        format!(
+            "{{\n  \"file\": {},\n  \"size\": {},\n  \"sha1\": \"{}\",\n  \"platform\": \"{}\",\n  \"keys\": [{}],\n  \"waits_for_key\": {},\n  \"opcodes\": {{\n{}\n  }}\n}}",
+            json_string(&path.display().to_string()),
+            self.size,
+            self.digest,
+            match self.platform {
+                Some(Platform::XoChip) => "xochip",
+                Some(Platform::Chip8x) => "chip8x",
+                Some(Platform::MegaChip) => "megachip",
+                Some(_) => "schip",
+                None => "chip8",
+            },
+            keys.join(", "),
+            self.waits,
+            counts.join(",\n")
+        )
+    }
+}
+
+/// Prints what can be learned about a ROM without running it: its size and
+/// SHA-1, the platform it seems to target, how often each opcode appears and
+/// the keys it checks, as JSON if `json` is set. Returns the process exit code.
+pub fn run_info(path: &Path, json: bool) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let info = RomInfo::new(&rom);
+    if json {
+        println!("{}", info.json(path));
+        return 0;
+    }
+
+    println!("File:     {}", path.display());
+    println!("Size:     {} bytes", info.size);
+    println!("SHA-1:    {}", info.digest);
+    println!(
+        "Platform: {}",
+        match info.platform {
+            Some(Platform::XoChip) => "XO-CHIP",
+            Some(Platform::Chip8x) => "CHIP-8X",
+            Some(Platform::MegaChip) => "MEGA-CHIP",
+            Some(_) => "SUPER-CHIP",
+            None => "CHIP-8",
+        }
+    );
+
+    let mut key_list: Vec<String> = info.keys.iter().map(|key| format!("{:X}", key)).collect();
+    if info.waits {
+        key_list.push("any (FX0A)".to_string());
+    }
+    println!(
+        "Keys:     {}",
+        if key_list.is_empty() {
+            "none found".to_string()
+        } else {
+            key_list.join(" ")
+        }
+    );
+
+    println!("\nOpcodes in {} words:", info.words);
+    for (pattern, count) in info.counts {
+        println!("  {:<6}{:>6}", pattern, count);
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_report_parses_with_control_characters_in_the_name() {
+        let rom = [0x60, 0x05, 0xE0, 0x9E, 0x12, 0x00];
+        let path = Path::new("a\tb\u{1}\"c\".ch8");
+        let report: serde_json::Value =
+            serde_json::from_str(&RomInfo::new(&rom).json(path)).unwrap();
+        assert_eq!(report["file"], "a\tb\u{1}\"c\".ch8");
+        assert_eq!(report["size"], 6);
+        assert_eq!(report["keys"], serde_json::json!([5]));
+    }
+}
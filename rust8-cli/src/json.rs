@@ -0,0 +1,57 @@
+//! Hand written JSON for the subcommands' `--json` output, which is small
+//! enough not to need a serializer.
+
+use crate::hardware::OPCODE_TABLE;
+
+/// Quotes and escapes a string for JSON output
+pub fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Exports the opcode table as a JSON array, for editors and documentation
+/// tooling that need to match what the decoder supports
+pub fn opcode_table_json() -> String {
+    let entries: Vec<String> = OPCODE_TABLE
+        .iter()
+        .map(|info| {
+            let operands: Vec<String> = info.operands().iter().map(|o| json_string(o)).collect();
+            format!(
+                "  {{\"pattern\": {}, \"mnemonic\": {}, \"operands\": [{}], \"description\": {}}}",
+                json_string(info.pattern),
+                json_string(info.mnemonic()),
+                operands.join(", "),
+                json_string(info.description)
+            )
+        })
+        .collect();
+
+    format!("[\n{}\n]", entries.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_are_escaped() {
+        assert_eq!(json_string("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(json_string("\t\r\n\u{8}\u{c}"), r#""\t\r\n\b\f""#);
+        assert_eq!(json_string("\u{0}\u{1f} é"), r#""\u0000\u001f é""#);
+    }
+}
@@ -0,0 +1,448 @@
+mod differential;
+mod fuzzer;
+mod headless;
+mod info;
+mod json;
+mod profiler;
+mod reference;
+mod selftest;
+mod sprites;
+mod verify;
+
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use rust8_core as hardware;
+
+// Host pieces shared with the window, under the same paths as within it
+use rust8_frontend_winit::{assembler, audio, config, demo, display, input, patcher, png};
+use rust8_frontend_winit::{rom_file, sha1};
+
+use demo::Demo;
+use hardware::Platform;
+use input::{KeyProvider, ReplayKeys, ScriptedKeys, StdinKeys};
+use rust8_frontend_winit::{
+    load_watches, run_multi_window, run_netplay, run_window, Playlist, RomSource, WindowOptions,
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(name = "rust8", about = "A CHIP-8 emulator")]
+struct Opt {
+    /// ROM to run in a window. Several ROMs open a window each, without
+    /// the extras such as prompts, demos and watches.
+    #[structopt(parse(from_os_str))]
+    roms: Vec<PathBuf>,
+    /// Demo file to play back when the emulator is left idle, in place of
+    /// the one the ROM database lists for the ROM
+    #[structopt(long, parse(from_os_str))]
+    demo: Option<PathBuf>,
+    /// Seconds without a key press before the demo starts
+    #[structopt(long, default_value = "30")]
+    attract_after: u64,
+    /// Records this session's inputs to a demo file on exit
+    #[structopt(long, parse(from_os_str))]
+    record_demo: Option<PathBuf>,
+    /// Shows emulated time, cycles per frame and dropped frames in the title bar
+    #[structopt(long)]
+    frame_stats: bool,
+    /// Prints instruction, frame and draw counts and an opcode histogram on exit
+    #[structopt(long)]
+    session_stats: bool,
+    /// Shows the time from a host key event to the first EX9E, EXA1 or FX0A
+    /// that reads the key over the bottom of the game
+    #[structopt(long)]
+    input_latency: bool,
+    /// Shows the memory locations and expressions listed in this file over the game,
+    /// toggled with F5
+    #[structopt(long, parse(from_os_str))]
+    watch: Option<PathBuf>,
+    /// Poisons unwritten memory and reports reads of it, writes to the
+    /// interpreter's reserved memory and stack misuse, with the address of
+    /// the instruction
+    #[structopt(long)]
+    strict: bool,
+    /// Also takes keys from TCP clients connecting to this address, each
+    /// sending lines of the held keys as a hex bitmask
+    #[structopt(long)]
+    listen_keys: Option<String>,
+    /// Also takes keys piped in on standard input, one line per change as
+    /// `CYCLE keys MASK`, or `keys MASK` to apply it straight away
+    #[structopt(long)]
+    stdin_keys: bool,
+    /// Uses the quirks of an interpreter: vip, chip8x, eti660, chip48,
+    /// schip, megachip or xochip
+    #[structopt(long)]
+    platform: Option<Platform>,
+    /// Small font for FX29: chip48, vip, dream6800 or the path to an 80 byte
+    /// file. Overrides the config.
+    #[structopt(long)]
+    font: Option<String>,
+    /// Uses the settings of this profile from the config's `profiles` table
+    #[structopt(long)]
+    profile: Option<String>,
+    /// Presents only every Nth frame while still emulating all of them, for
+    /// slow hosts. Overrides the config.
+    #[structopt(long)]
+    frame_skip: Option<u32>,
+    /// Saves every frame to this directory as a numbered PNG
+    #[structopt(long, parse(from_os_str))]
+    capture: Option<PathBuf>,
+    /// Checks a ROM for opcodes that cannot run without running it, then exits
+    #[structopt(long, parse(from_os_str))]
+    verify_rom: Option<PathBuf>,
+    /// Prints the results of --verify-rom as JSON
+    #[structopt(long)]
+    json: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Runs a test ROM headlessly until it reports a result through the 00FA
+    /// opcode or fails an 00F9 assertion
+    Test {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Number of instructions to execute before giving up
+        #[structopt(long, default_value = "1000000")]
+        max_cycles: u64,
+        /// Reports memory and stack misuse as with the windowed `--strict`
+        #[structopt(long)]
+        strict: bool,
+        /// Plays back the key presses of a demo file
+        #[structopt(long, parse(from_os_str))]
+        replay: Option<PathBuf>,
+        /// Holds a key over a range of instructions, written as KEY@START-END
+        /// with the key in hex. May be given several times.
+        #[structopt(long = "press", number_of_values = 1)]
+        presses: Vec<String>,
+        /// Takes key events piped in on standard input as with the windowed
+        /// `--stdin-keys`, waiting for the next one rather than running ahead
+        #[structopt(long)]
+        stdin_keys: bool,
+    },
+    /// Plays a ROM headlessly across many seeds, reporting seeds that reach
+    /// new code or crash the emulator. The inputs of runs that reach new code
+    /// are mutated to make the inputs of later ones.
+    Fuzz {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Number of seeds to try
+        #[structopt(long, default_value = "100")]
+        seeds: u64,
+        /// Seed to start from, so a reported seed can be replayed on its own
+        #[structopt(long, default_value = "0")]
+        first_seed: u64,
+        /// Instructions to execute per seed
+        #[structopt(long, default_value = "100000")]
+        cycles: u64,
+        /// Saves the inputs of each reported seed to this directory as a demo
+        /// file, to replay with `diff --script` or the window's `--demo`
+        #[structopt(long, parse(from_os_str))]
+        demos: Option<PathBuf>,
+    },
+    /// Runs a ROM through this core and a simple reference interpreter side by
+    /// side, reporting the first frame where their state differs
+    Diff {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Demo file whose inputs and timer ticks drive both interpreters
+        #[structopt(long, parse(from_os_str))]
+        script: Option<PathBuf>,
+        /// Instructions to execute before declaring the run clean
+        #[structopt(long, default_value = "100000")]
+        cycles: u64,
+    },
+    /// Runs a built in program headlessly with a fixed seed and checks the
+    /// final frame, to validate a build on a machine without a GPU
+    Selftest,
+    /// Runs a ROM headlessly as fast as possible and reports the host time
+    /// spent on each opcode family
+    Bench {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Instructions to execute
+        #[structopt(long, default_value = "1000000")]
+        cycles: u64,
+        /// Prints the timings as JSON
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Times a built in XO-CHIP program that does nothing but draw, with the
+    /// frame buffer converted after every instruction and once a frame
+    DrawBench {
+        /// Frames to emulate for each timing
+        #[structopt(long, default_value = "60")]
+        frames: u64,
+        /// Instructions per frame, as heavy XO-CHIP games set it
+        #[structopt(long, default_value = "100000")]
+        cycles_per_frame: u32,
+        /// Prints the timings as JSON
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Runs a ROM headlessly, printing every sprite drawn with its position,
+    /// to rip the graphics of a game
+    Sprites {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Number of 60hz frames to run for
+        #[structopt(long, default_value = "600")]
+        frames: u64,
+        /// Demo file supplying the inputs, so sprites past the title screen are reached
+        #[structopt(long, parse(from_os_str))]
+        demo: Option<PathBuf>,
+        /// Saves each distinct sprite to this PNG sprite sheet
+        #[structopt(long, parse(from_os_str))]
+        sheet: Option<PathBuf>,
+    },
+    /// Writes a copy of a ROM with patches applied, e.g. community bug fixes
+    Patch {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Patch as address=value, where the value is a number such as
+        /// 0x00E0 or assembly such as CLS. Can be given more than once.
+        #[structopt(long = "set", number_of_values = 1)]
+        sets: Vec<String>,
+        /// IPS patch applied before any --set patches
+        #[structopt(long, parse(from_os_str))]
+        ips: Option<PathBuf>,
+        /// Where to write the patched ROM
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Prints the supported opcodes as JSON, for editors and other tooling
+    Opcodes,
+    /// Prints a ROM's size, SHA-1, likely platform, the keys it checks and
+    /// how often each opcode appears, without running it
+    Info {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Prints the summary as JSON
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Cycles through every ROM in a directory, with a title card between games
+    Kiosk {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        /// Seconds to run each ROM for, unless it halts first
+        #[structopt(long, default_value = "120")]
+        seconds: u64,
+        /// Seconds to show the title card before each ROM
+        #[structopt(long, default_value = "3")]
+        title_seconds: u64,
+    },
+    /// Experimental: plays a ROM in lockstep with another instance over TCP,
+    /// so two players can share the keypad from different computers
+    Netplay {
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Address to wait for the other player on, e.g. 0.0.0.0:7878
+        #[structopt(long, required_unless = "join", conflicts_with = "join")]
+        host: Option<String>,
+        /// Address of the instance hosting the game
+        #[structopt(long)]
+        join: Option<String>,
+    },
+    /// Assembles a source file into a ROM
+    Asm {
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+        /// Where to write the assembled ROM
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    if let Some(path) = &opt.verify_rom {
+        process::exit(verify::run_verify(path, opt.json));
+    }
+
+    let demo = match &opt.demo {
+        Some(path) => match Demo::load(path) {
+            Ok(demo) => Some(demo),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let watches = match &opt.watch {
+        Some(path) => match load_watches(path) {
+            Ok(watches) => Some(watches),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let attract_after = Duration::from_secs(opt.attract_after);
+    let record_demo = opt.record_demo;
+    let frame_stats = opt.frame_stats;
+    let session_stats = opt.session_stats;
+    let input_latency = opt.input_latency;
+    let strict = opt.strict;
+    let listen_keys = opt.listen_keys;
+    let stdin_keys = opt.stdin_keys;
+    let capture = opt.capture;
+    let platform = opt.platform;
+    let font = opt.font;
+    let profile = opt.profile;
+    let frame_skip = opt.frame_skip;
+    let window_options = |source| WindowOptions {
+        source,
+        demo,
+        attract_after,
+        record_demo,
+        frame_stats,
+        session_stats,
+        input_latency,
+        watches,
+        strict,
+        listen_keys,
+        stdin_keys,
+        capture,
+        platform,
+        font: font.clone(),
+        profile: profile.clone(),
+        frame_skip,
+    };
+
+    match opt.command {
+        Some(Command::Test {
+            rom,
+            max_cycles,
+            strict,
+            replay,
+            presses,
+            stdin_keys,
+        }) => {
+            let keys: Result<Box<dyn KeyProvider>, String> = match replay {
+                Some(path) => Demo::load(&path).map(|demo| Box::new(ReplayKeys::new(demo)) as _),
+                None if stdin_keys => Ok(Box::new(StdinKeys::spawn(true))),
+                None => ScriptedKeys::parse(&presses).map(|keys| Box::new(keys) as _),
+            };
+            match keys {
+                Ok(mut keys) => {
+                    process::exit(headless::run_test(&rom, max_cycles, strict, keys.as_mut()))
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                }
+            }
+        }
+        Some(Command::Fuzz {
+            rom,
+            seeds,
+            first_seed,
+            cycles,
+            demos,
+        }) => process::exit(fuzzer::run_fuzz(
+            &rom,
+            seeds,
+            first_seed,
+            cycles,
+            demos.as_deref(),
+        )),
+        Some(Command::Diff {
+            rom,
+            script,
+            cycles,
+        }) => process::exit(differential::run_diff(&rom, script.as_deref(), cycles)),
+        Some(Command::Selftest) => process::exit(selftest::run_selftest()),
+        Some(Command::Bench { rom, cycles, json }) => {
+            process::exit(profiler::run_bench(&rom, cycles, json))
+        }
+        Some(Command::DrawBench {
+            frames,
+            cycles_per_frame,
+            json,
+        }) => process::exit(profiler::run_draw_bench(frames, cycles_per_frame, json)),
+        Some(Command::Sprites {
+            rom,
+            frames,
+            demo,
+            sheet,
+        }) => {
+            let demo = match demo.as_deref().map(Demo::load).transpose() {
+                Ok(demo) => demo,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                }
+            };
+            process::exit(sprites::run_sprites(
+                &rom,
+                frames,
+                demo.as_ref(),
+                sheet.as_deref(),
+            ))
+        }
+        Some(Command::Patch {
+            rom,
+            sets,
+            ips,
+            output,
+        }) => process::exit(patcher::run_patch(&rom, &sets, ips.as_deref(), &output)),
+        Some(Command::Opcodes) => println!("{}", json::opcode_table_json()),
+        Some(Command::Info { rom, json }) => process::exit(info::run_info(&rom, json)),
+        Some(Command::Netplay { rom, host, join }) => {
+            run_netplay(&rom, host.as_deref(), join.as_deref())
+        }
+        Some(Command::Asm { source, output }) => {
+            process::exit(assembler::run_assemble(&source, &output))
+        }
+        Some(Command::Kiosk {
+            dir,
+            seconds,
+            title_seconds,
+        }) => {
+            let play_time = Duration::from_secs(seconds);
+            let title_time = Duration::from_secs(title_seconds);
+            match Playlist::from_dir(&dir, play_time, title_time) {
+                Ok(playlist) => run_window(window_options(RomSource::Playlist(playlist))),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                }
+            }
+        }
+        None => {
+            if opt.roms.is_empty() {
+                run_window(window_options(RomSource::Splash));
+                return;
+            }
+
+            let mut roms = vec![];
+            for path in &opt.roms {
+                match rom_file::read_rom(path) {
+                    Ok(rom) => {
+                        let name = path.file_name().unwrap_or_default();
+                        roms.push((name.to_string_lossy().to_string(), rom));
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        process::exit(2);
+                    }
+                }
+            }
+
+            if roms.len() > 1 {
+                run_multi_window(roms, strict, platform, font, profile);
+            } else {
+                let (name, rom) = roms.remove(0);
+                run_window(window_options(RomSource::File { name, rom }))
+            }
+        }
+    }
+}
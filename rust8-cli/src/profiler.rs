@@ -0,0 +1,254 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::assembler;
+use crate::display;
+use crate::hardware::{opcode_pattern, Chip8};
+use crate::headless::{frame_buffer, handle_events, CYCLES_PER_TIMER_TICK};
+use crate::json::json_string;
+use crate::rom_file::read_rom;
+
+/// Host time spent executing one opcode family
+#[derive(Default)]
+struct OpcodeStats {
+    count: u64,
+    total: Duration,
+    worst: Duration,
+}
+
+/// Collects per-opcode timings, keyed by the opcode table pattern so that
+/// every draw is counted under `DXYN`, every BCD under `FX33` and so on
+#[derive(Default)]
+pub struct Profiler {
+    stats: HashMap<&'static str, OpcodeStats>,
+}
+
+impl Profiler {
+    pub fn record(&mut self, opcode: u16, elapsed: Duration) {
+        let pattern = opcode_pattern(opcode).unwrap_or("????");
+        let stats = self.stats.entry(pattern).or_default();
+
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.worst = stats.worst.max(elapsed);
+    }
+
+    /// A table of every opcode family seen, most expensive overall first
+    pub fn report(&self) -> String {
+        let mut families: Vec<_> = self.stats.iter().collect();
+        families.sort_by_key(|(_, stats)| Reverse(stats.total));
+
+        let mut report = format!(
+            "{:<8}{:>12}{:>12}{:>12}{:>12}\n",
+            "opcode", "count", "avg ns", "worst ns", "total ms"
+        );
+        for (pattern, stats) in families {
+            report.push_str(&format!(
+                "{:<8}{:>12}{:>12}{:>12}{:>12.3}\n",
+                pattern,
+                stats.count,
+                stats.total.as_nanos() / stats.count as u128,
+                stats.worst.as_nanos(),
+                stats.total.as_secs_f64() * 1000.0
+            ));
+        }
+
+        report
+    }
+
+    /// The same figures as `report` as a JSON array, with times in nanoseconds
+    pub fn report_json(&self) -> String {
+        let mut families: Vec<_> = self.stats.iter().collect();
+        families.sort_by_key(|(_, stats)| Reverse(stats.total));
+
+        let entries: Vec<String> = families
+            .iter()
+            .map(|(pattern, stats)| {
+                format!(
+                    "    {{\"opcode\": {}, \"count\": {}, \"worst_ns\": {}, \"total_ns\": {}}}",
+                    json_string(pattern),
+                    stats.count,
+                    stats.worst.as_nanos(),
+                    stats.total.as_nanos()
+                )
+            })
+            .collect();
+        format!("[\n{}\n  ]", entries.join(",\n"))
+    }
+}
+
+/// Runs a ROM headlessly as fast as possible, timing every instruction, and
+/// prints a per-opcode breakdown, as JSON if `json` is set. Returns the
+/// process exit code.
+pub fn run_bench(path: &Path, cycles: u64, json: bool) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(0);
+    if let Err(e) = chip8.load_rom(&rom) {
+        eprintln!("{}", e);
+        return 2;
+    }
+
+    let key_states = [false; 16];
+    let mut profiler = Profiler::default();
+
+    let started = Instant::now();
+    let mut executed = 0;
+    let mut stopped = None;
+
+    for cycle in 0..cycles {
+        let opcode = chip8.opcode();
+
+        let step_started = Instant::now();
+        let result = chip8.step(&key_states);
+        profiler.record(opcode, step_started.elapsed());
+
+        if let Err(e) = result {
+            stopped = Some(e);
+            break;
+        }
+        executed += 1;
+
+        handle_events(&mut chip8);
+
+        if cycle % CYCLES_PER_TIMER_TICK == 0 {
+            chip8.update_timers();
+        }
+    }
+
+    let elapsed = started.elapsed();
+
+    if json {
+        println!(
+            "{{\n  \"instructions\": {},\n  \"seconds\": {},\n  \"stopped\": {},\n  \"opcodes\": {}\n}}",
+            executed,
+            elapsed.as_secs_f64(),
+            stopped.map_or("null".to_string(), |e| json_string(&e.to_string())),
+            profiler.report_json()
+        );
+        return 0;
+    }
+
+    if let Some(e) = stopped {
+        println!("Stopped after {} cycles: {}", executed, e);
+    }
+    print!("{}", profiler.report());
+    println!(
+        "{} instructions in {:.3}s ({:.0} per second)",
+        executed,
+        elapsed.as_secs_f64(),
+        executed as f64 / elapsed.as_secs_f64()
+    );
+
+    0
+}
+
+/// Draws 16x16 sprites on both XO-CHIP planes as fast as it can, wrapping
+/// around the high resolution display
+const DRAW_PROGRAM: &str = "
+        HIGH
+        PLANE 3
+        LD I, sprite
+loop:   DRW V0, V1, 0
+        ADD V0, 3
+        ADD V1, 1
+        JP loop
+sprite:
+        DB 0xFF, 0xFF, 0x80, 0x01, 0xBF, 0xFD, 0xA0, 0x05
+        DB 0xAF, 0xF5, 0xA8, 0x15, 0xAB, 0xD5, 0xAA, 0x55
+        DB 0xAA, 0x55, 0xAB, 0xD5, 0xA8, 0x15, 0xAF, 0xF5
+        DB 0xA0, 0x05, 0xBF, 0xFD, 0x80, 0x01, 0xFF, 0xFF
+        DB 0x00, 0x00, 0x7F, 0xFE, 0x40, 0x02, 0x5F, 0xFA
+        DB 0x50, 0x0A, 0x57, 0xEA, 0x54, 0x2A, 0x55, 0xAA
+        DB 0x55, 0xAA, 0x54, 0x2A, 0x57, 0xEA, 0x50, 0x0A
+        DB 0x5F, 0xFA, 0x40, 0x02, 0x7F, 0xFE, 0x00, 0x00
+";
+
+/// Host time taken to emulate `frames` frames of the draw program, with the
+/// frame buffer brought up to date after every instruction if `per_step`,
+/// or once a frame as `run_frame` does otherwise
+fn time_draws(rom: &[u8], frames: u64, cycles_per_frame: u32, per_step: bool) -> Duration {
+    let mut chip8 = Chip8::new();
+    chip8.set_cycles_per_frame(cycles_per_frame);
+    chip8
+        .load_rom(rom)
+        .expect("the draw benchmark program loads");
+
+    let key_states = [false; 16];
+    // Get into high resolution before timing anything
+    chip8
+        .step(&key_states)
+        .expect("the draw benchmark program runs");
+    handle_events(&mut chip8);
+    let mut pixels = frame_buffer(&chip8);
+
+    let started = Instant::now();
+    for _ in 0..frames {
+        if per_step {
+            for _ in 0..cycles_per_frame {
+                chip8
+                    .step(&key_states)
+                    .expect("the draw benchmark program runs");
+                display::present(&chip8, &mut pixels);
+            }
+            chip8.update_timers();
+        } else {
+            chip8
+                .run_frame(&key_states)
+                .expect("the draw benchmark program runs");
+            display::present(&chip8, &mut pixels);
+        }
+    }
+    started.elapsed()
+}
+
+/// Times a built in XO-CHIP program that does little but draw, converting
+/// the display to RGBA after every instruction and then only once a frame,
+/// and prints both, as JSON if `json` is set. Returns the process exit code.
+pub fn run_draw_bench(frames: u64, cycles_per_frame: u32, json: bool) -> i32 {
+    let rom = assembler::assemble(DRAW_PROGRAM).expect("the draw benchmark program assembles");
+
+    let per_step = time_draws(&rom, frames, cycles_per_frame, true);
+    let per_frame = time_draws(&rom, frames, cycles_per_frame, false);
+    let speedup = per_step.as_secs_f64() / per_frame.as_secs_f64();
+
+    if json {
+        println!(
+            "{{\n  \"frames\": {},\n  \"cycles_per_frame\": {},\n  \"per_step_seconds\": {},\n  \"per_frame_seconds\": {},\n  \"speedup\": {}\n}}",
+            frames,
+            cycles_per_frame,
+            per_step.as_secs_f64(),
+            per_frame.as_secs_f64(),
+            speedup
+        );
+        return 0;
+    }
+
+    println!(
+        "{} frames of {} instructions, a quarter of them 16x16 draws on both planes",
+        frames, cycles_per_frame
+    );
+    for (label, elapsed) in [
+        ("Presented every step", per_step),
+        ("Presented every frame", per_frame),
+    ] {
+        println!(
+            "{:<24}{:>10.3}s{:>10.2}ms per frame",
+            label,
+            elapsed.as_secs_f64(),
+            elapsed.as_secs_f64() * 1000.0 / frames as f64
+        );
+    }
+    println!("{:.1}x faster presenting once a frame", speedup);
+
+    0
+}
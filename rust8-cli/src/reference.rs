@@ -0,0 +1,238 @@
+//! A deliberately naive CHIP-8 interpreter used as an oracle for differential
+//! testing. It shares no code with `hardware` so that a bug in the decoder or
+//! an instruction handler there shows up as a divergence here.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const MEMORY_SIZE: usize = 4096;
+const DISPLAY_WIDTH: usize = 64;
+const DISPLAY_HEIGHT: usize = 32;
+
+const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
+    0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
+    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
+    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, 0xF0, 0x80, 0x80, 0x80,
+    0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+];
+
+/// The SUPER-CHIP 8x10 digits, which the core keeps straight after the small font
+const LARGE_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x18, 0x78, 0x78, 0x18, 0x18, 0x18,
+    0x18, 0x18, 0xFF, 0xFF, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03,
+    0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xFF, 0xFF, 0xC0, 0xC0,
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18,
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF,
+    0x03, 0x03, 0xFF, 0xFF, 0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xFC, 0xFC,
+    0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3,
+    0xFF, 0x3C, 0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, 0xFF, 0xFF, 0xC0, 0xC0,
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0,
+];
+
+pub struct Reference {
+    pub pc: u16,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub stack: Vec<u16>,
+    pub delay: u8,
+    pub sound: u8,
+    pub memory: [u8; MEMORY_SIZE],
+    /// One entry per pixel, row major
+    pub display: [bool; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    rng: StdRng,
+}
+
+impl Reference {
+    pub fn new(rom: &[u8], seed: u64) -> Self {
+        let mut memory = [0u8; MEMORY_SIZE];
+        memory[..FONT.len()].copy_from_slice(&FONT);
+        memory[FONT.len()..FONT.len() + LARGE_FONT.len()].copy_from_slice(&LARGE_FONT);
+        memory[0x200..0x200 + rom.len()].copy_from_slice(rom);
+
+        Reference {
+            pc: 0x200,
+            v: [0; 16],
+            i: 0,
+            stack: vec![],
+            delay: 0,
+            sound: 0,
+            memory,
+            display: [false; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    fn read(&self, address: usize) -> Result<u8, String> {
+        self.memory
+            .get(address)
+            .copied()
+            .ok_or_else(|| out_of_memory(address))
+    }
+
+    /// Executes one instruction, describing the problem if it cannot
+    pub fn step(&mut self, keys: &[bool; 16]) -> Result<(), String> {
+        let pc = self.pc as usize;
+        let op = (self.read(pc)? as u16) << 8 | self.read(pc + 1)? as u16;
+
+        let x = ((op >> 8) & 0xF) as usize;
+        let y = ((op >> 4) & 0xF) as usize;
+        let n = (op & 0xF) as u8;
+        let nn = (op & 0xFF) as u8;
+        let nnn = op & 0xFFF;
+
+        let mut next = self.pc + 2;
+        let skip = self.pc + 4;
+
+        match op >> 12 {
+            0x0 => match op {
+                0x00E0 => self.display = [false; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+                0x00EE => next = self.stack.pop().ok_or("stack underflow")?,
+                _ => (),
+            },
+            0x1 => next = nnn,
+            0x2 => {
+                self.stack.push(next);
+                next = nnn;
+            }
+            0x3 if self.v[x] == nn => next = skip,
+            0x4 if self.v[x] != nn => next = skip,
+            0x5 if self.v[x] == self.v[y] => next = skip,
+            0x3..=0x5 => (),
+            0x6 => self.v[x] = nn,
+            0x7 => self.v[x] = self.v[x].wrapping_add(nn),
+            0x8 => match n {
+                0x0 => self.v[x] = self.v[y],
+                0x1 => self.v[x] |= self.v[y],
+                0x2 => self.v[x] &= self.v[y],
+                0x3 => self.v[x] ^= self.v[y],
+                0x4 => {
+                    let sum = self.v[x] as u16 + self.v[y] as u16;
+                    self.v[x] = sum as u8;
+                    self.v[0xF] = (sum > 0xFF) as u8;
+                }
+                0x5 => {
+                    let no_borrow = self.v[x] >= self.v[y];
+                    self.v[x] = self.v[x].wrapping_sub(self.v[y]);
+                    self.v[0xF] = no_borrow as u8;
+                }
+                0x6 => {
+                    let bit = self.v[x] & 1;
+                    self.v[x] >>= 1;
+                    self.v[0xF] = bit;
+                }
+                0x7 => {
+                    let no_borrow = self.v[y] >= self.v[x];
+                    self.v[x] = self.v[y].wrapping_sub(self.v[x]);
+                    self.v[0xF] = no_borrow as u8;
+                }
+                0xE => {
+                    let bit = self.v[x] >> 7;
+                    self.v[x] <<= 1;
+                    self.v[0xF] = bit;
+                }
+                _ => return Err(format!("unknown opcode {:#06X}", op)),
+            },
+            0x9 if self.v[x] != self.v[y] => next = skip,
+            0x9 => (),
+            0xA => self.i = nnn,
+            0xB => next = self.v[0] as u16 + nnn,
+            0xC => self.v[x] = self.rng.gen::<u8>() & nn,
+            0xD => {
+                let mut collision = false;
+                for row in 0..n as usize {
+                    let sprite = self.read(self.i as usize + row)?;
+                    for col in 0..8 {
+                        if sprite & (0x80 >> col) == 0 {
+                            continue;
+                        }
+                        let px = (self.v[x] as usize + col) % DISPLAY_WIDTH;
+                        let py = (self.v[y] as usize + row) % DISPLAY_HEIGHT;
+                        let pixel = &mut self.display[px + py * DISPLAY_WIDTH];
+                        collision |= *pixel;
+                        *pixel = !*pixel;
+                    }
+                }
+                self.v[0xF] = collision as u8;
+            }
+            0xE if nn == 0x9E && keys[(self.v[x] & 0xF) as usize] => next = skip,
+            0xE if nn == 0xA1 && !keys[(self.v[x] & 0xF) as usize] => next = skip,
+            0xE if nn == 0x9E || nn == 0xA1 => (),
+            0xF => match nn {
+                0x07 => self.v[x] = self.delay,
+                0x0A => match keys.iter().position(|&held| held) {
+                    Some(key) => self.v[x] = key as u8,
+                    None => next = self.pc,
+                },
+                0x15 => self.delay = self.v[x],
+                0x18 => self.sound = self.v[x],
+                0x1E => self.i = self.i.wrapping_add(self.v[x] as u16),
+                0x29 => self.i = self.v[x] as u16 * 5,
+                0x33 => {
+                    let i = self.i as usize;
+                    let digits = [self.v[x] / 100, self.v[x] / 10 % 10, self.v[x] % 10];
+                    let memory = self
+                        .memory
+                        .get_mut(i..i + 3)
+                        .ok_or_else(|| out_of_memory(i))?;
+                    memory.copy_from_slice(&digits);
+                }
+                0x55 => {
+                    let i = self.i as usize;
+                    let memory = self
+                        .memory
+                        .get_mut(i..=i + x)
+                        .ok_or_else(|| out_of_memory(i))?;
+                    memory.copy_from_slice(&self.v[..=x]);
+                }
+                0x65 => {
+                    let i = self.i as usize;
+                    let memory = self.memory.get(i..=i + x).ok_or_else(|| out_of_memory(i))?;
+                    self.v[..=x].copy_from_slice(memory);
+                }
+                _ => return Err(format!("unknown opcode {:#06X}", op)),
+            },
+            _ => return Err(format!("unknown opcode {:#06X}", op)),
+        }
+
+        self.pc = next;
+        Ok(())
+    }
+}
+
+fn out_of_memory(address: usize) -> String {
+    format!("memory access past the end at {:#05X}", address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steps through every instruction of `program`, or until one fails
+    fn run(program: &[u16]) -> Result<(), String> {
+        let rom: Vec<u8> = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let mut reference = Reference::new(&rom, 0);
+        for _ in 0..program.len() {
+            reference.step(&[false; 16])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn accesses_past_the_end_of_memory_are_errors() {
+        // Jumps to 0xFFF, whose second byte is past the end
+        assert!(run(&[0x6001, 0xBFFE, 0x0000]).is_err());
+        assert!(run(&[0xAFFE, 0xF255]).is_err());
+        assert!(run(&[0xAFFE, 0xF265]).is_err());
+        assert!(run(&[0xAFFE, 0xF033]).is_err());
+        assert!(run(&[0xAFFC, 0xD008]).is_err());
+
+        assert!(run(&[0xAFFD, 0xF255, 0xF265, 0xF033, 0xD003]).is_ok());
+    }
+}
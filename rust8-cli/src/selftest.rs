@@ -0,0 +1,198 @@
+use crate::assembler;
+use crate::audio::{AudioSink, OfflineAudio, SAMPLES_PER_FRAME};
+use crate::config::AudioConfig;
+use crate::display;
+use crate::hardware::Chip8;
+use crate::headless::{frame_buffer, handle_events, CYCLES_PER_TIMER_TICK};
+
+/// Draws the font, a seeded random number in decimal and the result of some
+/// arithmetic, so a broken build shows up as a different frame
+const PROGRAM: &str = "
+        CLS
+        LD V0, 0
+        LD V1, 0
+        LD V2, 0
+digits: LD F, V2
+        DRW V0, V1, 5
+        ADD V0, 5
+        ADD V2, 1
+        SE V2, 12
+        JP digits
+
+        RND V3, 0xFF
+        LD I, scratch
+        LD B, V3
+        LD V2, [I]
+        LD V4, 0
+        LD V5, 8
+        LD F, V0
+        DRW V4, V5, 5
+        ADD V4, 5
+        LD F, V1
+        DRW V4, V5, 5
+        ADD V4, 5
+        LD F, V2
+        DRW V4, V5, 5
+
+        LD V6, 0x3C
+        LD V7, 0x0F
+        XOR V6, V7
+        SUB V6, V7
+        SHL V6
+        LD V0, V6
+        LD I, scratch
+        LD [I], V0
+        LD V4, 20
+        DRW V4, V5, 1
+end:    JP end
+scratch: DB 0, 0, 0
+";
+
+/// Sounds the buzzer for 30 frames
+const TONE_PROGRAM: &str = "
+        LD V0, 30
+        LD ST, V0
+end:    JP end
+";
+const TONE_FRAMES: usize = 30;
+
+/// Plays a pattern of four samples high and four low for a second at pitch
+/// 112, which is 8000 samples per second
+const PATTERN_PROGRAM: &str = "
+        LD I, pattern
+        AUDIO
+        LD V0, 112
+        PITCH V0
+        LD V0, 60
+        LD ST, V0
+end:    JP end
+pattern:
+        DB 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0
+        DB 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0
+";
+/// Changes between high and low a second at 8000 samples per second
+const PATTERN_EDGES: f32 = 8000.0 / 4.0;
+
+const SEED: u64 = 8;
+const CYCLES: u64 = 2000;
+/// FNV-1a hash of the frame buffer a correct build ends up with
+const EXPECTED_HASH: u64 = 0x39CE_B2AD_27F6_A111;
+
+/// 64 bit FNV-1a, which is plenty to tell frames apart
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+/// Runs a program for a number of frames, rendering its sound offline
+fn render_audio(source: &str, frames: usize) -> Result<Vec<f32>, String> {
+    let rom = assembler::assemble(source).map_err(|e| e.to_string())?;
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&rom).map_err(|e| e.to_string())?;
+
+    // No fades, so the tone starts and stops on the frame
+    let mut audio = OfflineAudio::new(&AudioConfig {
+        volume: 1.0,
+        attack_ms: 0.0,
+        release_ms: 0.0,
+        low_pass_hz: None,
+        ..AudioConfig::default()
+    });
+    let key_states = [false; 16];
+
+    for _ in 0..frames {
+        for _ in 0..CYCLES_PER_TIMER_TICK {
+            chip8.step(&key_states).map_err(|e| e.to_string())?;
+            handle_events(&mut chip8);
+        }
+        audio.set_pattern(chip8.audio_pattern());
+        audio.set_active(chip8.is_sound_playing());
+        audio.render_frame();
+        chip8.update_timers();
+    }
+
+    Ok(audio.samples)
+}
+
+/// Checks the beep lasts as long as the sound timer says and that XO-CHIP
+/// patterns play at the pitch set
+fn check_audio() -> Result<(), String> {
+    let tone = render_audio(TONE_PROGRAM, TONE_FRAMES + 10)?;
+    let sounding = tone.iter().filter(|&&sample| sample != 0.0).count();
+    if sounding != TONE_FRAMES * SAMPLES_PER_FRAME {
+        return Err(format!(
+            "the beep lasted {} samples, expected {}",
+            sounding,
+            TONE_FRAMES * SAMPLES_PER_FRAME
+        ));
+    }
+
+    let pattern = render_audio(PATTERN_PROGRAM, 60)?;
+    let edges = pattern
+        .windows(2)
+        .filter(|pair| (pair[0] > 0.0) != (pair[1] > 0.0))
+        .count() as f32;
+    if (edges - PATTERN_EDGES).abs() > PATTERN_EDGES * 0.02 {
+        return Err(format!(
+            "the audio pattern changed level {} times in a second, expected {}",
+            edges, PATTERN_EDGES
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs the built in program for a fixed number of instructions with a
+/// fixed seed and checks the final frame against a known hash, then checks
+/// the audio rendered offline, needing no window, GPU or sound card.
+/// Returns the process exit code.
+pub fn run_selftest() -> i32 {
+    let rom = match assembler::assemble(PROGRAM) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("FAIL: the self test program does not assemble: {}", e);
+            return 1;
+        }
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(SEED);
+    if let Err(e) = chip8.load_rom(&rom) {
+        println!("FAIL: {}", e);
+        return 1;
+    }
+
+    let key_states = [false; 16];
+
+    for cycle in 0..CYCLES {
+        if let Err(e) = chip8.step(&key_states) {
+            println!("FAIL: {} after {} cycles", e, cycle);
+            return 1;
+        }
+        handle_events(&mut chip8);
+
+        if cycle % CYCLES_PER_TIMER_TICK == 0 {
+            chip8.update_timers();
+        }
+    }
+
+    let mut pixels = frame_buffer(&chip8);
+    display::present(&chip8, &mut pixels);
+    let hash = fnv1a(&pixels);
+    if hash != EXPECTED_HASH {
+        println!(
+            "FAIL: frame buffer hash {:016X}, expected {:016X}",
+            hash, EXPECTED_HASH
+        );
+        return 1;
+    }
+
+    if let Err(e) = check_audio() {
+        println!("FAIL: {}", e);
+        return 1;
+    }
+
+    println!("PASS, frame buffer hash {:016X}, audio as expected", hash);
+    0
+}
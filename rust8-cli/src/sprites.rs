@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::Path;
+
+use crate::demo::{self, Demo, DemoEvent, DemoPlayer};
+use crate::hardware::{Chip8, Instruction};
+use crate::headless::{handle_events, CYCLES_PER_TIMER_TICK};
+use crate::png;
+use crate::rom_file::read_rom;
+
+/// Sprites are always 8 pixels wide and at most 15 rows tall
+const SPRITE_WIDTH: usize = 8;
+const MAX_SPRITE_HEIGHT: usize = 15;
+/// Sprites per row of the sprite sheet
+const SHEET_COLUMNS: usize = 16;
+/// Space between sprites on the sheet
+const SHEET_GAP: usize = 1;
+
+/// Collects each distinct sprite drawn, in the order first seen
+#[derive(Default)]
+struct SpriteSheet {
+    sprites: Vec<Vec<u8>>,
+}
+
+impl SpriteSheet {
+    fn add(&mut self, sprite: &[u8]) {
+        if !self.sprites.iter().any(|seen| seen == sprite) {
+            self.sprites.push(sprite.to_vec());
+        }
+    }
+
+    /// Lays the sprites out in a grid, lit pixels white on black
+    fn to_png(&self) -> Vec<u8> {
+        let cell_width = SPRITE_WIDTH + SHEET_GAP;
+        let cell_height = MAX_SPRITE_HEIGHT + SHEET_GAP;
+        let columns = self.sprites.len().clamp(1, SHEET_COLUMNS);
+        let rows = self.sprites.len().div_ceil(columns);
+
+        let width = columns * cell_width - SHEET_GAP;
+        let height = (rows * cell_height).saturating_sub(SHEET_GAP).max(1);
+        let mut pixels = vec![0u8; width * height];
+
+        for (i, sprite) in self.sprites.iter().enumerate() {
+            let (left, top) = ((i % columns) * cell_width, (i / columns) * cell_height);
+
+            for (row, bits) in sprite.iter().enumerate() {
+                for col in 0..SPRITE_WIDTH {
+                    if bits & (0x80 >> col) != 0 {
+                        pixels[(left + col) + (top + row) * width] = 255;
+                    }
+                }
+            }
+        }
+
+        png::encode_grayscale(width as u32, height as u32, &pixels)
+    }
+}
+
+/// Runs a ROM headlessly for a number of frames, printing every sprite drawn
+/// and optionally saving the distinct sprites as a PNG sprite sheet. Inputs
+/// come from a demo if one is given, otherwise no keys are pressed.
+/// `frames` counts 60hz timer ticks.
+pub fn run_sprites(path: &Path, frames: u64, demo: Option<&Demo>, sheet: Option<&Path>) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let mut chip8 = Chip8::new();
+    if let Some(demo) = demo {
+        chip8.seed_rng(demo.seed);
+    }
+    if let Err(e) = chip8.load_rom(&rom) {
+        eprintln!("{}", e);
+        return 2;
+    }
+
+    let mut key_states = [false; 16];
+    let mut player = DemoPlayer::new();
+    let mut sprite_sheet = SpriteSheet::default();
+    let mut frame = 0;
+
+    for cycle in 0.. {
+        // Once the demo runs out the timers carry on at the usual rate
+        let replaying = demo.filter(|demo| !player.is_finished(demo));
+        match replaying {
+            Some(demo) => {
+                for (_, event) in player.events_until(demo, cycle) {
+                    match *event {
+                        DemoEvent::Keys(mask) => key_states = demo::key_states(mask),
+                        DemoEvent::TimerTick => {
+                            chip8.update_timers();
+                            frame += 1;
+                        }
+                    }
+                }
+            }
+            None if cycle % CYCLES_PER_TIMER_TICK == 0 && cycle != 0 => {
+                chip8.update_timers();
+                frame += 1;
+            }
+            None => (),
+        }
+
+        if frame >= frames {
+            break;
+        }
+
+        // The sprite is read before the draw executes, which is when the core reads it too
+        if let Instruction::Draw(register_x, register_y, height) =
+            Instruction::decode(chip8.opcode())
+        {
+            let registers = chip8.registers();
+            let (x, y) = (
+                registers[register_x as usize],
+                registers[register_y as usize],
+            );
+            let memory = chip8.memory();
+            let sprite: Vec<u8> = (0..height as usize)
+                .map(|row| memory[(chip8.i() as usize + row) % memory.len()])
+                .collect();
+
+            let bytes: Vec<String> = sprite.iter().map(|byte| format!("{:02X}", byte)).collect();
+            println!(
+                "frame {} pc {:#06X} i {:#06X} x {} y {} height {} sprite {}",
+                frame,
+                chip8.program_counter(),
+                chip8.i(),
+                x,
+                y,
+                height,
+                bytes.join(" ")
+            );
+            sprite_sheet.add(&sprite);
+        }
+
+        if let Err(e) = chip8.step(&key_states) {
+            eprintln!("Stopped after {} cycles: {}", cycle, e);
+            break;
+        }
+        handle_events(&mut chip8);
+    }
+
+    if let Some(sheet) = sheet {
+        if let Err(e) = fs::write(sheet, sprite_sheet.to_png()) {
+            eprintln!("Failed to write {}: {}", sheet.display(), e);
+            return 2;
+        }
+        eprintln!(
+            "Saved {} distinct sprites to {}",
+            sprite_sheet.sprites.len(),
+            sheet.display()
+        );
+    }
+
+    0
+}
@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use crate::hardware::Instruction;
+use crate::json::json_string;
+use crate::rom_file::read_rom;
+
+/// Address the ROM is loaded at and where execution starts
+const PROGRAM_START_ADDRESS: u16 = 0x200;
+
+enum Finding {
+    /// Not an instruction, which is expected for sprite data but fatal if executed
+    Unknown,
+    /// Decodes, but no interpreter this emulator targets can run it
+    Unsupported(&'static str),
+    /// Runs, but rarely means what the author intended
+    Suspicious(&'static str),
+}
+
+fn check(address: u16, instruction: Instruction, rom_end: u16) -> Option<Finding> {
+    let in_rom = |target: u16| (PROGRAM_START_ADDRESS..rom_end).contains(&target);
+
+    match instruction {
+        Instruction::NoOp(_) => Some(Finding::Unsupported("machine code routine, ignored")),
+        Instruction::ReportResult | Instruction::Assert => {
+            Some(Finding::Unsupported("test extension, ignored"))
+        }
+        Instruction::Jmp(target) | Instruction::Call(target) if !in_rom(target) => {
+            Some(Finding::Suspicious("jumps outside the ROM"))
+        }
+        Instruction::Jmp(target) | Instruction::Call(target) if target % 2 != 0 => {
+            Some(Finding::Suspicious("jumps to an odd address"))
+        }
+        Instruction::Call(target) if target == address => {
+            Some(Finding::Suspicious("calls itself forever"))
+        }
+        _ => None,
+    }
+}
+
+impl Finding {
+    fn kind(&self) -> &'static str {
+        match self {
+            Finding::Unknown => "unknown",
+            Finding::Unsupported(_) => "unsupported",
+            Finding::Suspicious(_) => "suspicious",
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Finding::Unknown => "unknown opcode",
+            Finding::Unsupported(reason) | Finding::Suspicious(reason) => reason,
+        }
+    }
+}
+
+/// Decodes every aligned word of a ROM without running it, listing anything
+/// that would stop it from running, as JSON if `json` is set. Returns the
+/// process exit code, nonzero if the ROM cannot run at all.
+pub fn run_verify(path: &Path, json: bool) -> i32 {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if rom.len() < 2 {
+        if json {
+            println!("{{\"words\": 0, \"findings\": [], \"runnable\": false}}");
+        } else {
+            println!("{} is too short to hold an instruction", path.display());
+        }
+        return 1;
+    }
+
+    let rom_end = PROGRAM_START_ADDRESS + rom.len() as u16;
+    let mut findings = vec![];
+
+    for (offset, word) in rom.chunks_exact(2).enumerate() {
+        let address = PROGRAM_START_ADDRESS + 2 * offset as u16;
+        let opcode = (word[0] as u16) << 8 | word[1] as u16;
+
+        let finding = match Instruction::decode(opcode) {
+            Instruction::Unknown(_) => Some(Finding::Unknown),
+            instruction => check(address, instruction, rom_end),
+        };
+        if let Some(finding) = finding {
+            findings.push((address, opcode, finding));
+        }
+    }
+
+    let count = |kind: &str| {
+        findings
+            .iter()
+            .filter(|(_, _, finding)| finding.kind() == kind)
+            .count()
+    };
+    let first = (rom[0] as u16) << 8 | rom[1] as u16;
+    let runnable = !matches!(Instruction::decode(first), Instruction::Unknown(_));
+
+    if json {
+        let entries: Vec<String> = findings
+            .iter()
+            .map(|(address, opcode, finding)| {
+                format!(
+                    "    {{\"address\": {}, \"opcode\": {}, \"kind\": \"{}\", \"reason\": {}}}",
+                    address,
+                    opcode,
+                    finding.kind(),
+                    json_string(finding.reason())
+                )
+            })
+            .collect();
+        println!(
+            "{{\n  \"words\": {},\n  \"unknown\": {},\n  \"unsupported\": {},\n  \"suspicious\": {},\n  \"runnable\": {},\n  \"findings\": [\n{}\n  ]\n}}",
+            rom.len() / 2,
+            count("unknown"),
+            count("unsupported"),
+            count("suspicious"),
+            runnable,
+            entries.join(",\n")
+        );
+    } else {
+        for (address, opcode, finding) in &findings {
+            let description = match finding {
+                Finding::Unknown => finding.reason().to_string(),
+                _ => format!("{}, {}", finding.kind(), finding.reason()),
+            };
+            println!("{:#05X}: {:#06X} {}", address, opcode, description);
+        }
+
+        println!(
+            "{} words: {} unknown, {} unsupported, {} suspicious",
+            rom.len() / 2,
+            count("unknown"),
+            count("unsupported"),
+            count("suspicious")
+        );
+        if !runnable {
+            println!("The first instruction cannot run, so neither can the ROM");
+        }
+    }
+
+    if runnable {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,349 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+use super::instruction_decoder::{Address, Instruction, Register};
+
+impl fmt::Display for Instruction {
+    /// Formats the instruction in conventional assembly syntax, e.g. `LD V1, 0x20`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = self.mnemonic();
+
+        match *self {
+            Instruction::Clear
+            | Instruction::Ret
+            | Instruction::ReportResult
+            | Instruction::Assert
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::LowRes
+            | Instruction::HighRes
+            | Instruction::LoadAudio
+            | Instruction::CycleBackground
+            | Instruction::MegaOff
+            | Instruction::MegaOn
+            | Instruction::StopSound => write!(f, "{}", mnemonic),
+            Instruction::ScrollDown(rows) | Instruction::ScrollUp(rows) => {
+                write!(f, "{} {}", mnemonic, rows)
+            }
+            Instruction::SelectPlanes(planes) => write!(f, "{} {}", mnemonic, planes),
+            Instruction::PlaySound(n)
+            | Instruction::SetBlendMode(n)
+            | Instruction::MegaScrollUp(n) => {
+                write!(f, "{} {}", mnemonic, n)
+            }
+            Instruction::SetIHuge(value)
+            | Instruction::LoadPalette(value)
+            | Instruction::SetSpriteWidth(value)
+            | Instruction::SetSpriteHeight(value)
+            | Instruction::SetScreenAlpha(value)
+            | Instruction::SetCollisionColor(value) => write!(f, "{} {:#04X}", mnemonic, value),
+            Instruction::SetILong => write!(f, "{} I, LONG", mnemonic),
+            Instruction::NoOp(address) | Instruction::Call(address) | Instruction::Jmp(address) => {
+                write!(f, "{} {:#05X}", mnemonic, address)
+            }
+            Instruction::SetI(address) => write!(f, "{} I, {:#05X}", mnemonic, address),
+            Instruction::JmpOffset(address) => write!(f, "{} V0, {:#05X}", mnemonic, address),
+            Instruction::RegEqVal(x, value)
+            | Instruction::RegNeqVal(x, value)
+            | Instruction::SetRegVal(x, value)
+            | Instruction::AddRegVal(x, value)
+            | Instruction::SetRegRand(x, value) => {
+                write!(f, "{} V{:X}, {:#04X}", mnemonic, x, value)
+            }
+            Instruction::RegEqReg(x, y)
+            | Instruction::SetRegReg(x, y)
+            | Instruction::SetRegOrReg(x, y)
+            | Instruction::SetRegAndReg(x, y)
+            | Instruction::SetRegXorReg(x, y)
+            | Instruction::AddRegReg(x, y)
+            | Instruction::SubRegReg(x, y)
+            | Instruction::ShiftRegRight(x, y)
+            | Instruction::RevRegSubReg(x, y)
+            | Instruction::ShiftRegLeft(x, y)
+            | Instruction::RegNeqReg(x, y)
+            | Instruction::SaveRange(x, y)
+            | Instruction::LoadRange(x, y)
+            | Instruction::AddNibbles(x, y) => write!(f, "{} V{:X}, V{:X}", mnemonic, x, y),
+            Instruction::Draw(x, y, height) => {
+                write!(f, "{} V{:X}, V{:X}, {}", mnemonic, x, y, height)
+            }
+            Instruction::KeyUp(x)
+            | Instruction::KeyDown(x)
+            | Instruction::SetPitch(x)
+            | Instruction::SecondKeyDown(x)
+            | Instruction::SecondKeyUp(x)
+            | Instruction::OutputPort(x)
+            | Instruction::InputPort(x) => write!(f, "{} V{:X}", mnemonic, x),
+            Instruction::SetRegDelay(x) => write!(f, "{} V{:X}, DT", mnemonic, x),
+            Instruction::SetRegKey(x) => write!(f, "{} V{:X}, K", mnemonic, x),
+            Instruction::SetDelayReg(x) => write!(f, "{} DT, V{:X}", mnemonic, x),
+            Instruction::SetSoundReg(x) => write!(f, "{} ST, V{:X}", mnemonic, x),
+            Instruction::AddIReg(x) => write!(f, "{} I, V{:X}", mnemonic, x),
+            Instruction::SetISpriteReg(x) => write!(f, "{} F, V{:X}", mnemonic, x),
+            Instruction::Bcd(x) => write!(f, "{} B, V{:X}", mnemonic, x),
+            Instruction::Dump(x) => write!(f, "{} [I], V{:X}", mnemonic, x),
+            Instruction::Load(x) => write!(f, "{} V{:X}, [I]", mnemonic, x),
+            Instruction::SetIBigSpriteReg(x) => write!(f, "{} HF, V{:X}", mnemonic, x),
+            Instruction::StoreFlags(x) => write!(f, "{} R, V{:X}", mnemonic, x),
+            Instruction::LoadFlags(x) => write!(f, "{} V{:X}, R", mnemonic, x),
+            Instruction::Unknown(opcode) => {
+                write!(
+                    f,
+                    "{} {:#04X}, {:#04X}",
+                    mnemonic,
+                    opcode >> 8,
+                    opcode & 0xFF
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseInstructionError {
+    message: String,
+}
+
+impl ParseInstructionError {
+    fn new(message: impl Into<String>) -> Self {
+        ParseInstructionError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseInstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ParseInstructionError {}
+
+/// A single parsed operand of an assembly instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Register(Register),
+    Immediate(u16),
+    I,
+    IndirectI,
+    DelayTimer,
+    SoundTimer,
+    Key,
+    Font,
+    BigFont,
+    Bcd,
+    Flags,
+    /// The 16 bit address in the word after `LD I, LONG`
+    Long,
+}
+
+impl FromStr for Operand {
+    type Err = ParseInstructionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+
+        match upper.as_str() {
+            "I" => return Ok(Operand::I),
+            "[I]" => return Ok(Operand::IndirectI),
+            "DT" => return Ok(Operand::DelayTimer),
+            "ST" => return Ok(Operand::SoundTimer),
+            "K" => return Ok(Operand::Key),
+            "F" => return Ok(Operand::Font),
+            "HF" => return Ok(Operand::BigFont),
+            "B" => return Ok(Operand::Bcd),
+            "R" => return Ok(Operand::Flags),
+            "LONG" => return Ok(Operand::Long),
+            _ => (),
+        }
+
+        if let Some(register) = upper.strip_prefix('V') {
+            return match u8::from_str_radix(register, 16) {
+                Ok(index) if register.len() == 1 => Ok(Operand::Register(index)),
+                _ => Err(ParseInstructionError::new(format!(
+                    "Invalid register '{}'",
+                    s
+                ))),
+            };
+        }
+
+        parse_number(&upper)
+            .map(Operand::Immediate)
+            .ok_or_else(|| ParseInstructionError::new(format!("Invalid operand '{}'", s)))
+    }
+}
+
+/// Parses an uppercased number written in hex (`0X1F`, `#1F`, `$1F`), binary
+/// (`0B101`) or decimal
+pub fn parse_number(s: &str) -> Option<u16> {
+    let (digits, radix) = if let Some(hex) = s.strip_prefix("0X") {
+        (hex, 16)
+    } else if let Some(hex) = s.strip_prefix('#').or_else(|| s.strip_prefix('$')) {
+        (hex, 16)
+    } else if let Some(binary) = s.strip_prefix("0B") {
+        (binary, 2)
+    } else {
+        (s, 10)
+    };
+
+    u16::from_str_radix(digits, radix).ok()
+}
+
+fn address(value: u16) -> Result<Address, ParseInstructionError> {
+    if value <= 0xFFF {
+        Ok(value)
+    } else {
+        Err(ParseInstructionError::new(format!(
+            "Address {:#X} does not fit in 12 bits",
+            value
+        )))
+    }
+}
+
+fn byte(value: u16) -> Result<u8, ParseInstructionError> {
+    if value <= 0xFF {
+        Ok(value as u8)
+    } else {
+        Err(ParseInstructionError::new(format!(
+            "Value {:#X} does not fit in 8 bits",
+            value
+        )))
+    }
+}
+
+fn nibble(value: u16) -> Result<u8, ParseInstructionError> {
+    if value <= 0xF {
+        Ok(value as u8)
+    } else {
+        Err(ParseInstructionError::new(format!(
+            "Value {:#X} does not fit in 4 bits",
+            value
+        )))
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseInstructionError;
+
+    /// Parses conventional assembly syntax such as `LD V1, 0x20` or `SE V1, V2`,
+    /// the inverse of the `Display` implementation
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (mnemonic, rest) = match s.find(char::is_whitespace) {
+            Some(index) => (&s[..index], s[index..].trim()),
+            None => (s, ""),
+        };
+
+        let operands = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',')
+                .map(|operand| operand.trim().parse())
+                .collect::<Result<Vec<Operand>, _>>()?
+        };
+
+        use Operand::*;
+
+        let instruction = match (mnemonic.to_ascii_uppercase().as_str(), operands.as_slice()) {
+            ("CLS", []) => Instruction::Clear,
+            ("RET", []) => Instruction::Ret,
+            ("REPORT", []) => Instruction::ReportResult,
+            // The condition follows as its own `SE VX, NN`
+            ("ASSERT", []) => Instruction::Assert,
+            ("SCD", [Immediate(n)]) => Instruction::ScrollDown(nibble(*n)?),
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::LowRes,
+            ("HIGH", []) => Instruction::HighRes,
+            ("SCU", [Immediate(n)]) => Instruction::ScrollUp(nibble(*n)?),
+            ("SAVE", [Register(x), Register(y)]) => Instruction::SaveRange(*x, *y),
+            ("LOAD", [Register(x), Register(y)]) => Instruction::LoadRange(*x, *y),
+            ("PLANE", [Immediate(n)]) => Instruction::SelectPlanes(nibble(*n)?),
+            ("AUDIO", []) => Instruction::LoadAudio,
+            ("PITCH", [Register(x)]) => Instruction::SetPitch(*x),
+            ("BGC", []) => Instruction::CycleBackground,
+            ("ADDN", [Register(x), Register(y)]) => Instruction::AddNibbles(*x, *y),
+            ("SKP2", [Register(x)]) => Instruction::SecondKeyDown(*x),
+            ("SKNP2", [Register(x)]) => Instruction::SecondKeyUp(*x),
+            ("OUT", [Register(x)]) => Instruction::OutputPort(*x),
+            ("IN", [Register(x)]) => Instruction::InputPort(*x),
+            ("MEGAOFF", []) => Instruction::MegaOff,
+            ("MEGAON", []) => Instruction::MegaOn,
+            // The low 16 bits of the address follow as data
+            ("LDHI", [Immediate(nn)]) => Instruction::SetIHuge(byte(*nn)?),
+            ("LDPAL", [Immediate(nn)]) => Instruction::LoadPalette(byte(*nn)?),
+            ("SPRW", [Immediate(nn)]) => Instruction::SetSpriteWidth(byte(*nn)?),
+            ("SPRH", [Immediate(nn)]) => Instruction::SetSpriteHeight(byte(*nn)?),
+            ("ALPHA", [Immediate(nn)]) => Instruction::SetScreenAlpha(byte(*nn)?),
+            ("DIGISND", [Immediate(n)]) => Instruction::PlaySound(nibble(*n)?),
+            ("STOPSND", []) => Instruction::StopSound,
+            ("BMODE", [Immediate(n)]) => Instruction::SetBlendMode(nibble(*n)?),
+            ("CCOL", [Immediate(nn)]) => Instruction::SetCollisionColor(byte(*nn)?),
+            ("SCRU", [Immediate(n)]) => Instruction::MegaScrollUp(nibble(*n)?),
+            // CHIP-8X reuses BNNN for coloring, so it disassembles as a jump
+            ("COL", [Register(x), Register(y), Immediate(n)]) => {
+                Instruction::JmpOffset((*x as u16) << 8 | (*y as u16) << 4 | nibble(*n)? as u16)
+            }
+            ("LD", [I, Long]) => Instruction::SetILong,
+            ("SYS", [Immediate(nnn)]) => Instruction::NoOp(address(*nnn)?),
+            ("JP", [Immediate(nnn)]) => Instruction::Jmp(address(*nnn)?),
+            ("JP", [Register(0), Immediate(nnn)]) => Instruction::JmpOffset(address(*nnn)?),
+            ("CALL", [Immediate(nnn)]) => Instruction::Call(address(*nnn)?),
+            ("SE", [Register(x), Immediate(nn)]) => Instruction::RegEqVal(*x, byte(*nn)?),
+            ("SE", [Register(x), Register(y)]) => Instruction::RegEqReg(*x, *y),
+            ("SNE", [Register(x), Immediate(nn)]) => Instruction::RegNeqVal(*x, byte(*nn)?),
+            ("SNE", [Register(x), Register(y)]) => Instruction::RegNeqReg(*x, *y),
+            ("LD", [Register(x), Immediate(nn)]) => Instruction::SetRegVal(*x, byte(*nn)?),
+            ("LD", [Register(x), Register(y)]) => Instruction::SetRegReg(*x, *y),
+            ("LD", [I, Immediate(nnn)]) => Instruction::SetI(address(*nnn)?),
+            ("LD", [Register(x), DelayTimer]) => Instruction::SetRegDelay(*x),
+            ("LD", [Register(x), Key]) => Instruction::SetRegKey(*x),
+            ("LD", [DelayTimer, Register(x)]) => Instruction::SetDelayReg(*x),
+            ("LD", [SoundTimer, Register(x)]) => Instruction::SetSoundReg(*x),
+            ("LD", [Font, Register(x)]) => Instruction::SetISpriteReg(*x),
+            ("LD", [Bcd, Register(x)]) => Instruction::Bcd(*x),
+            ("LD", [IndirectI, Register(x)]) => Instruction::Dump(*x),
+            ("LD", [Register(x), IndirectI]) => Instruction::Load(*x),
+            ("LD", [BigFont, Register(x)]) => Instruction::SetIBigSpriteReg(*x),
+            ("LD", [Flags, Register(x)]) => Instruction::StoreFlags(*x),
+            ("LD", [Register(x), Flags]) => Instruction::LoadFlags(*x),
+            ("ADD", [Register(x), Immediate(nn)]) => Instruction::AddRegVal(*x, byte(*nn)?),
+            ("ADD", [Register(x), Register(y)]) => Instruction::AddRegReg(*x, *y),
+            ("ADD", [I, Register(x)]) => Instruction::AddIReg(*x),
+            ("OR", [Register(x), Register(y)]) => Instruction::SetRegOrReg(*x, *y),
+            ("AND", [Register(x), Register(y)]) => Instruction::SetRegAndReg(*x, *y),
+            ("XOR", [Register(x), Register(y)]) => Instruction::SetRegXorReg(*x, *y),
+            ("SUB", [Register(x), Register(y)]) => Instruction::SubRegReg(*x, *y),
+            ("SUBN", [Register(x), Register(y)]) => Instruction::RevRegSubReg(*x, *y),
+            ("SHR", [Register(x)]) => Instruction::ShiftRegRight(*x, *x),
+            ("SHR", [Register(x), Register(y)]) => Instruction::ShiftRegRight(*x, *y),
+            ("SHL", [Register(x)]) => Instruction::ShiftRegLeft(*x, *x),
+            ("SHL", [Register(x), Register(y)]) => Instruction::ShiftRegLeft(*x, *y),
+            ("RND", [Register(x), Immediate(nn)]) => Instruction::SetRegRand(*x, byte(*nn)?),
+            ("DRW", [Register(x), Register(y), Immediate(n)]) => {
+                Instruction::Draw(*x, *y, nibble(*n)?)
+            }
+            ("SKP", [Register(x)]) => Instruction::KeyDown(*x),
+            ("SKNP", [Register(x)]) => Instruction::KeyUp(*x),
+            ("DB", [Immediate(high), Immediate(low)]) => {
+                Instruction::decode((byte(*high)? as u16) << 8 | byte(*low)? as u16)
+            }
+            _ => {
+                return Err(ParseInstructionError::new(format!(
+                    "Unrecognised instruction '{}'",
+                    s
+                )))
+            }
+        };
+
+        Ok(instruction)
+    }
+}
@@ -0,0 +1,2120 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::chip8x::Chip8xColors;
+use super::display::Planes;
+use super::font::{Font, FONT_SIZE};
+use super::host_page::{HostPage, HostTime, HOST_PAGE_SIZE};
+use super::instruction_decoder::Instruction;
+use super::megachip::{
+    DigitisedSound, MegaChip, MEGACHIP_HEIGHT, MEGACHIP_MEMORY_SIZE, MEGACHIP_WIDTH,
+};
+use super::quirks::Quirks;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{self, Debug};
+use core::mem;
+use core::ops::Range;
+
+#[derive(Clone)]
+pub struct Chip8 {
+    program_counter: u16,
+    registers: [u8; REGISTER_COUNT],
+    stack: Vec<u16>,
+    /// Levels of nesting before `2NNN` overflows the stack
+    stack_size: usize,
+    i: u16,
+    /// `DEFAULT_MEMORY_SIZE` unless `set_memory_size` changes it, e.g. to
+    /// the 64K XO-CHIP or 16M MEGA-CHIP expect
+    memory: Vec<u8>,
+    sound_timer: u8,
+    delay_timer: u8,
+    test_extensions: bool,
+    test_result: Option<TestResult>,
+    rng: StdRng,
+    waiting_for_key: bool,
+    /// Set once `00FD` has run
+    exited: bool,
+    /// Keys held while `FX0A` waits, one bit per key, which only answer it
+    /// once released and pressed again. Kept here rather than in the
+    /// frontend so a save state taken mid wait resumes it as it was.
+    key_wait: Option<u16>,
+    /// The key that answers the waiting `FX0A` once released, unless the
+    /// `key_wait_on_press` quirk answers it on the press
+    key_wait_pressed: Option<u8>,
+    /// Set by each 60hz tick and cleared by `DXYN`, for the `display_wait` quirk
+    vblank: bool,
+    screen_width: u16,
+    screen_height: u16,
+    /// What is drawn, converted into the frontend's frame buffer by `present`
+    display: Planes,
+    events: VecDeque<Chip8Event>,
+    display_changed: bool,
+    quirks: Quirks,
+    cycles_per_frame: u32,
+    strict: Option<Strict>,
+    /// SUPER-CHIP RPL user flags, saved and restored by `FX75` and `FX85`
+    flags: [u8; FLAG_COUNT],
+    /// XO-CHIP bit planes selected by `FN01`, one bit per plane
+    planes: u8,
+    audio: Option<AudioPattern>,
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    memory_access_policy: MemoryAccessPolicy,
+    /// Where `load_rom` puts the ROM and execution starts
+    program_start: usize,
+    /// Where `load_rom` put the ROM, empty before it is loaded
+    rom: Range<usize>,
+    /// Where the small font starts, with the large font straight after it
+    font_address: usize,
+    /// The standard display size, 64x48 on the ETI-660
+    lores_size: (u16, u16),
+    /// The CHIP-8X color map, `None` unless CHIP-8X is enabled
+    colors: Option<Chip8xColors>,
+    /// `None` unless MEGA-CHIP is enabled
+    megachip: Option<MegaChip>,
+    /// `None` unless the host page extension is enabled
+    host_page: Option<HostPage>,
+    /// Set by `load_rom` for VIP two-page hi-res ROMs, which draw to a
+    /// 64x64 display and clear it with `0230`
+    two_page: bool,
+    /// Addresses of unknown opcodes already reported, so a loop over one
+    /// doesn't report it forever
+    reported_unknown: BTreeSet<u16>,
+    /// Every diagnostic reported so far, for debugging. The addresses of
+    /// their instructions keep each from being reported twice.
+    diagnostics: Vec<Diagnostic>,
+    diagnosed: BTreeSet<u16>,
+}
+
+/// What `step` does on a word that isn't a valid instruction, which happens
+/// when a buggy ROM runs into its data
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownOpcodePolicy {
+    /// Carry on with the next instruction
+    Skip,
+    /// Stay on the opcode, so `is_halted` reports the program as finished
+    Halt,
+    /// Return `Chip8Error::UnknownOpcode`
+    #[default]
+    Error,
+}
+
+/// What happens when an instruction reading or writing memory relative to
+/// `I` runs past the end of memory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryAccessPolicy {
+    /// Carry on from the start of memory
+    Wrap,
+    /// Stay on the last byte of memory
+    Saturate,
+    /// Return `Chip8Error::MemoryOutOfBounds`
+    #[default]
+    Fault,
+}
+
+/// What memory outside the fonts holds at power on. Real hardware rarely
+/// came up zeroed, so ROMs relying on it break there.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryInit {
+    /// Every byte 0x00
+    #[default]
+    Zero,
+    /// Every byte 0xFF
+    Ones,
+    /// Bytes from a seeded generator, so a run can be repeated
+    Random,
+}
+
+/// The XO-CHIP audio pattern, played instead of the plain beep while the
+/// sound timer runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioPattern {
+    /// 128 one bit samples, most significant bit first
+    pub pattern: [u8; AUDIO_PATTERN_SIZE],
+    /// Set by `FX3A`, 64 plays the pattern at 4000 samples per second
+    pub pitch: u8,
+}
+
+impl AudioPattern {
+    /// Samples played per second, doubling every 48 steps of pitch
+    #[cfg(feature = "std")]
+    pub fn playback_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+}
+
+impl Default for AudioPattern {
+    /// A square wave, for ROMs that set the pitch without loading a pattern
+    fn default() -> Self {
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        for byte in pattern.iter_mut().step_by(2) {
+            *byte = 0xFF;
+        }
+        AudioPattern { pattern, pitch: 64 }
+    }
+}
+
+/// Memory bookkeeping for strict mode, see `Chip8::set_strict`
+#[derive(Clone)]
+struct Strict {
+    written: Vec<bool>,
+    /// Addresses already reported, so a loop doesn't report the same one forever
+    reported: Vec<bool>,
+    /// Whether the stack has already been reported outgrowing its region
+    stack_reported: bool,
+}
+
+/// Notifications from the core that the frontend needs to react to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Event {
+    /// The display switched resolution, so frontends should resize whatever
+    /// they show it in to `width * height` pixels
+    ResolutionChanged { width: u16, height: u16 },
+    /// Strict mode caught the ROM misusing memory
+    StrictViolation(StrictViolation),
+    /// An unknown opcode was skipped or halted on, as the `UnknownOpcodePolicy`
+    /// asked. Reported once per address.
+    UnknownOpcode { address: u16, opcode: u16 },
+    /// `FX75` saved registers to the RPL user flags, which frontends can
+    /// keep on disk so the ROM finds them there next time
+    FlagsStored,
+    /// `00FD` ended the program. The machine stays on the instruction, so
+    /// frontends can show that it finished or move on to another ROM.
+    /// Reported the first time only.
+    Exited,
+    /// The ROM did something that works but is probably a mistake. Meant
+    /// for ROM authors, so frontends should log it rather than show players.
+    Diagnostic(Diagnostic),
+}
+
+/// Something odd but harmless a ROM did, reported once per instruction
+/// address with that address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// `DXYN` started a sprite off the display, so it only shows up at all
+    /// because the coordinates wrap around
+    SpriteOffScreen { pc: u16, x: u8, y: u8 },
+    /// `FX33` wrote its digits over the fonts
+    BcdIntoFont { pc: u16, address: u16 },
+    /// `FX29` was asked for a digit past F, pointing I past the small font,
+    /// or `FX30` for one past the large font's SUPER-CHIP digits
+    FontDigitOutOfRange { pc: u16, digit: u8 },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::SpriteOffScreen { pc, x, y } => write!(
+                f,
+                "{:#06X}: sprite drawn off screen at ({}, {}), wrapped back on",
+                pc, x, y
+            ),
+            Diagnostic::BcdIntoFont { pc, address } => write!(
+                f,
+                "{:#06X}: BCD written over the fonts at {:#06X}",
+                pc, address
+            ),
+            Diagnostic::FontDigitOutOfRange { pc, digit } => write!(
+                f,
+                "{:#06X}: font character {:#04X} asked for, past the font's last digit",
+                pc, digit
+            ),
+        }
+    }
+}
+
+/// Memory misuse reported in strict mode, with the address of the offending instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictViolation {
+    /// Memory was read before anything was written to it
+    UninitializedRead { pc: u16, address: u16 },
+    /// Memory was written in the region the original interpreter kept its
+    /// stack, variables and display buffer
+    ReservedWrite { pc: u16, address: u16 },
+    /// Subroutines nested deeper than the original interpreter's stack
+    /// region holds, which would have overwritten its variables
+    StackOverflow { pc: u16, depth: usize },
+    /// `00EE` was executed with nothing on the stack to return to
+    StackUnderflow { pc: u16 },
+}
+
+impl fmt::Display for StrictViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StrictViolation::UninitializedRead { pc, address } => write!(
+                f,
+                "{:#06X}: read of uninitialized memory at {:#06X}",
+                pc, address
+            ),
+            StrictViolation::ReservedWrite { pc, address } => write!(
+                f,
+                "{:#06X}: write to reserved memory at {:#06X}",
+                pc, address
+            ),
+            StrictViolation::StackOverflow { pc, depth } => write!(
+                f,
+                "{:#06X}: subroutines nested {} deep, past the {} the stack region holds",
+                pc, depth, STRICT_STACK_DEPTH
+            ),
+            StrictViolation::StackUnderflow { pc } => {
+                write!(f, "{:#06X}: return with an empty stack", pc)
+            }
+        }
+    }
+}
+
+/// Outcome reported by a test ROM through the `00FA` extension opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestResult {
+    Pass,
+    /// The ROM reported a failure, carrying the nonzero code it left in V0
+    Fail(u8),
+    /// The `00F9` at `address` found VX holding `actual` rather than `expected`
+    AssertionFailed {
+        address: u16,
+        register: u8,
+        expected: u8,
+        actual: u8,
+    },
+}
+
+/// A fault that stops the ROM from running any further. The program counter
+/// is left pointing at the offending instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode {
+        address: u16,
+        opcode: u16,
+    },
+    /// `00EE` was executed with nothing on the stack to return to
+    StackUnderflow {
+        address: u16,
+    },
+    /// `2NNN` was executed with every level of the stack in use
+    StackOverflow {
+        address: u16,
+    },
+    /// An instruction relative to `I` reached `target`, past the end of
+    /// memory, under `MemoryAccessPolicy::Fault`
+    MemoryOutOfBounds {
+        address: u16,
+        target: usize,
+    },
+    /// `load_rom` was given more bytes than fit in memory after the
+    /// program start address
+    RomTooLarge {
+        size: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { address, opcode } => {
+                write!(f, "Unknown opcode {:#06X} at {:#05X}", opcode, address)
+            }
+            Chip8Error::StackUnderflow { address } => {
+                write!(f, "Stack underflow at {:#05X}", address)
+            }
+            Chip8Error::StackOverflow { address } => {
+                write!(f, "Stack overflow at {:#05X}", address)
+            }
+            Chip8Error::MemoryOutOfBounds { address, target } => write!(
+                f,
+                "Memory access at {:#X} runs past the end of memory at {:#05X}",
+                target, address
+            ),
+            Chip8Error::RomTooLarge { size, available } => write!(
+                f,
+                "ROM is {} bytes, larger than the {} bytes available",
+                size, available
+            ),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+const PROGRAM_START_ADDRESS: usize = 0x200;
+/// The jump two-page hi-res ROMs start with, over the interpreter patch
+/// they carry
+const TWO_PAGE_SIGNATURE: [u8; 2] = [0x12, 0x60];
+/// Where the game itself starts in a two-page hi-res ROM, past the patch
+const TWO_PAGE_PROGRAM_START_ADDRESS: u16 = 0x2C0;
+const TWO_PAGE_SCREEN_HEIGHT: u16 = 64;
+/// The patched interpreter's clear screen, since its `00E0` only cleared
+/// one page. It calls the patch's machine code, so no other ROM uses it.
+const TWO_PAGE_CLEAR: u16 = 0x0230;
+const REGISTER_COUNT: usize = 16;
+/// Levels of subroutine nesting, as on the SUPER-CHIP
+pub const DEFAULT_STACK_SIZE: usize = 16;
+/// The 4K of memory most interpreters had
+pub const DEFAULT_MEMORY_SIZE: usize = 0x1000;
+/// XO-CHIP extends memory to the full 16 bit address space
+pub const XOCHIP_MEMORY_SIZE: usize = 0x10000;
+/// Largest ROM that fits between the program start address and the end of
+/// XO-CHIP's memory
+pub const MAX_ROM_SIZE: usize = XOCHIP_MEMORY_SIZE - PROGRAM_START_ADDRESS;
+/// Largest ROM that fits with MEGA-CHIP's larger memory
+pub const MEGACHIP_MAX_ROM_SIZE: usize = MEGACHIP_MEMORY_SIZE - PROGRAM_START_ADDRESS;
+/// Start of the memory the COSMAC VIP interpreter used for its stack,
+/// variables and display buffer, which ROMs had no business writing to
+const RESERVED_START: usize = 0xEA0;
+const RESERVED_END: usize = 0x1000;
+/// Return addresses the original interpreter's stack region, the start of
+/// the reserved region, has room for
+const STRICT_STACK_DEPTH: usize = 12;
+/// What strict mode fills unwritten memory with, an invalid opcode if executed
+const POISON: u8 = 0xFF;
+/// Resolution of the original low resolution display
+const SCREEN_WIDTH: u16 = 64;
+const SCREEN_HEIGHT: u16 = 32;
+const SPRITE_WIDTH: u16 = 8;
+const HIRES_SCREEN_WIDTH: u16 = 128;
+const HIRES_SCREEN_HEIGHT: u16 = 64;
+/// Width and height of the sprites drawn by `DXY0`
+const LARGE_SPRITE_SIZE: u16 = 16;
+/// Pixels moved by the SUPER-CHIP horizontal scrolls
+const SCROLL_DISTANCE: usize = 4;
+const FLAG_COUNT: usize = 8;
+const AUDIO_PATTERN_SIZE: usize = 16;
+
+/// Instructions per 60hz frame, roughly the 500hz most ROMs are written for
+pub const DEFAULT_CYCLES_PER_FRAME: u32 = 8;
+
+/// Number of instructions shown either side of the program counter when debugging
+const DISASSEMBLY_CONTEXT: usize = 3;
+
+/// The planes drawn to until a ROM selects others with `FN01`
+const DEFAULT_PLANES: u8 = 0b01;
+
+/// The SUPER-CHIP 8x10 font, stored in memory straight after the small font
+const LARGE_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+/// Digits of the large font SUPER-CHIP had, A to F only coming later
+const LARGE_FONT_DIGITS: u8 = 10;
+/// Bytes both fonts take together
+const FONTS_SIZE: usize = FONT_SIZE + LARGE_FONT.len();
+
+impl Chip8 {
+    pub fn new() -> Self {
+        Chip8::with_font(Font::default().bytes())
+    }
+
+    /// A machine with `font` in place of the usual small font, either one of
+    /// the bundled `Font`s or a user's own
+    pub fn with_font(font: &[u8; FONT_SIZE]) -> Self {
+        let mut memory = vec![0u8; DEFAULT_MEMORY_SIZE];
+        memory[0..FONT_SIZE].copy_from_slice(font);
+        memory[FONT_SIZE..FONTS_SIZE].copy_from_slice(&LARGE_FONT);
+
+        Chip8 {
+            program_counter: 0x200,
+            registers: [0; REGISTER_COUNT],
+            stack: vec![],
+            stack_size: DEFAULT_STACK_SIZE,
+            i: 0,
+            memory,
+            sound_timer: 0,
+            delay_timer: 0,
+            test_extensions: false,
+            test_result: None,
+            rng: new_rng(),
+            waiting_for_key: false,
+            exited: false,
+            key_wait: None,
+            key_wait_pressed: None,
+            vblank: false,
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            display: Planes::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            events: VecDeque::new(),
+            display_changed: true,
+            quirks: Quirks::default(),
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            strict: None,
+            flags: [0; FLAG_COUNT],
+            planes: DEFAULT_PLANES,
+            audio: None,
+            unknown_opcode_policy: UnknownOpcodePolicy::default(),
+            memory_access_policy: MemoryAccessPolicy::default(),
+            program_start: PROGRAM_START_ADDRESS,
+            rom: 0..0,
+            font_address: 0,
+            lores_size: (SCREEN_WIDTH, SCREEN_HEIGHT),
+            colors: None,
+            megachip: None,
+            host_page: None,
+            two_page: false,
+            reported_unknown: BTreeSet::new(),
+            diagnostics: vec![],
+            diagnosed: BTreeSet::new(),
+        }
+    }
+
+    /// Current display resolution as `(width, height)` in pixels
+    pub fn display_size(&self) -> (u16, u16) {
+        (self.screen_width, self.screen_height)
+    }
+
+    /// What the core holds for the pixel at `(x, y)`: a palette index from 0
+    /// to 3 with a bit from each XO-CHIP plane, or 0 off the display.
+    /// Frontends turn these into colors in whatever pixel format they show
+    /// them in. MEGA-CHIP's color mode has its own, see `color_frame`.
+    pub fn pixel(&self, x: u16, y: u16) -> u8 {
+        self.display.pixel(x, y)
+    }
+
+    /// The color of each pixel MEGA-CHIP's color mode last showed with
+    /// `00E0`, row by row, or `None` outside that mode
+    pub fn color_frame(&self) -> Option<&[[u8; 3]]> {
+        match &self.megachip {
+            Some(megachip) if megachip.active => Some(megachip.front()),
+            _ => None,
+        }
+    }
+
+    /// Whether the display was drawn to since the last call, so frontends only
+    /// need to present frames that actually changed
+    pub fn take_display_changed(&mut self) -> bool {
+        mem::take(&mut self.display_changed)
+    }
+
+    /// Takes the next pending event, if any. Frontends should drain these
+    /// after every step.
+    pub fn poll_event(&mut self) -> Option<Chip8Event> {
+        self.events.pop_front()
+    }
+
+    fn set_resolution(&mut self, width: u16, height: u16) {
+        if (width, height) != self.display_size() {
+            self.screen_width = width;
+            self.screen_height = height;
+            self.display = Planes::new(width, height);
+            self.events
+                .push_back(Chip8Event::ResolutionChanged { width, height });
+            self.display_changed = true;
+        }
+    }
+
+    /// The original interpreter clobbered VF as a side effect of the logical ops
+    fn reset_flag_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.set_register(0xF, 0);
+        }
+    }
+
+    /// The register shifted by `8XY6`/`8XYE`, which was VY on the original interpreter
+    fn shift_source(&self, register_x: u8, register_y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            register_y
+        } else {
+            register_x
+        }
+    }
+
+    /// Sets how deep subroutines can nest before `2NNN` returns
+    /// `Chip8Error::StackOverflow`, 16 by default as on most interpreters
+    pub fn set_stack_size(&mut self, size: usize) {
+        self.stack_size = size;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Sets how many instructions `run_frame` executes, which controls the
+    /// emulation speed independently of the host's timing
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles;
+    }
+
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame
+    }
+
+    /// Runs one 60hz frame: `cycles_per_frame` instructions followed by a
+    /// timer tick. Stops executing early if the display resolution changes,
+    /// so the caller can resize its frame buffer before the next frame.
+    pub fn run_frame(&mut self, key_states: &[bool; 16]) -> Result<(), Chip8Error> {
+        self.run_frame_with(key_states, |_| ())
+    }
+
+    /// Like `run_frame`, showing the machine to `before_step` before each
+    /// instruction executes, e.g. to trace or count instructions
+    pub fn run_frame_with(
+        &mut self,
+        key_states: &[bool; 16],
+        mut before_step: impl FnMut(&Chip8),
+    ) -> Result<(), Chip8Error> {
+        let display_size = self.display_size();
+
+        for _ in 0..self.cycles_per_frame {
+            before_step(self);
+            self.step(key_states)?;
+
+            if self.display_size() != display_size {
+                break;
+            }
+        }
+
+        self.update_timers();
+        Ok(())
+    }
+
+    /// Strict mode poisons memory that hasn't been written and reports
+    /// reads of it, writes to the reserved region at the top of memory and
+    /// misuse of the stack as `StrictViolation` events. Enable it before
+    /// loading the ROM.
+    pub fn set_strict(&mut self, enabled: bool) {
+        if !enabled {
+            self.strict = None;
+            return;
+        }
+
+        let mut strict = Strict {
+            written: vec![false; self.memory.len()],
+            reported: vec![false; self.memory.len()],
+            stack_reported: false,
+        };
+        strict.written[self.fonts()].fill(true);
+
+        for (byte, written) in self.memory.iter_mut().zip(strict.written.iter()) {
+            if !written {
+                *byte = POISON;
+            }
+        }
+        self.strict = Some(strict);
+    }
+
+    /// In strict mode, reports the stack growing past its region in the
+    /// original interpreter, once
+    fn check_stack_depth(&mut self) {
+        let (pc, depth) = (self.program_counter, self.stack.len());
+        if let Some(strict) = &mut self.strict {
+            if depth > STRICT_STACK_DEPTH && !strict.stack_reported {
+                strict.stack_reported = true;
+                self.events.push_back(Chip8Event::StrictViolation(
+                    StrictViolation::StackOverflow { pc, depth },
+                ));
+            }
+        }
+    }
+
+    /// In strict mode, reports reads of memory nothing has written to
+    fn check_read(&mut self, address: usize, length: usize) {
+        let pc = self.program_counter;
+        if let Some(strict) = &mut self.strict {
+            for address in address..(address + length).min(strict.written.len()) {
+                if !strict.written[address] && !strict.reported[address] {
+                    strict.reported[address] = true;
+                    self.events.push_back(Chip8Event::StrictViolation(
+                        StrictViolation::UninitializedRead {
+                            pc,
+                            address: address as u16,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    /// In strict mode, notes that memory was written and reports writes to
+    /// the reserved region
+    fn check_write(&mut self, address: usize, length: usize) {
+        let pc = self.program_counter;
+        if let Some(strict) = &mut self.strict {
+            for address in address..(address + length).min(strict.written.len()) {
+                strict.written[address] = true;
+                let reserved = (RESERVED_START..RESERVED_END).contains(&address);
+                if reserved && !strict.reported[address] {
+                    strict.reported[address] = true;
+                    self.events.push_back(Chip8Event::StrictViolation(
+                        StrictViolation::ReservedWrite {
+                            pc,
+                            address: address as u16,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    pub fn set_memory_access_policy(&mut self, policy: MemoryAccessPolicy) {
+        self.memory_access_policy = policy;
+    }
+
+    /// Sets where `load_rom` puts the ROM and execution starts, which is
+    /// later than 0x200 on machines with a larger interpreter. Set it before
+    /// loading the ROM.
+    pub fn set_program_start(&mut self, address: u16) {
+        self.program_start = address as usize;
+        self.program_counter = address;
+    }
+
+    /// Sets the size of the standard display, which `00FE` also returns to
+    pub fn set_lores_size(&mut self, width: u16, height: u16) {
+        self.lores_size = (width, height);
+        self.set_resolution(width, height);
+    }
+
+    /// Sets how many bytes of memory the machine has, 4K by default and
+    /// never less than the fonts and the program start take. Set it after
+    /// the program start and before loading the ROM. In strict mode memory
+    /// it adds is poisoned like the rest.
+    pub fn set_memory_size(&mut self, size: usize) {
+        let minimum = self.fonts().end.max(self.program_start);
+        let size = size.max(minimum);
+
+        match &mut self.strict {
+            Some(strict) => {
+                self.memory.resize(size, POISON);
+                strict.written.resize(size, false);
+                strict.reported.resize(size, false);
+            }
+            None => self.memory.resize(size, 0),
+        }
+    }
+
+    /// Moves the fonts so the small font starts at `address`, such as the
+    /// 0x50 some interpreters use, for ROMs and tools that expect the glyphs
+    /// there. `FX29` and `FX30` point I into the fonts wherever they are.
+    /// Set it before filling memory, enabling strict mode and loading the ROM.
+    pub fn set_font_address(&mut self, address: u16) {
+        let address = (address as usize).min(self.memory.len() - FONTS_SIZE);
+        let old = self.fonts();
+        let fonts = self.memory[old.clone()].to_vec();
+        self.memory[old].fill(0);
+        self.font_address = address;
+        let new = self.fonts();
+        self.memory[new].copy_from_slice(&fonts);
+    }
+
+    /// Where the small font starts, with the large font straight after it
+    pub fn font_address(&self) -> u16 {
+        self.font_address as u16
+    }
+
+    /// The memory both fonts take
+    fn fonts(&self) -> Range<usize> {
+        self.font_address..self.font_address + FONTS_SIZE
+    }
+
+    /// Enables the CHIP-8X instructions and color map
+    pub fn set_chip8x(&mut self, enabled: bool) {
+        self.colors = if enabled {
+            Some(Chip8xColors::default())
+        } else {
+            None
+        };
+    }
+
+    /// The colors to show the display in, if CHIP-8X is enabled
+    pub fn chip8x_colors(&self) -> Option<&Chip8xColors> {
+        self.colors.as_ref()
+    }
+
+    /// Enables the MEGA-CHIP instructions, which ROMs switch to full color
+    /// mode with. They expect the 16M of memory `Platform::MegaChip` sets.
+    pub fn set_megachip(&mut self, enabled: bool) {
+        self.megachip = if enabled {
+            Some(MegaChip::default())
+        } else {
+            None
+        };
+    }
+
+    /// Fills memory outside the fonts with a power on pattern, `seed`
+    /// choosing the bytes of `MemoryInit::Random`. Call it before loading the
+    /// ROM and after `set_megachip`, which resizes memory.
+    pub fn set_memory_init(&mut self, init: MemoryInit, seed: u64) {
+        let fonts = self.fonts();
+        let (before, rest) = self.memory.split_at_mut(fonts.start);
+        let after = &mut rest[FONTS_SIZE..];
+        match init {
+            MemoryInit::Zero => {
+                before.fill(0);
+                after.fill(0);
+            }
+            MemoryInit::Ones => {
+                before.fill(0xFF);
+                after.fill(0xFF);
+            }
+            MemoryInit::Random => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                rng.fill(before);
+                rng.fill(after);
+            }
+        }
+    }
+
+    /// Whether the display is in MEGA-CHIP's full color mode, which
+    /// frontends shouldn't recolor
+    pub fn draws_own_colors(&self) -> bool {
+        self.megachip
+            .as_ref()
+            .is_some_and(|megachip| megachip.active)
+    }
+
+    /// The MEGA-CHIP digitised sound to play, if one is playing
+    pub fn digitised_sound(&self) -> Option<&DigitisedSound> {
+        self.megachip.as_ref()?.sound.as_ref()
+    }
+
+    /// Reseeds the generator behind `CXNN` so runs can be reproduced exactly
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Enables the `00FA` opcode, which test ROMs use to report their result,
+    /// and `00F9 3XNN`, which fails the test unless VX holds NN. When
+    /// disabled they are treated as machine code routines and ignored.
+    pub fn set_test_extensions(&mut self, enabled: bool) {
+        self.test_extensions = enabled;
+    }
+
+    /// Enables the host page at `address`, or disables it with `None`. The
+    /// page maps the host's clock, a frame counter, a random byte and the
+    /// last key pressed into memory, refreshed by `update_host_page`. Call
+    /// it after `load_rom`: a page that would overwrite the ROM or the fonts,
+    /// or that doesn't fit in memory, is left disabled and `false` returned.
+    pub fn set_host_page(&mut self, address: Option<u16>) -> bool {
+        self.host_page = None;
+        let address = match address {
+            Some(address) => address,
+            None => return true,
+        };
+
+        let page = address as usize..address as usize + HOST_PAGE_SIZE;
+        let overlaps = |other: &Range<usize>| page.start < other.end && other.start < page.end;
+        if page.end > self.memory.len() || overlaps(&self.rom) || overlaps(&self.fonts()) {
+            return false;
+        }
+        self.host_page = Some(HostPage::new(address));
+        true
+    }
+
+    /// Writes this frame's values to the host page, if it is enabled.
+    /// Frontends call it once per frame.
+    pub fn update_host_page(&mut self, time: HostTime, key_states: &[bool; 16]) {
+        let page = match &mut self.host_page {
+            Some(page) => page,
+            None => return,
+        };
+        let bytes = page.next_frame(time, key_states, self.rng.gen());
+
+        let start = page.address as usize;
+        let range = start..start + bytes.len();
+        self.memory[range.clone()].copy_from_slice(&bytes);
+        if let Some(strict) = &mut self.strict {
+            strict.written[range].fill(true);
+        }
+    }
+
+    /// The most recent result reported by the ROM, if any
+    pub fn test_result(&self) -> Option<TestResult> {
+        self.test_result
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// The opcode at the program counter, which the next `step` will execute
+    pub fn opcode(&self) -> u16 {
+        self.get_opcode()
+    }
+
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT] {
+        &self.registers
+    }
+
+    /// The SUPER-CHIP RPL user flags
+    pub fn flags(&self) -> &[u8] {
+        &self.flags
+    }
+
+    /// Restores RPL user flags kept from an earlier run, ignoring any past
+    /// the number the machine has
+    pub fn set_flags(&mut self, flags: &[u8]) {
+        let count = flags.len().min(FLAG_COUNT);
+        self.flags[..count].copy_from_slice(&flags[..count]);
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Overwrites a byte of memory, wrapping addresses past the end
+    pub fn write_memory(&mut self, address: u16, value: u8) {
+        let address = address as usize % self.memory.len();
+        self.memory[address] = value;
+
+        if let Some(strict) = &mut self.strict {
+            strict.written[address] = true;
+        }
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Whether the last step was blocked in `FX0A`, so nothing will change
+    /// until a key is pressed
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key
+    }
+
+    /// Whether the program has finished, which classic ROMs signal by jumping
+    /// to the jump instruction itself and SUPER-CHIP ROMs with `00FD`
+    pub fn is_halted(&self) -> bool {
+        match Instruction::decode(self.get_opcode()) {
+            Instruction::Jmp(address) => address == self.program_counter,
+            Instruction::Exit => true,
+            Instruction::Unknown(_) => self.unknown_opcode_policy == UnknownOpcodePolicy::Halt,
+            _ => false,
+        }
+    }
+
+    /// Whether the program ended itself with `00FD`, as opposed to just
+    /// looping forever like `is_halted` also counts
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Whether either timer is still counting down
+    pub fn timers_active(&self) -> bool {
+        self.delay_timer != 0 || self.sound_timer != 0
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let start = self.program_start;
+        let available = self.memory.len().saturating_sub(start);
+        if rom.len() > available || start > self.memory.len() {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom.len(),
+                available,
+            });
+        }
+
+        self.memory[start..(start + rom.len())].copy_from_slice(rom);
+        self.rom = start..start + rom.len();
+
+        // The patch is 1802 machine code, so run the game past it on a
+        // display twice as tall as it would set up
+        self.two_page =
+            start == PROGRAM_START_ADDRESS && self.megachip.is_none() && is_two_page(rom);
+        if self.two_page {
+            self.program_counter = TWO_PAGE_PROGRAM_START_ADDRESS;
+            self.set_resolution(SCREEN_WIDTH, TWO_PAGE_SCREEN_HEIGHT);
+        }
+
+        if let Some(strict) = &mut self.strict {
+            strict.written[start..(start + rom.len())].fill(true);
+        }
+        Ok(())
+    }
+
+    pub fn update_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        self.vblank = true;
+    }
+
+    /// Whether the buzzer should be sounding, which it does until the sound timer expires
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_timer != 0
+    }
+
+    /// The XO-CHIP audio pattern to play, or `None` for the plain beep if
+    /// the ROM never loaded one
+    pub fn audio_pattern(&self) -> Option<AudioPattern> {
+        self.audio
+    }
+
+    /// Executes one instruction
+    pub fn step(&mut self, key_states: &[bool; 16]) -> Result<(), Chip8Error> {
+        let opcode = self.get_opcode();
+        let address = self.program_counter;
+        self.waiting_for_key = false;
+        self.check_read(address as usize, 2);
+
+        let instruction = match Instruction::decode(opcode) {
+            Instruction::LoadPalette(_) if self.two_page && opcode == TWO_PAGE_CLEAR => {
+                Instruction::Clear
+            }
+            // 02A0 is one of MEGA-CHIP's palette loads
+            Instruction::CycleBackground if self.megachip.is_some() => {
+                Instruction::LoadPalette(0xA0)
+            }
+            // Other interpreters have no color board, so treat the CHIP-8X
+            // additions as what they would have been there
+            Instruction::CycleBackground if self.colors.is_none() => Instruction::NoOp(opcode),
+            Instruction::AddNibbles(..)
+            | Instruction::SecondKeyDown(_)
+            | Instruction::SecondKeyUp(_)
+            | Instruction::OutputPort(_)
+            | Instruction::InputPort(_)
+                if self.colors.is_none() =>
+            {
+                Instruction::Unknown(opcode)
+            }
+            // The MEGA-CHIP additions are all machine code routines elsewhere
+            Instruction::MegaOff
+            | Instruction::MegaOn
+            | Instruction::SetIHuge(_)
+            | Instruction::LoadPalette(_)
+            | Instruction::SetSpriteWidth(_)
+            | Instruction::SetSpriteHeight(_)
+            | Instruction::SetScreenAlpha(_)
+            | Instruction::PlaySound(_)
+            | Instruction::StopSound
+            | Instruction::SetBlendMode(_)
+            | Instruction::SetCollisionColor(_)
+            | Instruction::MegaScrollUp(_)
+                if self.megachip.is_none() =>
+            {
+                Instruction::NoOp(opcode)
+            }
+            instruction => instruction,
+        };
+
+        match instruction {
+            Instruction::Unknown(opcode) => {
+                if self.unknown_opcode_policy == UnknownOpcodePolicy::Error {
+                    return Err(Chip8Error::UnknownOpcode { address, opcode });
+                }
+                if self.reported_unknown.insert(address) {
+                    self.events
+                        .push_back(Chip8Event::UnknownOpcode { address, opcode });
+                }
+                if self.unknown_opcode_policy == UnknownOpcodePolicy::Halt {
+                    self.program_counter = self.program_counter.wrapping_sub(2);
+                }
+            }
+            Instruction::NoOp(_) => (),
+            Instruction::ReportResult => {
+                if self.test_extensions {
+                    self.test_result = Some(match self.get_register(0) {
+                        0 => TestResult::Pass,
+                        code => TestResult::Fail(code),
+                    });
+                }
+            }
+            Instruction::Assert => {
+                if self.test_extensions {
+                    self.check_assertion(address)?;
+                }
+            }
+            Instruction::Clear if self.draws_own_colors() => {
+                if let Some(megachip) = &mut self.megachip {
+                    megachip.present();
+                }
+                self.display_changed = true;
+            }
+            Instruction::Clear => {
+                self.display.clear(self.planes);
+                self.display_changed = true;
+            }
+            Instruction::ScrollDown(rows) => self.scroll(0, rows as isize),
+            Instruction::ScrollUp(rows) => self.scroll(0, -(rows as isize)),
+            Instruction::ScrollRight => self.scroll(SCROLL_DISTANCE as isize, 0),
+            Instruction::ScrollLeft => self.scroll(-(SCROLL_DISTANCE as isize), 0),
+            // Stays on the instruction, so the program never moves past it
+            Instruction::Exit => {
+                if !mem::replace(&mut self.exited, true) {
+                    self.events.push_back(Chip8Event::Exited);
+                }
+                self.program_counter = self.program_counter.wrapping_sub(2);
+            }
+            Instruction::LowRes => self.set_resolution(self.lores_size.0, self.lores_size.1),
+            Instruction::HighRes => self.set_resolution(HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT),
+            Instruction::Ret => {
+                if self.stack.is_empty() && self.strict.is_some() {
+                    self.events.push_back(Chip8Event::StrictViolation(
+                        StrictViolation::StackUnderflow { pc: address },
+                    ));
+                }
+                let return_address = self
+                    .stack
+                    .pop()
+                    .ok_or(Chip8Error::StackUnderflow { address })?;
+                self.program_counter = return_address.wrapping_sub(2);
+            }
+            Instruction::Jmp(address) => self.program_counter = address.wrapping_sub(2),
+            Instruction::Call(address) => {
+                if self.stack.len() >= self.stack_size {
+                    return Err(Chip8Error::StackOverflow {
+                        address: self.program_counter,
+                    });
+                }
+                self.stack.push(self.program_counter.wrapping_add(2));
+                self.check_stack_depth();
+                self.program_counter = address.wrapping_sub(2);
+            }
+            Instruction::RegEqVal(register, value) => {
+                if self.get_register(register) == value {
+                    self.skip();
+                }
+            }
+            Instruction::RegNeqVal(register, value) => {
+                if self.get_register(register) != value {
+                    self.skip();
+                }
+            }
+            Instruction::RegEqReg(register_x, register_y) => {
+                if self.get_register(register_x) == self.get_register(register_y) {
+                    self.skip();
+                }
+            }
+            Instruction::SetRegVal(register, value) => self.set_register(register, value),
+            Instruction::AddRegVal(register, value) => {
+                self.set_register(register, self.get_register(register).wrapping_add(value))
+            }
+            Instruction::SetRegReg(register_x, register_y) => {
+                self.set_register(register_x, self.get_register(register_y))
+            }
+            Instruction::SetRegOrReg(register_x, register_y) => {
+                self.set_register(
+                    register_x,
+                    self.get_register(register_x) | self.get_register(register_y),
+                );
+                self.reset_flag_after_logic();
+            }
+            Instruction::SetRegAndReg(register_x, register_y) => {
+                self.set_register(
+                    register_x,
+                    self.get_register(register_x) & self.get_register(register_y),
+                );
+                self.reset_flag_after_logic();
+            }
+            Instruction::SetRegXorReg(register_x, register_y) => {
+                self.set_register(
+                    register_x,
+                    self.get_register(register_x) ^ self.get_register(register_y),
+                );
+                self.reset_flag_after_logic();
+            }
+            Instruction::AddRegReg(register_x, register_y) => {
+                let x = self.get_register(register_x);
+                let y = self.get_register(register_y);
+
+                let (new_x, is_carry) = x.overflowing_add(y);
+
+                self.set_register(register_x, new_x);
+                self.set_register(0xF, if is_carry { 1 } else { 0 });
+            }
+            Instruction::SubRegReg(register_x, register_y) => {
+                let x = self.get_register(register_x);
+                let y = self.get_register(register_y);
+
+                let (new_x, is_borrow) = x.overflowing_sub(y);
+
+                self.set_register(register_x, new_x);
+                self.set_register(0xF, if is_borrow { 0 } else { 1 });
+            }
+            Instruction::ShiftRegRight(register_x, register_y) => {
+                let value = self.get_register(self.shift_source(register_x, register_y));
+                self.set_register(register_x, value >> 1);
+                self.set_register(0xF, value & 1);
+            }
+            Instruction::RevRegSubReg(register_x, register_y) => {
+                let x = self.get_register(register_x);
+                let y = self.get_register(register_y);
+
+                let (new_x, is_borrow) = y.overflowing_sub(x);
+
+                self.set_register(register_x, new_x);
+                self.set_register(0xF, if is_borrow { 0 } else { 1 });
+            }
+            Instruction::ShiftRegLeft(register_x, register_y) => {
+                let value = self.get_register(self.shift_source(register_x, register_y));
+                self.set_register(register_x, value << 1);
+                self.set_register(0xF, (value & 0x80) >> 7);
+            }
+            Instruction::RegNeqReg(register_x, register_y) => {
+                if self.get_register(register_x) != self.get_register(register_y) {
+                    self.skip();
+                }
+            }
+            Instruction::SetI(address) => self.set_i(address),
+            Instruction::SetRegRand(register, value) => {
+                let random = self.rng.gen::<u8>();
+                self.set_register(register, random & value);
+            }
+            Instruction::JmpOffset(address) if self.colors.is_some() => {
+                // CHIP-8X has no BNNN, the color board took its place
+                let register_x = (address >> 8) as u8;
+                let register_y = ((address >> 4) & 0xF) as u8;
+                let x = self.get_register(register_x);
+                let next = self.get_register((register_x + 1) & 0xF);
+                let color = self.get_register(register_y);
+
+                if let Some(colors) = &mut self.colors {
+                    match (address & 0xF) as u8 {
+                        0 => colors.set_zones(x, next, color),
+                        height => colors.set_rows(x, next, height, color),
+                    }
+                }
+                self.display_changed = true;
+            }
+            Instruction::JmpOffset(address) => {
+                // BXNN reads the offset from VX, where X is the top nibble of the address
+                let register = if self.quirks.jump_uses_vx {
+                    (address >> 8) as u8
+                } else {
+                    0
+                };
+                self.program_counter =
+                    (self.get_register(register) as u16 + address).wrapping_sub(2)
+            }
+            Instruction::Draw(register_x, register_y, sprite_height) if self.draws_own_colors() => {
+                self.draw_megachip(register_x, register_y, sprite_height)?
+            }
+            Instruction::Draw(register_x, register_y, sprite_height) => {
+                // Stay on the instruction until the next tick, as the COSMAC
+                // VIP interpreter waited for the display interrupt
+                if self.quirks.display_wait && !self.vblank {
+                    return Ok(());
+                }
+                self.vblank = false;
+
+                let (x, y) = (self.get_register(register_x), self.get_register(register_y));
+                if x as u16 >= self.screen_width || y as u16 >= self.screen_height {
+                    let pc = self.program_counter;
+                    self.diagnose(Diagnostic::SpriteOffScreen { pc, x, y });
+                }
+
+                let origin_x = x as u16 % self.screen_width;
+                let origin_y = y as u16 % self.screen_height;
+
+                // A height of zero draws a 16x16 sprite, two bytes per row,
+                // or an 8x16 one in low resolution with the quirk
+                let hires = self.screen_width == HIRES_SCREEN_WIDTH;
+                let (width, height) = match sprite_height {
+                    0 if !hires && self.quirks.lores_tall_sprites => {
+                        (SPRITE_WIDTH, LARGE_SPRITE_SIZE)
+                    }
+                    0 => (LARGE_SPRITE_SIZE, LARGE_SPRITE_SIZE),
+                    height => (SPRITE_WIDTH, height as u16),
+                };
+                let bytes_per_row = width / 8;
+                let sprite_size = height * bytes_per_row;
+
+                // With several planes selected, the sprite for each plane
+                // follows the one for the plane before it
+                let selected = self.planes;
+                let planes = (0..2).filter(|plane| selected & (1 << plane) != 0);
+                let mut collision = false;
+
+                for (sprite, plane) in planes.enumerate() {
+                    let sprite_start = sprite * sprite_size as usize;
+                    self.check_read(self.i_address() + sprite_start, sprite_size as usize);
+
+                    for row in 0..height {
+                        let mut bits = 0;
+                        for byte in 0..bytes_per_row {
+                            let offset = sprite_start + (row * bytes_per_row + byte) as usize;
+                            let byte_bits = self.read_byte(self.i_address(), offset)? as u128;
+                            bits |= byte_bits << (120 - 8 * byte);
+                        }
+
+                        let y = origin_y + row;
+                        if y >= self.screen_height && self.quirks.clip_vertical {
+                            continue;
+                        }
+                        collision |= self.display.draw_row(
+                            plane,
+                            origin_x,
+                            y % self.screen_height,
+                            bits,
+                            self.quirks.clip_horizontal,
+                        );
+                    }
+                }
+
+                self.registers[0xF] = if collision { 1 } else { 0 };
+                self.display_changed = true;
+            }
+            Instruction::KeyDown(register) => {
+                if key_states[(self.get_register(register) & 0xF) as usize] {
+                    self.skip();
+                }
+            }
+            Instruction::KeyUp(register) => {
+                if !key_states[(self.get_register(register) & 0xF) as usize] {
+                    self.skip();
+                }
+            }
+            Instruction::SetRegDelay(register) => {
+                self.set_register(register, self.delay_timer);
+            }
+            Instruction::SetRegKey(register) => {
+                let held = key_states
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &key)| key)
+                    .fold(0u16, |mask, (key, _)| mask | 1 << key);
+                // Only a key pressed since the wait began answers it, so a key
+                // still down from the last FX0A isn't read twice
+                let ignored = *self.key_wait.get_or_insert(held);
+                let pressed = held & !ignored;
+                if self.key_wait_pressed.is_none() && pressed != 0 {
+                    self.key_wait_pressed = Some(pressed.trailing_zeros() as u8);
+                }
+
+                let answer = match self.key_wait_pressed {
+                    Some(key) if self.quirks.key_wait_on_press || held & 1 << key == 0 => Some(key),
+                    _ => None,
+                };
+                if let Some(key) = answer {
+                    self.set_register(register, key);
+                    self.key_wait = None;
+                    self.key_wait_pressed = None;
+                } else {
+                    self.key_wait = Some(ignored & held);
+                    self.waiting_for_key = true;
+                    self.program_counter = self.program_counter.wrapping_sub(2);
+                }
+            }
+            Instruction::SetDelayReg(register) => {
+                self.delay_timer = self.get_register(register);
+            }
+            Instruction::SetSoundReg(register) => {
+                self.sound_timer = self.get_register(register);
+            }
+            Instruction::AddIReg(register) => {
+                let (old_i, value) = (self.i, self.get_register(register) as u16);
+                self.i = old_i.wrapping_add(value);
+                if self.quirks.add_i_sets_vf {
+                    // From the sum itself, which a 16-bit I can wrap past
+                    let overflow = old_i as u32 + value as u32 > 0x0FFF;
+                    self.registers[0xF] = overflow as u8;
+                }
+            }
+            Instruction::SetISpriteReg(register) => {
+                let mut digit = self.get_register(register);
+                if self.quirks.font_digit_masked {
+                    digit &= 0xF;
+                } else if digit > 0xF {
+                    let pc = self.program_counter;
+                    self.diagnose(Diagnostic::FontDigitOutOfRange { pc, digit });
+                }
+                self.set_i(self.font_address as u16 + 5 * digit as u16);
+            }
+            Instruction::SetIBigSpriteReg(register) => {
+                let mut digit = self.get_register(register);
+                if self.quirks.font_digit_masked {
+                    digit &= 0xF;
+                } else if digit >= LARGE_FONT_DIGITS {
+                    let pc = self.program_counter;
+                    self.diagnose(Diagnostic::FontDigitOutOfRange { pc, digit });
+                }
+                self.set_i((self.font_address + FONT_SIZE + 10 * digit as usize) as u16);
+            }
+            Instruction::StoreFlags(register) => {
+                let count = (register as usize + 1).min(FLAG_COUNT);
+                self.flags[..count].copy_from_slice(&self.registers[..count]);
+                self.events.push_back(Chip8Event::FlagsStored);
+            }
+            Instruction::LoadFlags(register) => {
+                let count = (register as usize + 1).min(FLAG_COUNT);
+                self.registers[..count].copy_from_slice(&self.flags[..count]);
+            }
+            Instruction::SaveRange(register_x, register_y) => {
+                let registers = register_range(register_x, register_y);
+                self.check_write(self.i_address(), registers.len());
+                for (offset, register) in registers.enumerate() {
+                    self.write_byte(self.i_address(), offset, self.get_register(register))?;
+                }
+            }
+            Instruction::LoadRange(register_x, register_y) => {
+                let registers = register_range(register_x, register_y);
+                self.check_read(self.i_address(), registers.len());
+                for (offset, register) in registers.enumerate() {
+                    let value = self.read_byte(self.i_address(), offset)?;
+                    self.set_register(register, value);
+                }
+            }
+            Instruction::SelectPlanes(planes) => self.planes = planes & 0b11,
+            Instruction::SetILong => {
+                let address = self.program_counter.wrapping_add(2);
+                self.check_read(address as usize, 2);
+                self.set_i(self.read_word(address));
+                self.program_counter = address;
+            }
+            Instruction::LoadAudio => {
+                self.check_read(self.i_address(), AUDIO_PATTERN_SIZE);
+
+                let mut audio = self.audio.unwrap_or_default();
+                for (offset, byte) in audio.pattern.iter_mut().enumerate() {
+                    *byte = self.read_byte(self.i_address(), offset)?;
+                }
+                self.audio = Some(audio);
+            }
+            Instruction::SetPitch(register) => {
+                let mut audio = self.audio.unwrap_or_default();
+                audio.pitch = self.get_register(register);
+                self.audio = Some(audio);
+            }
+            Instruction::CycleBackground => {
+                if let Some(colors) = &mut self.colors {
+                    colors.cycle_background();
+                }
+                self.display_changed = true;
+            }
+            Instruction::AddNibbles(register_x, register_y) => {
+                let x = self.get_register(register_x);
+                let y = self.get_register(register_y);
+                let high = ((x >> 4) + (y >> 4)) & 0x7;
+                let low = ((x & 0xF) + (y & 0xF)) & 0x7;
+                self.set_register(register_x, high << 4 | low);
+            }
+            // There is only the one keypad, so the second reads the same keys
+            Instruction::SecondKeyDown(register) => {
+                if key_states[(self.get_register(register) & 0xF) as usize] {
+                    self.skip();
+                }
+            }
+            Instruction::SecondKeyUp(register) => {
+                if !key_states[(self.get_register(register) & 0xF) as usize] {
+                    self.skip();
+                }
+            }
+            // Nothing is plugged into the expansion port, so output goes
+            // nowhere and input reads as zero
+            Instruction::OutputPort(_) => (),
+            Instruction::InputPort(register) => self.set_register(register, 0),
+            Instruction::MegaScrollUp(rows) => self.scroll(0, -(rows as isize)),
+            Instruction::MegaOff
+            | Instruction::MegaOn
+            | Instruction::SetIHuge(_)
+            | Instruction::LoadPalette(_)
+            | Instruction::SetSpriteWidth(_)
+            | Instruction::SetSpriteHeight(_)
+            | Instruction::SetScreenAlpha(_)
+            | Instruction::PlaySound(_)
+            | Instruction::StopSound
+            | Instruction::SetBlendMode(_)
+            | Instruction::SetCollisionColor(_) => self.execute_megachip(instruction)?,
+            Instruction::Bcd(register) => {
+                if self.fonts().contains(&self.i_address()) {
+                    let (pc, address) = (self.program_counter, self.i);
+                    self.diagnose(Diagnostic::BcdIntoFont { pc, address });
+                }
+                self.check_write(self.i_address(), 3);
+                let mut value = self.get_register(register);
+                self.write_byte(self.i_address(), 2, value % 10)?;
+                value /= 10;
+                self.write_byte(self.i_address(), 1, value % 10)?;
+                value /= 10;
+                self.write_byte(self.i_address(), 0, value % 10)?;
+            }
+            Instruction::Dump(register) => {
+                self.check_write(self.i_address(), register as usize + 1);
+                for offset in 0..=register as usize {
+                    self.write_byte(self.i_address(), offset, self.registers[offset])?;
+                }
+
+                if self.quirks.memory_increment_i {
+                    self.i = self.i.wrapping_add(register as u16 + 1);
+                }
+            }
+            Instruction::Load(register) => {
+                self.check_read(self.i_address(), register as usize + 1);
+                for offset in 0..=register as usize {
+                    self.registers[offset] = self.read_byte(self.i_address(), offset)?;
+                }
+
+                if self.quirks.memory_increment_i {
+                    self.i = self.i.wrapping_add(register as u16 + 1);
+                }
+            }
+        }
+
+        self.program_counter = self.program_counter.wrapping_add(2);
+        Ok(())
+    }
+
+    /// Runs the MEGA-CHIP instructions that only change its own state
+    fn execute_megachip(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        let start = self.i_address();
+        let megachip = match &mut self.megachip {
+            Some(megachip) => megachip,
+            None => return Ok(()),
+        };
+
+        match instruction {
+            Instruction::MegaOn => {
+                megachip.active = true;
+                self.set_resolution(MEGACHIP_WIDTH, MEGACHIP_HEIGHT);
+            }
+            Instruction::MegaOff => {
+                megachip.active = false;
+                self.set_resolution(self.lores_size.0, self.lores_size.1);
+            }
+            Instruction::SetIHuge(high) => {
+                megachip.i_high = high;
+                let address = self.program_counter.wrapping_add(2);
+                self.check_read(address as usize, 2);
+                self.i = self.read_word(address);
+                self.program_counter = address;
+            }
+            Instruction::LoadPalette(count) => {
+                let length = 4 * count as usize;
+                self.check_read(start, length);
+                let colors = (0..length)
+                    .map(|offset| self.read_byte(start, offset))
+                    .collect::<Result<Vec<u8>, Chip8Error>>()?;
+                if let Some(megachip) = &mut self.megachip {
+                    megachip.load_palette(&colors);
+                }
+            }
+            Instruction::SetSpriteWidth(width) => {
+                megachip.sprite_width = MegaChip::sprite_size(width)
+            }
+            Instruction::SetSpriteHeight(height) => {
+                megachip.sprite_height = MegaChip::sprite_size(height)
+            }
+            Instruction::SetScreenAlpha(alpha) => megachip.set_alpha(alpha),
+            Instruction::PlaySound(mode) => {
+                let data = self.memory.get(start..).unwrap_or_default();
+                megachip.play_sound(data, mode == 0);
+            }
+            Instruction::StopSound => megachip.sound = None,
+            Instruction::SetBlendMode(mode) => megachip.set_blend(mode),
+            Instruction::SetCollisionColor(index) => megachip.set_collision_color(index),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// `DXYN` in MEGA-CHIP mode, drawing a sprite of palette indexes the
+    /// size `03NN` and `04NN` set. Font glyphs have no colors, so a sprite
+    /// read from the fonts is drawn N rows high in white instead.
+    fn draw_megachip(
+        &mut self,
+        register_x: u8,
+        register_y: u8,
+        sprite_height: u8,
+    ) -> Result<(), Chip8Error> {
+        let origin_x = self.get_register(register_x) as usize;
+        let origin_y = self.get_register(register_y) as usize;
+        let start = self.i_address();
+        let mut collision = false;
+
+        let (width, height) = match &self.megachip {
+            Some(megachip) => (megachip.sprite_width, megachip.sprite_height),
+            None => return Ok(()),
+        };
+
+        if self.fonts().contains(&start) {
+            self.check_read(start, sprite_height as usize);
+            for row in 0..sprite_height as usize {
+                let byte = self.read_byte(start, row)?;
+                for col in (0..8).filter(|col| byte & (0x80 >> col) != 0) {
+                    if let Some(megachip) = &mut self.megachip {
+                        collision |= megachip.plot_glyph(origin_x + col, origin_y + row);
+                    }
+                }
+            }
+        } else {
+            self.check_read(start, width * height);
+            for row in 0..height {
+                for col in 0..width {
+                    let index = self.read_byte(start, row * width + col)?;
+                    if let Some(megachip) = &mut self.megachip {
+                        collision |= megachip.plot(origin_x + col, origin_y + row, index);
+                    }
+                }
+            }
+        }
+
+        self.registers[0xF] = if collision { 1 } else { 0 };
+        Ok(())
+    }
+
+    /// The full address in I, which only MEGA-CHIP extends past 16 bits
+    fn i_address(&self) -> usize {
+        let high = self.megachip.as_ref().map_or(0, |megachip| megachip.i_high);
+        (high as usize) << 16 | self.i as usize
+    }
+
+    fn set_i(&mut self, address: u16) {
+        self.i = address;
+        if let Some(megachip) = &mut self.megachip {
+            megachip.i_high = 0;
+        }
+    }
+
+    /// The index of the byte `offset` bytes past `base`, applying the memory
+    /// access policy if that runs past the end of memory
+    fn memory_index(&self, base: usize, offset: usize) -> Result<usize, Chip8Error> {
+        let target = base + offset;
+        let size = self.memory.len();
+        match self.memory_access_policy {
+            MemoryAccessPolicy::Wrap => Ok(target % size),
+            MemoryAccessPolicy::Saturate => Ok(target.min(size - 1)),
+            MemoryAccessPolicy::Fault if target < size => Ok(target),
+            MemoryAccessPolicy::Fault => Err(Chip8Error::MemoryOutOfBounds {
+                address: self.program_counter,
+                target,
+            }),
+        }
+    }
+
+    fn read_byte(&self, base: usize, offset: usize) -> Result<u8, Chip8Error> {
+        Ok(self.memory[self.memory_index(base, offset)?])
+    }
+
+    fn write_byte(&mut self, base: usize, offset: usize, value: u8) -> Result<(), Chip8Error> {
+        let index = self.memory_index(base, offset)?;
+        self.memory[index] = value;
+        Ok(())
+    }
+
+    /// Reports a diagnostic, unless the instruction it is about already has
+    fn diagnose(&mut self, diagnostic: Diagnostic) {
+        if self.diagnosed.insert(self.program_counter) {
+            self.diagnostics.push(diagnostic);
+            self.events.push_back(Chip8Event::Diagnostic(diagnostic));
+        }
+    }
+
+    /// Every diagnostic reported so far, oldest first
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Checks the `SE VX, NN` following the `00F9` at `address`, failing the
+    /// test if VX doesn't hold NN, and moves past it
+    fn check_assertion(&mut self, address: u16) -> Result<(), Chip8Error> {
+        let next = self.program_counter.wrapping_add(2);
+        self.check_read(next as usize, 2);
+
+        let (register, expected) = match Instruction::decode(self.read_word(next)) {
+            Instruction::RegEqVal(register, value) => (register, value),
+            _ => {
+                return Err(Chip8Error::UnknownOpcode {
+                    address,
+                    opcode: Instruction::Assert.encode(),
+                })
+            }
+        };
+
+        let actual = self.get_register(register);
+        if actual != expected {
+            self.test_result = Some(TestResult::AssertionFailed {
+                address,
+                register,
+                expected,
+                actual,
+            });
+        }
+        self.program_counter = next;
+        Ok(())
+    }
+
+    /// Skips the next instruction, which is two words long if it is `F000 NNNN`,
+    /// MEGA-CHIP's `01NN NNNN` or the test extension's `00F9 3XNN`
+    fn skip(&mut self) {
+        let next = self.program_counter.wrapping_add(2);
+        self.program_counter = match Instruction::decode(self.read_word(next)) {
+            Instruction::SetILong => next.wrapping_add(2),
+            Instruction::Assert if self.test_extensions => next.wrapping_add(2),
+            Instruction::SetIHuge(_) if self.megachip.is_some() => next.wrapping_add(2),
+            _ => next,
+        };
+    }
+
+    /// Moves the selected planes of the display by `(dx, dy)` pixels,
+    /// blanking what scrolls in
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        if self.draws_own_colors() {
+            if let Some(megachip) = &mut self.megachip {
+                megachip.scroll(dx, dy);
+            }
+            return;
+        }
+
+        self.display.scroll(self.planes, dx, dy);
+        self.display_changed = true;
+    }
+
+    #[inline(always)]
+    fn get_register(&self, register: u8) -> u8 {
+        self.registers[register as usize]
+    }
+
+    #[inline(always)]
+    fn set_register(&mut self, register: u8, value: u8) {
+        self.registers[register as usize] = value;
+    }
+
+    #[inline(always)]
+    fn get_opcode(&self) -> u16 {
+        self.read_word(self.program_counter)
+    }
+
+    /// The big endian word at `address`, wrapping past the end of memory so
+    /// a program running off the end of a small memory carries on from the start
+    #[inline(always)]
+    fn read_word(&self, address: u16) -> u16 {
+        let length = self.memory.len();
+        let address = address as usize;
+        (self.memory[address % length] as u16) << 8 | self.memory[(address + 1) % length] as u16
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new()
+    }
+}
+
+impl Debug for Chip8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Chip8")
+            .field(
+                "program_counter",
+                &format_args!("{:#05X}", self.program_counter),
+            )
+            .field("i", &format_args!("{:#05X}", self.i))
+            .field("registers", &self.registers)
+            .field("stack", &self.stack)
+            .field("delay_timer", &self.delay_timer)
+            .field("sound_timer", &self.sound_timer)
+            .field("disassembly", &Disassembly(self))
+            .field("diagnostics", &self.diagnostics)
+            .finish()
+    }
+}
+
+/// The generator behind `CXNN`, seeded from the operating system
+#[cfg(feature = "std")]
+fn new_rng() -> StdRng {
+    StdRng::from_entropy()
+}
+
+/// Without an operating system to seed from, every run starts the same until
+/// `seed_rng` is called
+#[cfg(not(feature = "std"))]
+fn new_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
+}
+
+/// The registers `5XY2` and `5XY3` cover, in order, which runs backwards when
+/// X is greater than Y
+fn register_range(x: u8, y: u8) -> Box<dyn ExactSizeIterator<Item = u8>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+/// Whether `rom` starts up the way two-page hi-res ROMs do: a jump over the
+/// interpreter patch, the patch itself up to the game at 0x2C0, and a game
+/// that clears the screen through the patch. A plain ROM that merely
+/// starts with `1260` has no use for the patch's clear.
+fn is_two_page(rom: &[u8]) -> bool {
+    let game = (TWO_PAGE_PROGRAM_START_ADDRESS as usize - PROGRAM_START_ADDRESS)..rom.len();
+    rom.starts_with(&TWO_PAGE_SIGNATURE)
+        && rom.get(game).is_some_and(|game| {
+            game.chunks_exact(2)
+                .any(|word| word == TWO_PAGE_CLEAR.to_be_bytes())
+        })
+}
+
+/// Instructions surrounding the program counter, read the way they are
+/// fetched so the listing wraps past the end of memory as execution does
+struct Disassembly<'a>(&'a Chip8);
+
+impl Debug for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let chip8 = self.0;
+        let program_counter = chip8.program_counter as usize;
+
+        let start = program_counter.saturating_sub(2 * DISASSEMBLY_CONTEXT);
+        let end = (program_counter + 2 * DISASSEMBLY_CONTEXT).min(u16::MAX as usize);
+
+        let mut list = f.debug_list();
+
+        for address in (start..=end).step_by(2) {
+            let marker = if address == program_counter { ">" } else { " " };
+            let opcode = chip8.read_word(address as u16);
+
+            list.entry(&format_args!(
+                "{} {:#05X}: {:04X}  {}",
+                marker,
+                address,
+                opcode,
+                Instruction::decode(opcode)
+            ));
+        }
+
+        list.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-page hi-res ROM whose game is `game`, with the patch left blank
+    fn two_page_rom(game: &[u16]) -> Vec<u8> {
+        let mut rom = TWO_PAGE_SIGNATURE.to_vec();
+        rom.resize(
+            TWO_PAGE_PROGRAM_START_ADDRESS as usize - PROGRAM_START_ADDRESS,
+            0,
+        );
+        rom.extend(game.iter().flat_map(|word| word.to_be_bytes()));
+        rom
+    }
+
+    fn run(chip8: &mut Chip8, steps: usize) {
+        for _ in 0..steps {
+            chip8.step(&[false; 16]).unwrap();
+        }
+    }
+
+    /// A machine loaded with `program`
+    fn machine(program: &[u16]) -> Chip8 {
+        let rom: Vec<u8> = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8
+    }
+
+    /// The lit pixels of the top left 8x5 corner, a row per byte
+    fn corner(chip8: &Chip8) -> [u8; 5] {
+        let mut rows = [0; 5];
+        for (y, row) in rows.iter_mut().enumerate() {
+            for x in 0..8 {
+                *row |= chip8.pixel(x, y as u16) << (7 - x);
+            }
+        }
+        rows
+    }
+
+    /// Points I at the 0 glyph and draws it at the top left
+    const DRAW_ZERO: [u16; 3] = [0x6000, 0xF029, 0xD005];
+
+    #[test]
+    fn draw_lights_the_sprite() {
+        let mut chip8 = machine(&DRAW_ZERO);
+        run(&mut chip8, 3);
+        assert_eq!(corner(&chip8), [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(chip8.registers()[0xF], 0);
+    }
+
+    #[test]
+    fn clear_blanks_the_display() {
+        let mut chip8 = machine(&[DRAW_ZERO[0], DRAW_ZERO[1], DRAW_ZERO[2], 0x00E0]);
+        run(&mut chip8, 3);
+        assert_ne!(corner(&chip8), [0; 5]);
+        run(&mut chip8, 1);
+        assert_eq!(corner(&chip8), [0; 5]);
+    }
+
+    #[test]
+    fn drawing_over_lit_pixels_erases_them_and_sets_vf() {
+        // The 0 glyph, then the 1 glyph a row lower over it
+        let program = [0x6000, 0xF029, 0xD005, 0x6101, 0xF129, 0xD015];
+        let mut chip8 = machine(&program);
+        run(&mut chip8, 6);
+        assert_eq!(corner(&chip8), [0xF0, 0xB0, 0xF0, 0xB0, 0xD0]);
+        assert_eq!(chip8.registers()[0xF], 1);
+
+        // A sprite drawn twice erases itself
+        let mut chip8 = machine(&[0x6000, 0xF029, 0xD005, 0xD005]);
+        run(&mut chip8, 4);
+        assert_eq!(corner(&chip8), [0; 5]);
+        assert_eq!(chip8.registers()[0xF], 1);
+    }
+
+    #[test]
+    fn key_checks_read_the_key_in_vx() {
+        let mut held = [false; 16];
+        held[5] = true;
+
+        // V3 = 5, then skip if key 5 is down and again if it is up
+        let mut chip8 = machine(&[0x6305, 0xE39E, 0x0000, 0xE3A1]);
+        chip8.step(&held).unwrap();
+        chip8.step(&held).unwrap();
+        assert_eq!(chip8.program_counter(), 0x206);
+        chip8.step(&held).unwrap();
+        assert_eq!(chip8.program_counter(), 0x208);
+
+        // Key 3, the register's number, doesn't count
+        let mut held = [false; 16];
+        held[3] = true;
+        let mut chip8 = machine(&[0x6305, 0xE39E]);
+        run(&mut chip8, 1);
+        chip8.step(&held).unwrap();
+        assert_eq!(chip8.program_counter(), 0x204);
+    }
+
+    #[test]
+    fn add_i_sets_vf_on_overflow_past_0xfff() {
+        let quirks = Quirks {
+            add_i_sets_vf: true,
+            ..Quirks::default()
+        };
+
+        // I = 0xFF0, then add 0x0F and 1
+        let mut chip8 = machine(&[0xAFF0, 0x600F, 0xF01E, 0x6001, 0xF01E]);
+        chip8.set_quirks(quirks);
+        run(&mut chip8, 3);
+        assert_eq!((chip8.i(), chip8.registers()[0xF]), (0xFFF, 0));
+        run(&mut chip8, 2);
+        assert_eq!((chip8.i(), chip8.registers()[0xF]), (0x1000, 1));
+
+        // A 16-bit I that wraps around to zero still overflowed
+        let mut chip8 = machine(&[0xF000, 0xFFF0, 0x6010, 0xF01E]);
+        chip8.set_quirks(quirks);
+        run(&mut chip8, 3);
+        assert_eq!((chip8.i(), chip8.registers()[0xF]), (0, 1));
+    }
+
+    #[test]
+    fn large_font_digit_is_masked_or_diagnosed() {
+        let masked = Quirks {
+            font_digit_masked: true,
+            ..Quirks::default()
+        };
+        let unmasked = Quirks {
+            font_digit_masked: false,
+            ..Quirks::default()
+        };
+
+        let mut chip8 = machine(&[0x6002, 0xF030, 0x6012, 0xF030]);
+        chip8.set_quirks(masked);
+        run(&mut chip8, 2);
+        let two = chip8.i();
+        run(&mut chip8, 2);
+        assert_eq!(chip8.i(), two);
+        assert!(chip8.diagnostics().is_empty());
+
+        let mut chip8 = machine(&[0x6009, 0xF030, 0x600A, 0xF030]);
+        chip8.set_quirks(unmasked);
+        run(&mut chip8, 2);
+        assert!(chip8.diagnostics().is_empty());
+        run(&mut chip8, 2);
+        let pc = PROGRAM_START_ADDRESS as u16 + 6;
+        let diagnostic = Diagnostic::FontDigitOutOfRange { pc, digit: 0xA };
+        assert_eq!(chip8.diagnostics(), [diagnostic]);
+    }
+
+    /// A strict machine loaded with `program`
+    fn strict_machine(program: &[u16]) -> Chip8 {
+        let rom: Vec<u8> = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let mut chip8 = Chip8::new();
+        chip8.set_strict(true);
+        chip8.load_rom(&rom).unwrap();
+        chip8
+    }
+
+    fn strict_violations(chip8: &mut Chip8) -> Vec<StrictViolation> {
+        let mut violations = vec![];
+        while let Some(event) = chip8.poll_event() {
+            if let Chip8Event::StrictViolation(violation) = event {
+                violations.push(violation);
+            }
+        }
+        violations
+    }
+
+    #[test]
+    fn strict_mode_reports_the_stack_outgrowing_its_region() {
+        // Calls itself forever
+        let mut chip8 = strict_machine(&[0x2200]);
+        run(&mut chip8, STRICT_STACK_DEPTH);
+        assert!(strict_violations(&mut chip8).is_empty());
+
+        run(&mut chip8, 2);
+        let pc = PROGRAM_START_ADDRESS as u16;
+        let violation = StrictViolation::StackOverflow {
+            pc,
+            depth: STRICT_STACK_DEPTH + 1,
+        };
+        assert_eq!(strict_violations(&mut chip8), [violation]);
+    }
+
+    #[test]
+    fn strict_mode_reports_returning_with_an_empty_stack() {
+        let mut chip8 = strict_machine(&[0x00EE]);
+        let pc = PROGRAM_START_ADDRESS as u16;
+        assert_eq!(
+            chip8.step(&[false; 16]),
+            Err(Chip8Error::StackUnderflow { address: pc })
+        );
+        let violation = StrictViolation::StackUnderflow { pc };
+        assert_eq!(strict_violations(&mut chip8), [violation]);
+    }
+
+    #[test]
+    fn resizing_memory_in_strict_mode_poisons_the_new_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.set_strict(true);
+        chip8.set_memory_size(0x10000);
+
+        // Past the 4K the bookkeeping started with
+        let rom = vec![0x12; 0x2000];
+        chip8.load_rom(&rom).unwrap();
+        chip8.write_memory(0xFFFE, 0x12);
+        assert_eq!(chip8.memory()[0xFFFF], POISON);
+
+        chip8.set_memory_size(0x1000);
+        chip8.write_memory(0xFFF, 0x12);
+        assert_eq!(chip8.memory().len(), 0x1000);
+    }
+
+    #[test]
+    fn assertions_fail_the_test_only_when_vx_differs() {
+        // V3 = 5, assert V3 == 5, assert V3 == 6
+        let mut chip8 = machine(&[0x6305, 0x00F9, 0x3305, 0x00F9, 0x3306]);
+        chip8.set_test_extensions(true);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.test_result(), None);
+        assert_eq!(chip8.program_counter(), PROGRAM_START_ADDRESS as u16 + 6);
+
+        run(&mut chip8, 1);
+        let failure = TestResult::AssertionFailed {
+            address: PROGRAM_START_ADDRESS as u16 + 6,
+            register: 3,
+            expected: 6,
+            actual: 5,
+        };
+        assert_eq!(chip8.test_result(), Some(failure));
+    }
+
+    #[test]
+    fn skips_step_over_a_whole_assertion() {
+        // Skips the assertion, which would fail
+        let mut chip8 = machine(&[0x3000, 0x00F9, 0x3001]);
+        chip8.set_test_extensions(true);
+        run(&mut chip8, 1);
+        assert_eq!(chip8.program_counter(), PROGRAM_START_ADDRESS as u16 + 6);
+        assert_eq!(chip8.test_result(), None);
+    }
+
+    #[test]
+    fn megachip_mode_switches_to_its_own_display_and_back() {
+        let mut chip8 = Chip8::new();
+        chip8.set_megachip(true);
+        chip8.set_memory_size(MEGACHIP_MEMORY_SIZE);
+        chip8.load_rom(&[0x00, 0x11, 0x00, 0x10]).unwrap();
+
+        run(&mut chip8, 1);
+        assert_eq!(chip8.display_size(), (MEGACHIP_WIDTH, MEGACHIP_HEIGHT));
+        let resized = Chip8Event::ResolutionChanged {
+            width: MEGACHIP_WIDTH,
+            height: MEGACHIP_HEIGHT,
+        };
+        assert_eq!(chip8.poll_event(), Some(resized));
+
+        run(&mut chip8, 1);
+        assert_eq!(chip8.display_size(), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    }
+
+    #[test]
+    fn megachip_opcodes_need_megachip_enabled() {
+        let mut chip8 = machine(&[0x0011]);
+        chip8.step(&[false; 16]).unwrap();
+        assert_eq!(chip8.display_size(), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    }
+
+    #[test]
+    fn roms_must_fit_in_memory_after_the_program_start() {
+        let available = DEFAULT_MEMORY_SIZE - PROGRAM_START_ADDRESS;
+        let mut chip8 = Chip8::new();
+        assert!(chip8.load_rom(&vec![0; available]).is_ok());
+        assert_eq!(
+            Chip8::new().load_rom(&vec![0; available + 1]),
+            Err(Chip8Error::RomTooLarge {
+                size: available + 1,
+                available,
+            })
+        );
+
+        let mut chip8 = Chip8::new();
+        chip8.set_memory_size(MEGACHIP_MEMORY_SIZE);
+        assert!(chip8.load_rom(&vec![0; MEGACHIP_MAX_ROM_SIZE]).is_ok());
+        let mut chip8 = Chip8::new();
+        chip8.set_memory_size(MEGACHIP_MEMORY_SIZE);
+        assert!(chip8.load_rom(&vec![0; MEGACHIP_MAX_ROM_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn two_page_clear_blanks_the_screen() {
+        let mut chip8 = Chip8::new();
+        // Draw the 0 glyph at the top left, then clear through the patch
+        let game = [0x6000, 0xF029, 0xD005, TWO_PAGE_CLEAR, 0x12C8];
+        chip8.load_rom(&two_page_rom(&game)).unwrap();
+        assert_eq!(chip8.display_size(), (SCREEN_WIDTH, TWO_PAGE_SCREEN_HEIGHT));
+
+        run(&mut chip8, 3);
+        assert_eq!(chip8.pixel(0, 0), 1);
+        run(&mut chip8, 1);
+        assert_eq!(chip8.pixel(0, 0), 0);
+    }
+
+    #[test]
+    fn plain_jump_to_0x260_is_not_two_page() {
+        let mut chip8 = Chip8::new();
+        let mut rom = two_page_rom(&[0x00E0, 0x12C2]);
+        chip8.load_rom(&rom).unwrap();
+        assert_eq!(chip8.display_size(), (SCREEN_WIDTH, SCREEN_HEIGHT));
+        assert_eq!(chip8.program_counter(), PROGRAM_START_ADDRESS as u16);
+
+        rom.truncate(2);
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        assert_eq!(chip8.display_size(), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    }
+}
@@ -0,0 +1,94 @@
+/// Width in pixels of a column of the VP-590 color board's color map
+const ZONE_WIDTH: usize = 8;
+/// Rows of pixels given one color by `BXY0`
+const ZONE_HEIGHT: usize = 4;
+const ZONE_COLUMNS: usize = 8;
+const ZONE_ROWS: usize = 32;
+
+/// Background colors, in the order `02A0` cycles through them
+const BACKGROUNDS: [[u8; 3]; 4] = [[0, 0, 0x80], [0, 0, 0], [0, 0x80, 0], [0x80, 0, 0]];
+/// Foreground colors, picked by the low three bits of the register `BXYN` reads
+const FOREGROUNDS: [[u8; 3]; 8] = [
+    [0, 0, 0],
+    [0xFF, 0, 0],
+    [0, 0, 0xFF],
+    [0xFF, 0, 0xFF],
+    [0, 0xFF, 0],
+    [0xFF, 0xFF, 0],
+    [0, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+/// The foreground every zone starts with, red on the blue background
+const DEFAULT_FOREGROUND: u8 = 1;
+
+/// The color map of the VP-590 color board CHIP-8X drives. Each 8 pixel wide
+/// strip of a row has its own foreground color, and the whole display shares
+/// one background color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chip8xColors {
+    background: usize,
+    zones: [u8; ZONE_COLUMNS * ZONE_ROWS],
+}
+
+impl Default for Chip8xColors {
+    fn default() -> Self {
+        Chip8xColors {
+            background: 0,
+            zones: [DEFAULT_FOREGROUND; ZONE_COLUMNS * ZONE_ROWS],
+        }
+    }
+}
+
+impl Chip8xColors {
+    /// `02A0`: moves on to the next background color
+    pub(super) fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    /// `BXY0`: colors a block of 8x4 zones. The low nibble of `horizontal`
+    /// is the first column and the high nibble the number of columns after
+    /// it, and likewise for `vertical` with rows of zones.
+    pub(super) fn set_zones(&mut self, horizontal: u8, vertical: u8, color: u8) {
+        let columns = span(horizontal, ZONE_COLUMNS);
+        let rows = span(vertical, ZONE_ROWS / ZONE_HEIGHT);
+
+        for zone_row in rows {
+            for row in zone_row * ZONE_HEIGHT..(zone_row + 1) * ZONE_HEIGHT {
+                for column in columns.clone() {
+                    self.zones[row * ZONE_COLUMNS + column] = color & 0b111;
+                }
+            }
+        }
+    }
+
+    /// `BXYN`: colors `height` rows of the 8 pixel strip containing `(x, y)`
+    pub(super) fn set_rows(&mut self, x: u8, y: u8, height: u8, color: u8) {
+        let column = (x as usize / ZONE_WIDTH) % ZONE_COLUMNS;
+        for row in (y as usize..y as usize + height as usize).map(|row| row % ZONE_ROWS) {
+            self.zones[row * ZONE_COLUMNS + column] = color & 0b111;
+        }
+    }
+
+    /// The color of the pixel at `(x, y)` of a display `width` pixels wide:
+    /// the foreground color of its zone if `lit`, or else the background
+    pub fn color(&self, x: u16, y: u16, width: u16, lit: bool) -> [u8; 3] {
+        if !lit {
+            return BACKGROUNDS[self.background];
+        }
+
+        // The color map covers the low resolution display, so a high
+        // resolution display uses each zone for twice as many pixels
+        let scale = (width as usize / (ZONE_COLUMNS * ZONE_WIDTH)).max(1);
+        let (x, y) = (x as usize / scale, y as usize / scale);
+        let zone = (y % ZONE_ROWS) * ZONE_COLUMNS + x / ZONE_WIDTH % ZONE_COLUMNS;
+        FOREGROUNDS[self.zones[zone] as usize]
+    }
+}
+
+/// The zones covered by a nibble pair: the low nibble is the first and the
+/// high nibble how many follow it
+fn span(value: u8, limit: usize) -> impl Iterator<Item = usize> + Clone {
+    let start = (value & 0xF) as usize;
+    let end = (start + (value >> 4) as usize + 1).min(limit);
+    start.min(limit)..end
+}
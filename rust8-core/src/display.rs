@@ -0,0 +1,99 @@
+//! The display as packed bit planes, one `u128` per row of each XO-CHIP
+//! plane, so a sprite row is drawn with a single XOR whatever its width.
+//! Frontends read it a pixel at a time and pick the colors themselves.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Widest display the planes can hold, one bit per column
+const MAX_WIDTH: u16 = 128;
+const PLANE_COUNT: usize = 2;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Planes {
+    width: u16,
+    /// Rows of each plane, with the leftmost column in the top bit
+    rows: [Vec<u128>; PLANE_COUNT],
+}
+
+impl Planes {
+    /// Blank planes for a display of the given size, which is clamped to
+    /// `MAX_WIDTH` columns
+    pub(crate) fn new(width: u16, height: u16) -> Self {
+        let height = height as usize;
+        Planes {
+            width: width.clamp(1, MAX_WIDTH),
+            rows: [vec![0; height], vec![0; height]],
+        }
+    }
+
+    /// The bits of a row that are on the display
+    fn visible(&self) -> u128 {
+        !0 << (MAX_WIDTH - self.width)
+    }
+
+    /// XORs one row of a sprite onto `plane` at column `x` of row `y`,
+    /// returning whether it turned off a lit pixel. `bits` holds the row
+    /// with its leftmost pixel in the top bit. What runs off the right edge
+    /// wraps around to the left unless `clip` is set.
+    pub(crate) fn draw_row(
+        &mut self,
+        plane: usize,
+        x: u16,
+        y: u16,
+        bits: u128,
+        clip: bool,
+    ) -> bool {
+        let visible = self.visible();
+        let mut sprite = bits >> x & visible;
+        if !clip {
+            sprite |= bits.checked_shl((self.width - x) as u32).unwrap_or(0) & visible;
+        }
+
+        let row = &mut self.rows[plane][y as usize];
+        let collision = *row & sprite != 0;
+        *row ^= sprite;
+        collision
+    }
+
+    /// Blanks the planes selected in `planes`, one bit per plane
+    pub(crate) fn clear(&mut self, planes: u8) {
+        for plane in selected(planes) {
+            self.rows[plane].fill(0);
+        }
+    }
+
+    /// Moves the planes selected in `planes` by `(dx, dy)` pixels, blanking
+    /// what scrolls in
+    pub(crate) fn scroll(&mut self, planes: u8, dx: isize, dy: isize) {
+        let visible = self.visible();
+        for plane in selected(planes) {
+            let previous = self.rows[plane].clone();
+            for (y, row) in self.rows[plane].iter_mut().enumerate() {
+                let source = y as isize - dy;
+                *row = match previous.get(source as usize).filter(|_| source >= 0) {
+                    Some(&row) if dx >= 0 => row >> dx & visible,
+                    Some(&row) => row << -dx & visible,
+                    None => 0,
+                };
+            }
+        }
+    }
+
+    /// The palette index of the pixel at `(x, y)`, with a bit from each
+    /// plane, or 0 off the display
+    pub(crate) fn pixel(&self, x: u16, y: u16) -> u8 {
+        let y = y as usize;
+        if x >= self.width || y >= self.rows[0].len() {
+            return 0;
+        }
+        let shift = MAX_WIDTH - 1 - x;
+        let (low, high) = (self.rows[0][y], self.rows[1][y]);
+        ((low >> shift & 1) | (high >> shift & 1) << 1) as u8
+    }
+}
+
+/// Indexes of the planes selected by a plane mask
+fn selected(planes: u8) -> impl Iterator<Item = usize> {
+    (0..PLANE_COUNT).filter(move |plane| planes & (1 << plane) != 0)
+}
@@ -0,0 +1,343 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::chip8::{Chip8, Chip8Error};
+
+/// Frames run at 60hz, the speed within a frame is set by the core's cycles per frame
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+enum Command {
+    Pause,
+    Resume,
+    /// Executes a single instruction, only meaningful while paused
+    Step,
+    Poke {
+        address: u16,
+        value: u8,
+    },
+    SetKeys([bool; 16]),
+    SetCyclesPerFrame(u32),
+    SubscribeFrames(Sender<Frame>),
+    SubscribeTraces(Sender<Trace>),
+    SubscribeErrors(Sender<Chip8Error>),
+    Shutdown,
+}
+
+/// A completed display frame, row by row
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub number: u64,
+    pub width: u16,
+    pub height: u16,
+    /// The palette index of each pixel, as `Chip8::pixel` gives it
+    pub pixels: Vec<u8>,
+}
+
+/// An instruction about to be executed
+#[derive(Debug, Clone, Copy)]
+pub struct Trace {
+    pub address: u16,
+    pub opcode: u16,
+}
+
+/// Owns a core on its own thread, running it in real time. Every interaction
+/// goes through a channel so any number of UIs can drive and observe the same
+/// machine without sharing it.
+pub struct Chip8Handle {
+    commands: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Chip8Handle {
+    /// Moves the core onto a new thread and starts running it
+    pub fn spawn(chip8: Chip8) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || Runner::new(chip8, receiver).run());
+
+        Chip8Handle {
+            commands,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(Command::Resume);
+    }
+
+    pub fn step(&self) {
+        self.send(Command::Step);
+    }
+
+    /// Writes a byte of memory, e.g. to patch a ROM while it runs
+    pub fn poke(&self, address: u16, value: u8) {
+        self.send(Command::Poke { address, value });
+    }
+
+    pub fn set_keys(&self, key_states: [bool; 16]) {
+        self.send(Command::SetKeys(key_states));
+    }
+
+    /// Changes the emulation speed, see `Chip8::set_cycles_per_frame`
+    pub fn set_cycles_per_frame(&self, cycles: u32) {
+        self.send(Command::SetCyclesPerFrame(cycles));
+    }
+
+    /// Receives every frame from now on, until the receiver is dropped
+    pub fn subscribe_frames(&self) -> Receiver<Frame> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::SubscribeFrames(sender));
+        receiver
+    }
+
+    /// Receives every executed instruction from now on, until the receiver is dropped
+    pub fn subscribe_traces(&self) -> Receiver<Trace> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::SubscribeTraces(sender));
+        receiver
+    }
+
+    /// Receives the error that stopped the core, if it ever faults
+    pub fn subscribe_errors(&self) -> Receiver<Chip8Error> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::SubscribeErrors(sender));
+        receiver
+    }
+
+    fn send(&self, command: Command) {
+        // The runner only exits once shut down, so a failed send can be ignored
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for Chip8Handle {
+    fn drop(&mut self) {
+        self.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The state living on the core's thread
+struct Runner {
+    chip8: Chip8,
+    commands: Receiver<Command>,
+    key_states: [bool; 16],
+    paused: bool,
+    frame_number: u64,
+    frames: Vec<Sender<Frame>>,
+    traces: Vec<Sender<Trace>>,
+    errors: Vec<Sender<Chip8Error>>,
+}
+
+impl Runner {
+    fn new(chip8: Chip8, commands: Receiver<Command>) -> Self {
+        Runner {
+            chip8,
+            commands,
+            key_states: [false; 16],
+            paused: false,
+            frame_number: 0,
+            frames: vec![],
+            traces: vec![],
+            errors: vec![],
+        }
+    }
+
+    fn run(mut self) {
+        let mut next_frame = Instant::now();
+
+        loop {
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+
+            match self.commands.recv_timeout(timeout) {
+                Ok(Command::Shutdown) | Err(RecvTimeoutError::Disconnected) => return,
+                Ok(command) => {
+                    self.handle(command);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+
+            if !self.paused {
+                self.run_frame();
+            }
+            self.publish_frame();
+            next_frame += FRAME_INTERVAL;
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::Step => {
+                if self.paused {
+                    self.step();
+                    self.publish_frame();
+                }
+            }
+            Command::Poke { address, value } => self.chip8.write_memory(address, value),
+            Command::SetKeys(key_states) => self.key_states = key_states,
+            Command::SetCyclesPerFrame(cycles) => self.chip8.set_cycles_per_frame(cycles),
+            Command::SubscribeFrames(sender) => self.frames.push(sender),
+            Command::SubscribeTraces(sender) => self.traces.push(sender),
+            Command::SubscribeErrors(sender) => self.errors.push(sender),
+            Command::Shutdown => (),
+        }
+    }
+
+    fn run_frame(&mut self) {
+        if !self.traces.is_empty() {
+            // Step one instruction at a time so each one can be traced
+            for _ in 0..self.chip8.cycles_per_frame() {
+                self.step();
+                if self.paused {
+                    return;
+                }
+            }
+            self.chip8.update_timers();
+            return;
+        }
+
+        let result = self.chip8.run_frame(&self.key_states);
+        self.finish(result);
+    }
+
+    fn step(&mut self) {
+        let trace = Trace {
+            address: self.chip8.program_counter(),
+            opcode: self.chip8.opcode(),
+        };
+        self.traces.retain(|sender| sender.send(trace).is_ok());
+
+        let result = self.chip8.step(&self.key_states);
+        self.finish(result);
+    }
+
+    /// Handles the outcome of running the core
+    fn finish(&mut self, result: Result<(), Chip8Error>) {
+        if let Err(error) = result {
+            // Stay paused on the faulting instruction so it can be inspected
+            self.paused = true;
+            self.errors.retain(|sender| sender.send(error).is_ok());
+        }
+
+        // Nothing here needs to react to events, but they'd pile up otherwise
+        while self.chip8.poll_event().is_some() {}
+    }
+
+    fn publish_frame(&mut self) {
+        let number = self.frame_number;
+        self.frame_number += 1;
+
+        if self.frames.is_empty() {
+            return;
+        }
+
+        let (width, height) = self.chip8.display_size();
+        let frame = Frame {
+            number,
+            width,
+            height,
+            pixels: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| self.chip8.pixel(x, y))
+                .collect(),
+        };
+
+        self.frames
+            .retain(|sender| sender.send(frame.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A core spinning on a jump to itself at the program start
+    fn spinning() -> Chip8Handle {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x00]).unwrap();
+        Chip8Handle::spawn(chip8)
+    }
+
+    #[test]
+    fn paused_handle_steps_a_poked_instruction() {
+        let handle = spinning();
+
+        handle.pause();
+        let traces = handle.subscribe_traces();
+        let frames = handle.subscribe_frames();
+        handle.poke(0x200, 0x61);
+        handle.poke(0x201, 0x23);
+        handle.step();
+
+        let trace = traces.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!((trace.address, trace.opcode), (0x200, 0x6123));
+        let frame = frames.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(
+            frame.pixels.len(),
+            frame.width as usize * frame.height as usize
+        );
+
+        // Nothing else runs while paused
+        assert!(traces.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn each_frame_runs_the_set_number_of_cycles() {
+        let handle = spinning();
+
+        handle.pause();
+        handle.set_cycles_per_frame(3);
+        let traces = handle.subscribe_traces();
+        let frames = handle.subscribe_frames();
+        handle.resume();
+
+        // Every command is queued by now, so the frame after this one runs
+        // with the new speed even if this one was published while paused
+        frames.recv_timeout(TIMEOUT).unwrap();
+        traces.try_iter().count();
+        frames.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(traces.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn resumed_handle_runs_again() {
+        let handle = spinning();
+
+        handle.pause();
+        let traces = handle.subscribe_traces();
+        assert!(traces.recv_timeout(Duration::from_millis(50)).is_err());
+
+        handle.resume();
+        let trace = traces.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!((trace.address, trace.opcode), (0x200, 0x1200));
+    }
+
+    #[test]
+    fn faults_are_sent_to_error_subscribers() {
+        let handle = spinning();
+
+        let errors = handle.subscribe_errors();
+        handle.pause();
+        handle.poke(0x200, 0x00);
+        handle.poke(0x201, 0xEE);
+        handle.resume();
+
+        let error = errors.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(error, Chip8Error::StackUnderflow { address: 0x200 });
+
+        // The core stays paused on the faulting instruction
+        let traces = handle.subscribe_traces();
+        assert!(traces.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! The host page, an opt-in extension mapping a few bytes of memory to
+//! values from the host so homebrew can show clocks or vary demos. Plain
+//! CHIP-8 has no way to read any of them.
+
+/// Where the page starts by default, at the top of 4K memory where the
+/// original interpreter kept its variables. A ROM long enough to reach it
+/// needs the page somewhere else.
+pub const HOST_PAGE_ADDRESS: u16 = 0xFF0;
+
+/// Offsets of the values from `HOST_PAGE_ADDRESS`
+pub const HOST_HOURS: usize = 0;
+pub const HOST_MINUTES: usize = 1;
+pub const HOST_SECONDS: usize = 2;
+/// Frames since the machine started, big endian over two bytes
+pub const HOST_FRAME: usize = 3;
+/// A new random byte every frame
+pub const HOST_RANDOM: usize = 5;
+/// The key most recently pressed down, or 0xFF before the first press. Of
+/// keys pressed down in the same frame, the lowest counts.
+pub const HOST_LAST_KEY: usize = 6;
+
+pub const HOST_PAGE_SIZE: usize = 7;
+
+/// The host's wall clock time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HostTime {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HostPage {
+    /// Where the page starts in memory
+    pub(crate) address: u16,
+    frame: u16,
+    last_key: u8,
+    /// Keys held last frame, to tell which were pressed since
+    held: [bool; 16],
+}
+
+impl HostPage {
+    pub(crate) fn new(address: u16) -> Self {
+        HostPage {
+            address,
+            frame: 0,
+            last_key: 0xFF,
+            held: [false; 16],
+        }
+    }
+
+    /// Moves on a frame and lays out the values as they appear in memory
+    pub(crate) fn next_frame(
+        &mut self,
+        time: HostTime,
+        key_states: &[bool; 16],
+        random: u8,
+    ) -> [u8; HOST_PAGE_SIZE] {
+        let pressed = key_states
+            .iter()
+            .zip(&self.held)
+            .position(|(&held, &was_held)| held && !was_held);
+        if let Some(key) = pressed {
+            self.last_key = key as u8;
+        }
+        self.held = *key_states;
+
+        let mut bytes = [0; HOST_PAGE_SIZE];
+        bytes[HOST_HOURS] = time.hours;
+        bytes[HOST_MINUTES] = time.minutes;
+        bytes[HOST_SECONDS] = time.seconds;
+        bytes[HOST_FRAME..HOST_FRAME + 2].copy_from_slice(&self.frame.to_be_bytes());
+        bytes[HOST_RANDOM] = random;
+        bytes[HOST_LAST_KEY] = self.last_key;
+
+        self.frame = self.frame.wrapping_add(1);
+        bytes
+    }
+}
@@ -0,0 +1,556 @@
+pub type Register = u8;
+pub type Address = u16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Clear,
+    NoOp(Address),
+    ReportResult,
+    /// Test extension: fail the test unless the `SE VX, NN` in the word
+    /// following the instruction would skip
+    Assert,
+    Call(Address),
+    Ret,
+    Jmp(Address),
+    RegEqVal(Register, u8),
+    RegNeqVal(Register, u8),
+    RegEqReg(Register, Register),
+    SetRegVal(Register, u8),
+    AddRegVal(Register, u8),
+    SetRegReg(Register, Register),
+    SetRegOrReg(Register, Register),
+    SetRegAndReg(Register, Register),
+    SetRegXorReg(Register, Register),
+    AddRegReg(Register, Register),
+    SubRegReg(Register, Register),
+    ShiftRegRight(Register, Register),
+    RevRegSubReg(Register, Register),
+    ShiftRegLeft(Register, Register),
+    RegNeqReg(Register, Register),
+    SetI(Address),
+    SetRegRand(Register, u8),
+    JmpOffset(Address),
+    Draw(Register, Register, u8),
+    KeyUp(Register),
+    KeyDown(Register),
+    SetRegDelay(Register),
+    SetRegKey(Register),
+    SetDelayReg(Register),
+    SetSoundReg(Register),
+    AddIReg(Register),
+    SetISpriteReg(Register),
+    Bcd(Register),
+    Dump(Register),
+    Load(Register),
+    /// SUPER-CHIP: scroll the display down by N pixels
+    ScrollDown(u8),
+    /// SUPER-CHIP: scroll the display right by 4 pixels
+    ScrollRight,
+    /// SUPER-CHIP: scroll the display left by 4 pixels
+    ScrollLeft,
+    /// SUPER-CHIP: stop the interpreter
+    Exit,
+    /// SUPER-CHIP: switch to the 64x32 display
+    LowRes,
+    /// SUPER-CHIP: switch to the 128x64 display
+    HighRes,
+    /// SUPER-CHIP: point I at the large font sprite for digit VX
+    SetIBigSpriteReg(Register),
+    /// SUPER-CHIP: store V0 to VX in the RPL user flags
+    StoreFlags(Register),
+    /// SUPER-CHIP: load V0 to VX from the RPL user flags
+    LoadFlags(Register),
+    /// XO-CHIP: scroll the display up by N pixels
+    ScrollUp(u8),
+    /// XO-CHIP: store VX to VY in memory starting at I, leaving I unchanged
+    SaveRange(Register, Register),
+    /// XO-CHIP: load VX to VY from memory starting at I, leaving I unchanged
+    LoadRange(Register, Register),
+    /// XO-CHIP: select the bit planes that drawing, clearing and scrolling affect
+    SelectPlanes(u8),
+    /// XO-CHIP: set I to the 16 bit address in the word following the instruction
+    SetILong,
+    /// XO-CHIP: load the 16 byte audio pattern from memory starting at I
+    LoadAudio,
+    /// XO-CHIP: set the playback rate of the audio pattern from VX
+    SetPitch(Register),
+    /// CHIP-8X: move on to the next background color
+    CycleBackground,
+    /// CHIP-8X: add each nibble of VY to the same nibble of VX, modulo 8
+    AddNibbles(Register, Register),
+    /// CHIP-8X: skip the next instruction if key VX on the second keypad is down
+    SecondKeyDown(Register),
+    /// CHIP-8X: skip the next instruction if key VX on the second keypad is up
+    SecondKeyUp(Register),
+    /// CHIP-8X: send VX to the expansion port
+    OutputPort(Register),
+    /// CHIP-8X: read the expansion port into VX
+    InputPort(Register),
+    /// MEGA-CHIP: leave MEGA-CHIP mode for the 64x32 display
+    MegaOff,
+    /// MEGA-CHIP: enter MEGA-CHIP mode and its 256x192 full color display
+    MegaOn,
+    /// MEGA-CHIP: set I to the 24 bit address made of NN and the word
+    /// following the instruction
+    SetIHuge(u8),
+    /// MEGA-CHIP: load NN colors from memory starting at I into the palette
+    LoadPalette(u8),
+    /// MEGA-CHIP: set the width of sprites, 0 meaning 256
+    SetSpriteWidth(u8),
+    /// MEGA-CHIP: set the height of sprites, 0 meaning 256
+    SetSpriteHeight(u8),
+    /// MEGA-CHIP: set the brightness of the display
+    SetScreenAlpha(u8),
+    /// MEGA-CHIP: play the digitised sound at I, once if N is 1 or looped if 0
+    PlaySound(u8),
+    /// MEGA-CHIP: stop the digitised sound
+    StopSound,
+    /// MEGA-CHIP: set how sprites are blended with what is drawn under them
+    SetBlendMode(u8),
+    /// MEGA-CHIP: set the palette index that sprites collide with
+    SetCollisionColor(u8),
+    /// MEGA-CHIP: scroll the display up by N pixels
+    MegaScrollUp(u8),
+    /// A word that isn't a valid instruction, usually sprite or other data
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// Given an opcode, decodes the instruction into the relevant parts. Words
+    /// that aren't valid instructions decode to `Unknown`.
+    pub fn decode(opcode: u16) -> Instruction {
+        let register_x: Register = ((opcode & 0x0F00) >> 8) as u8;
+        let register_y: Register = ((opcode & 0x00F0) >> 4) as u8;
+        let address: Address = opcode & 0x0FFF;
+
+        let nn = (opcode & 0xFF) as u8;
+        let n = (opcode & 0xF) as u8;
+
+        match opcode & 0xF000 {
+            0 => match address {
+                0xE0 => Instruction::Clear,
+                0xEE => Instruction::Ret,
+                0xFA => Instruction::ReportResult, // Test extension, only honoured when enabled
+                0xF9 => Instruction::Assert,       // Test extension, only honoured when enabled
+                0xC0..=0xCF => Instruction::ScrollDown(n),
+                0xD0..=0xDF => Instruction::ScrollUp(n),
+                0xFB => Instruction::ScrollRight,
+                0xFC => Instruction::ScrollLeft,
+                0xFD => Instruction::Exit,
+                0xFE => Instruction::LowRes,
+                0xFF => Instruction::HighRes,
+                0x2A0 => Instruction::CycleBackground, // CHIP-8X, only honoured in that mode
+                // MEGA-CHIP, only honoured in that mode
+                0x010 => Instruction::MegaOff,
+                0x011 => Instruction::MegaOn,
+                0x0B0..=0x0BF => Instruction::MegaScrollUp(n),
+                0x100..=0x1FF => Instruction::SetIHuge(nn),
+                0x200..=0x2FF => Instruction::LoadPalette(nn),
+                0x300..=0x3FF => Instruction::SetSpriteWidth(nn),
+                0x400..=0x4FF => Instruction::SetSpriteHeight(nn),
+                0x500..=0x5FF => Instruction::SetScreenAlpha(nn),
+                0x600..=0x60F => Instruction::PlaySound(n),
+                0x700 => Instruction::StopSound,
+                0x800..=0x80F => Instruction::SetBlendMode(n),
+                0x900..=0x9FF => Instruction::SetCollisionColor(nn),
+                address => Instruction::NoOp(address), // Would be a machine specific subroutine on actual hardware
+            },
+            0x1000 => Instruction::Jmp(address),
+            0x2000 => Instruction::Call(address),
+            0x3000 => Instruction::RegEqVal(register_x, nn),
+            0x4000 => Instruction::RegNeqVal(register_x, nn),
+            0x5000 => match n {
+                0x0 => Instruction::RegEqReg(register_x, register_y),
+                0x1 => Instruction::AddNibbles(register_x, register_y),
+                0x2 => Instruction::SaveRange(register_x, register_y),
+                0x3 => Instruction::LoadRange(register_x, register_y),
+                _ => Instruction::Unknown(opcode),
+            },
+            0x6000 => Instruction::SetRegVal(register_x, nn),
+            0x7000 => Instruction::AddRegVal(register_x, nn),
+            0x8000 => match n {
+                0x0 => Instruction::SetRegReg(register_x, register_y),
+                0x1 => Instruction::SetRegOrReg(register_x, register_y),
+                0x2 => Instruction::SetRegAndReg(register_x, register_y),
+                0x3 => Instruction::SetRegXorReg(register_x, register_y),
+                0x4 => Instruction::AddRegReg(register_x, register_y),
+                0x5 => Instruction::SubRegReg(register_x, register_y),
+                0x6 => Instruction::ShiftRegRight(register_x, register_y),
+                0x7 => Instruction::RevRegSubReg(register_x, register_y),
+                0xE => Instruction::ShiftRegLeft(register_x, register_y),
+                _ => Instruction::Unknown(opcode),
+            },
+            0x9000 => match n {
+                0x0 => Instruction::RegNeqReg(register_x, register_y),
+                _ => Instruction::Unknown(opcode),
+            },
+            0xA000 => Instruction::SetI(address),
+            0xB000 => Instruction::JmpOffset(address),
+            0xC000 => Instruction::SetRegRand(register_x, nn),
+            0xD000 => Instruction::Draw(register_x, register_y, n),
+            0xE000 => match nn {
+                0x9E => Instruction::KeyDown(register_x),
+                0xA1 => Instruction::KeyUp(register_x),
+                0xF2 => Instruction::SecondKeyDown(register_x),
+                0xF5 => Instruction::SecondKeyUp(register_x),
+                _ => Instruction::Unknown(opcode),
+            },
+            0xF000 => match nn {
+                0x00 if register_x == 0 => Instruction::SetILong,
+                0x01 => Instruction::SelectPlanes(register_x),
+                0x02 if register_x == 0 => Instruction::LoadAudio,
+                0x07 => Instruction::SetRegDelay(register_x),
+                0x0A => Instruction::SetRegKey(register_x),
+                0x15 => Instruction::SetDelayReg(register_x),
+                0x18 => Instruction::SetSoundReg(register_x),
+                0x1E => Instruction::AddIReg(register_x),
+                0x29 => Instruction::SetISpriteReg(register_x),
+                0x30 => Instruction::SetIBigSpriteReg(register_x),
+                0x33 => Instruction::Bcd(register_x),
+                0x3A => Instruction::SetPitch(register_x),
+                0x55 => Instruction::Dump(register_x),
+                0x65 => Instruction::Load(register_x),
+                0x75 => Instruction::StoreFlags(register_x),
+                0x85 => Instruction::LoadFlags(register_x),
+                0xF8 => Instruction::OutputPort(register_x),
+                0xFB => Instruction::InputPort(register_x),
+                _ => Instruction::Unknown(opcode),
+            },
+            _ => Instruction::Unknown(opcode),
+        }
+    }
+
+    /// Encodes the instruction back into its opcode, the inverse of `decode`
+    pub fn encode(&self) -> u16 {
+        let x = |register: Register| (register as u16) << 8;
+        let y = |register: Register| (register as u16) << 4;
+
+        match *self {
+            Instruction::Clear => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ReportResult => 0x00FA,
+            Instruction::Assert => 0x00F9,
+            Instruction::NoOp(address) => address,
+            Instruction::Jmp(address) => 0x1000 | address,
+            Instruction::Call(address) => 0x2000 | address,
+            Instruction::RegEqVal(register, value) => 0x3000 | x(register) | value as u16,
+            Instruction::RegNeqVal(register, value) => 0x4000 | x(register) | value as u16,
+            Instruction::RegEqReg(register_x, register_y) => 0x5000 | x(register_x) | y(register_y),
+            Instruction::SetRegVal(register, value) => 0x6000 | x(register) | value as u16,
+            Instruction::AddRegVal(register, value) => 0x7000 | x(register) | value as u16,
+            Instruction::SetRegReg(register_x, register_y) => {
+                0x8000 | x(register_x) | y(register_y)
+            }
+            Instruction::SetRegOrReg(register_x, register_y) => {
+                0x8001 | x(register_x) | y(register_y)
+            }
+            Instruction::SetRegAndReg(register_x, register_y) => {
+                0x8002 | x(register_x) | y(register_y)
+            }
+            Instruction::SetRegXorReg(register_x, register_y) => {
+                0x8003 | x(register_x) | y(register_y)
+            }
+            Instruction::AddRegReg(register_x, register_y) => {
+                0x8004 | x(register_x) | y(register_y)
+            }
+            Instruction::SubRegReg(register_x, register_y) => {
+                0x8005 | x(register_x) | y(register_y)
+            }
+            Instruction::ShiftRegRight(register_x, register_y) => {
+                0x8006 | x(register_x) | y(register_y)
+            }
+            Instruction::RevRegSubReg(register_x, register_y) => {
+                0x8007 | x(register_x) | y(register_y)
+            }
+            Instruction::ShiftRegLeft(register_x, register_y) => {
+                0x800E | x(register_x) | y(register_y)
+            }
+            Instruction::RegNeqReg(register_x, register_y) => {
+                0x9000 | x(register_x) | y(register_y)
+            }
+            Instruction::SetI(address) => 0xA000 | address,
+            Instruction::JmpOffset(address) => 0xB000 | address,
+            Instruction::SetRegRand(register, value) => 0xC000 | x(register) | value as u16,
+            Instruction::Draw(register_x, register_y, height) => {
+                0xD000 | x(register_x) | y(register_y) | height as u16
+            }
+            Instruction::KeyDown(register) => 0xE09E | x(register),
+            Instruction::KeyUp(register) => 0xE0A1 | x(register),
+            Instruction::SetRegDelay(register) => 0xF007 | x(register),
+            Instruction::SetRegKey(register) => 0xF00A | x(register),
+            Instruction::SetDelayReg(register) => 0xF015 | x(register),
+            Instruction::SetSoundReg(register) => 0xF018 | x(register),
+            Instruction::AddIReg(register) => 0xF01E | x(register),
+            Instruction::SetISpriteReg(register) => 0xF029 | x(register),
+            Instruction::Bcd(register) => 0xF033 | x(register),
+            Instruction::Dump(register) => 0xF055 | x(register),
+            Instruction::Load(register) => 0xF065 | x(register),
+            Instruction::ScrollDown(rows) => 0x00C0 | rows as u16,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::SetIBigSpriteReg(register) => 0xF030 | x(register),
+            Instruction::StoreFlags(register) => 0xF075 | x(register),
+            Instruction::LoadFlags(register) => 0xF085 | x(register),
+            Instruction::ScrollUp(rows) => 0x00D0 | rows as u16,
+            Instruction::SaveRange(register_x, register_y) => {
+                0x5002 | x(register_x) | y(register_y)
+            }
+            Instruction::LoadRange(register_x, register_y) => {
+                0x5003 | x(register_x) | y(register_y)
+            }
+            Instruction::SelectPlanes(planes) => 0xF001 | (planes as u16) << 8,
+            Instruction::SetILong => 0xF000,
+            Instruction::LoadAudio => 0xF002,
+            Instruction::SetPitch(register) => 0xF03A | x(register),
+            Instruction::CycleBackground => 0x02A0,
+            Instruction::AddNibbles(register_x, register_y) => {
+                0x5001 | x(register_x) | y(register_y)
+            }
+            Instruction::SecondKeyDown(register) => 0xE0F2 | x(register),
+            Instruction::SecondKeyUp(register) => 0xE0F5 | x(register),
+            Instruction::OutputPort(register) => 0xF0F8 | x(register),
+            Instruction::InputPort(register) => 0xF0FB | x(register),
+            Instruction::MegaOff => 0x0010,
+            Instruction::MegaOn => 0x0011,
+            Instruction::SetIHuge(value) => 0x0100 | value as u16,
+            Instruction::LoadPalette(count) => 0x0200 | count as u16,
+            Instruction::SetSpriteWidth(width) => 0x0300 | width as u16,
+            Instruction::SetSpriteHeight(height) => 0x0400 | height as u16,
+            Instruction::SetScreenAlpha(alpha) => 0x0500 | alpha as u16,
+            Instruction::PlaySound(mode) => 0x0600 | mode as u16,
+            Instruction::StopSound => 0x0700,
+            Instruction::SetBlendMode(mode) => 0x0800 | mode as u16,
+            Instruction::SetCollisionColor(index) => 0x0900 | index as u16,
+            Instruction::MegaScrollUp(rows) => 0x00B0 | rows as u16,
+            Instruction::Unknown(opcode) => opcode,
+        }
+    }
+
+    /// The conventional assembly mnemonic for the instruction
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Clear => "CLS",
+            Instruction::NoOp(_) => "SYS",
+            Instruction::ReportResult => "REPORT",
+            Instruction::Assert => "ASSERT",
+            Instruction::Call(_) => "CALL",
+            Instruction::Ret => "RET",
+            Instruction::Jmp(_) | Instruction::JmpOffset(_) => "JP",
+            Instruction::RegEqVal(..) | Instruction::RegEqReg(..) => "SE",
+            Instruction::RegNeqVal(..) | Instruction::RegNeqReg(..) => "SNE",
+            Instruction::AddRegVal(..) | Instruction::AddRegReg(..) | Instruction::AddIReg(_) => {
+                "ADD"
+            }
+            Instruction::SetRegOrReg(..) => "OR",
+            Instruction::SetRegAndReg(..) => "AND",
+            Instruction::SetRegXorReg(..) => "XOR",
+            Instruction::SubRegReg(..) => "SUB",
+            Instruction::ShiftRegRight(..) => "SHR",
+            Instruction::RevRegSubReg(..) => "SUBN",
+            Instruction::ShiftRegLeft(..) => "SHL",
+            Instruction::SetRegRand(..) => "RND",
+            Instruction::Draw(..) => "DRW",
+            Instruction::KeyDown(_) => "SKP",
+            Instruction::KeyUp(_) => "SKNP",
+            Instruction::ScrollDown(_) => "SCD",
+            Instruction::ScrollUp(_) => "SCU",
+            Instruction::SaveRange(..) => "SAVE",
+            Instruction::LoadRange(..) => "LOAD",
+            Instruction::SelectPlanes(_) => "PLANE",
+            Instruction::LoadAudio => "AUDIO",
+            Instruction::SetPitch(_) => "PITCH",
+            Instruction::ScrollRight => "SCR",
+            Instruction::ScrollLeft => "SCL",
+            Instruction::Exit => "EXIT",
+            Instruction::LowRes => "LOW",
+            Instruction::HighRes => "HIGH",
+            Instruction::CycleBackground => "BGC",
+            Instruction::AddNibbles(..) => "ADDN",
+            Instruction::SecondKeyDown(_) => "SKP2",
+            Instruction::SecondKeyUp(_) => "SKNP2",
+            Instruction::OutputPort(_) => "OUT",
+            Instruction::InputPort(_) => "IN",
+            Instruction::MegaOff => "MEGAOFF",
+            Instruction::MegaOn => "MEGAON",
+            Instruction::SetIHuge(_) => "LDHI",
+            Instruction::LoadPalette(_) => "LDPAL",
+            Instruction::SetSpriteWidth(_) => "SPRW",
+            Instruction::SetSpriteHeight(_) => "SPRH",
+            Instruction::SetScreenAlpha(_) => "ALPHA",
+            Instruction::PlaySound(_) => "DIGISND",
+            Instruction::StopSound => "STOPSND",
+            Instruction::SetBlendMode(_) => "BMODE",
+            Instruction::SetCollisionColor(_) => "CCOL",
+            Instruction::MegaScrollUp(_) => "SCRU",
+            Instruction::SetRegVal(..)
+            | Instruction::SetRegReg(..)
+            | Instruction::SetI(_)
+            | Instruction::SetRegDelay(_)
+            | Instruction::SetRegKey(_)
+            | Instruction::SetDelayReg(_)
+            | Instruction::SetSoundReg(_)
+            | Instruction::SetISpriteReg(_)
+            | Instruction::Bcd(_)
+            | Instruction::Dump(_)
+            | Instruction::Load(_)
+            | Instruction::SetIBigSpriteReg(_)
+            | Instruction::StoreFlags(_)
+            | Instruction::LoadFlags(_)
+            | Instruction::SetILong => "LD",
+            Instruction::Unknown(_) => "DB",
+        }
+    }
+
+    /// The X register operand, encoded in the second nibble
+    pub fn register_x(&self) -> Option<Register> {
+        match *self {
+            Instruction::RegEqVal(x, _)
+            | Instruction::RegNeqVal(x, _)
+            | Instruction::RegEqReg(x, _)
+            | Instruction::SetRegVal(x, _)
+            | Instruction::AddRegVal(x, _)
+            | Instruction::SetRegReg(x, _)
+            | Instruction::SetRegOrReg(x, _)
+            | Instruction::SetRegAndReg(x, _)
+            | Instruction::SetRegXorReg(x, _)
+            | Instruction::AddRegReg(x, _)
+            | Instruction::SubRegReg(x, _)
+            | Instruction::ShiftRegRight(x, _)
+            | Instruction::RevRegSubReg(x, _)
+            | Instruction::ShiftRegLeft(x, _)
+            | Instruction::RegNeqReg(x, _)
+            | Instruction::SetRegRand(x, _)
+            | Instruction::Draw(x, _, _)
+            | Instruction::KeyUp(x)
+            | Instruction::KeyDown(x)
+            | Instruction::SetRegDelay(x)
+            | Instruction::SetRegKey(x)
+            | Instruction::SetDelayReg(x)
+            | Instruction::SetSoundReg(x)
+            | Instruction::AddIReg(x)
+            | Instruction::SetISpriteReg(x)
+            | Instruction::Bcd(x)
+            | Instruction::Dump(x)
+            | Instruction::Load(x)
+            | Instruction::SetIBigSpriteReg(x)
+            | Instruction::StoreFlags(x)
+            | Instruction::LoadFlags(x)
+            | Instruction::SaveRange(x, _)
+            | Instruction::LoadRange(x, _)
+            | Instruction::SetPitch(x)
+            | Instruction::AddNibbles(x, _)
+            | Instruction::SecondKeyDown(x)
+            | Instruction::SecondKeyUp(x)
+            | Instruction::OutputPort(x)
+            | Instruction::InputPort(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The Y register operand, encoded in the third nibble
+    pub fn register_y(&self) -> Option<Register> {
+        match *self {
+            Instruction::RegEqReg(_, y)
+            | Instruction::SetRegReg(_, y)
+            | Instruction::SetRegOrReg(_, y)
+            | Instruction::SetRegAndReg(_, y)
+            | Instruction::SetRegXorReg(_, y)
+            | Instruction::AddRegReg(_, y)
+            | Instruction::SubRegReg(_, y)
+            | Instruction::ShiftRegRight(_, y)
+            | Instruction::RevRegSubReg(_, y)
+            | Instruction::ShiftRegLeft(_, y)
+            | Instruction::RegNeqReg(_, y)
+            | Instruction::Draw(_, y, _)
+            | Instruction::SaveRange(_, y)
+            | Instruction::LoadRange(_, y)
+            | Instruction::AddNibbles(_, y) => Some(y),
+            _ => None,
+        }
+    }
+
+    /// The 12 bit address operand (NNN)
+    pub fn address(&self) -> Option<Address> {
+        match *self {
+            Instruction::NoOp(address)
+            | Instruction::Call(address)
+            | Instruction::Jmp(address)
+            | Instruction::SetI(address)
+            | Instruction::JmpOffset(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// The 8 bit immediate operand (NN)
+    pub fn byte(&self) -> Option<u8> {
+        match *self {
+            Instruction::RegEqVal(_, value)
+            | Instruction::RegNeqVal(_, value)
+            | Instruction::SetRegVal(_, value)
+            | Instruction::AddRegVal(_, value)
+            | Instruction::SetRegRand(_, value)
+            | Instruction::SetIHuge(value)
+            | Instruction::LoadPalette(value)
+            | Instruction::SetSpriteWidth(value)
+            | Instruction::SetSpriteHeight(value)
+            | Instruction::SetScreenAlpha(value)
+            | Instruction::SetCollisionColor(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The 4 bit immediate operand (N)
+    pub fn nibble(&self) -> Option<u8> {
+        match *self {
+            Instruction::Draw(_, _, n)
+            | Instruction::ScrollDown(n)
+            | Instruction::ScrollUp(n)
+            | Instruction::SelectPlanes(n)
+            | Instruction::PlaySound(n)
+            | Instruction::SetBlendMode(n)
+            | Instruction::MegaScrollUp(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn every_opcode_encodes_back_to_itself() {
+        for opcode in 0..=0xFFFF {
+            let instruction = Instruction::decode(opcode);
+            if let Instruction::Unknown(_) = instruction {
+                continue;
+            }
+            assert_eq!(
+                instruction.encode(),
+                opcode,
+                "{:04X} decodes to {:?}",
+                opcode,
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn every_opcode_round_trips_through_display_and_from_str() {
+        for opcode in 0..=0xFFFF {
+            let instruction = Instruction::decode(opcode);
+            let text = instruction.to_string();
+            assert_eq!(
+                text.parse::<Instruction>(),
+                Ok(instruction),
+                "{:04X} is written as {}",
+                opcode,
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! The CHIP-8 interpreter and its extensions, shared by every frontend.
+//! It only needs `alloc`, so it builds for targets without `std` when the
+//! default `std` feature is turned off.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod assembly;
+mod chip8;
+mod chip8x;
+mod display;
+mod font;
+#[cfg(feature = "std")]
+mod handle;
+mod host_page;
+mod instruction_decoder;
+mod megachip;
+mod opcode_table;
+mod quirks;
+
+pub use assembly::parse_number;
+pub use chip8::{
+    AudioPattern, Chip8, Chip8Error, Chip8Event, Diagnostic, MemoryAccessPolicy, MemoryInit,
+    TestResult, UnknownOpcodePolicy, MAX_ROM_SIZE, MEGACHIP_MAX_ROM_SIZE,
+};
+pub use font::{Font, FONT_SIZE};
+#[cfg(feature = "std")]
+pub use handle::{Chip8Handle, Frame, Trace};
+pub use host_page::{HostTime, HOST_PAGE_ADDRESS, HOST_PAGE_SIZE};
+pub use instruction_decoder::Instruction;
+pub use megachip::DigitisedSound;
+pub use opcode_table::{opcode_pattern, OpcodeInfo, OPCODE_TABLE};
+pub use quirks::{Platform, Quirks};
@@ -0,0 +1,231 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Resolution of the MEGA-CHIP display
+pub(super) const MEGACHIP_WIDTH: u16 = 256;
+pub(super) const MEGACHIP_HEIGHT: u16 = 192;
+/// MEGA-CHIP addresses 24 bits of memory through `01NN`
+pub(super) const MEGACHIP_MEMORY_SIZE: usize = 0x100_0000;
+/// Bytes before the samples of a digitised sound: a 16 bit sample rate, a
+/// 24 bit length and a reserved byte
+const SOUND_HEADER_SIZE: usize = 6;
+
+/// A digitised sound started by MEGA-CHIP's `060N`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigitisedSound {
+    /// Samples per second
+    pub rate: u16,
+    /// Unsigned 8 bit samples
+    pub samples: Vec<u8>,
+    /// Whether the sound repeats until `0700` stops it
+    pub looped: bool,
+    /// Counts the sounds started, so frontends can tell a sound restarting
+    /// from the same one still playing
+    pub serial: u64,
+}
+
+/// How `DXYN` combines a MEGA-CHIP sprite with what is already drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Blend {
+    Normal,
+    /// The sprite is drawn at 25% opacity
+    Quarter,
+    /// The sprite is drawn at 50% opacity
+    Half,
+    Add,
+    Multiply,
+}
+
+impl Blend {
+    fn from_mode(mode: u8) -> Self {
+        match mode {
+            1 => Blend::Quarter,
+            2 => Blend::Half,
+            3 => Blend::Add,
+            4 => Blend::Multiply,
+            _ => Blend::Normal,
+        }
+    }
+
+    fn apply(self, source: u8, destination: u8) -> u8 {
+        let (source, destination) = (source as u16, destination as u16);
+        (match self {
+            Blend::Normal => source,
+            Blend::Quarter => (source + 3 * destination) / 4,
+            Blend::Half => (source + destination) / 2,
+            Blend::Add => (source + destination).min(255),
+            Blend::Multiply => source * destination / 255,
+        }) as u8
+    }
+}
+
+/// State of the MEGA-CHIP mode, which draws full color sprites to a back
+/// buffer that only reaches the display when `00E0` is executed
+#[derive(Debug, Clone)]
+pub(super) struct MegaChip {
+    /// Switched on by `0011` and off by `0010`
+    pub(super) active: bool,
+    /// Bits 16 to 23 of I, set by `01NN`
+    pub(super) i_high: u8,
+    /// RGB colors loaded by `02NN`, index 0 is transparent
+    palette: [[u8; 3]; 256],
+    /// Sprite size in pixels set by `03NN` and `04NN`
+    pub(super) sprite_width: usize,
+    pub(super) sprite_height: usize,
+    /// Brightness of the whole display set by `05NN`
+    alpha: u8,
+    blend: Blend,
+    /// Palette index that sets VF when a sprite is drawn over it, set by `09NN`
+    collision_color: u8,
+    /// RGBA pixels drawn since the last `00E0`
+    back: Vec<u8>,
+    /// Colors shown by the last `00E0`, with the screen alpha applied
+    front: Vec<[u8; 3]>,
+    /// Palette index of each pixel of `back`, for collisions
+    indexes: Vec<u8>,
+    pub(super) sound: Option<DigitisedSound>,
+    sounds_started: u64,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        let pixels = MEGACHIP_WIDTH as usize * MEGACHIP_HEIGHT as usize;
+        MegaChip {
+            active: false,
+            i_high: 0,
+            palette: [[0; 3]; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            alpha: 255,
+            blend: Blend::Normal,
+            collision_color: 0,
+            back: black(pixels),
+            front: vec![[0; 3]; pixels],
+            indexes: vec![0; pixels],
+            sound: None,
+            sounds_started: 0,
+        }
+    }
+}
+
+impl MegaChip {
+    /// `02NN`: loads ARGB colors from `colors` into the palette, starting
+    /// at index 1. The alpha byte isn't used.
+    pub(super) fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, argb) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3]];
+        }
+    }
+
+    /// `03NN`/`04NN`: a size of 0 means 256
+    pub(super) fn sprite_size(value: u8) -> usize {
+        match value {
+            0 => 256,
+            value => value as usize,
+        }
+    }
+
+    pub(super) fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    pub(super) fn set_blend(&mut self, mode: u8) {
+        self.blend = Blend::from_mode(mode);
+    }
+
+    pub(super) fn set_collision_color(&mut self, index: u8) {
+        self.collision_color = index;
+    }
+
+    /// `060N`: starts the sound whose header is at the start of `data`,
+    /// which runs to the end of memory
+    pub(super) fn play_sound(&mut self, data: &[u8], looped: bool) {
+        if data.len() < SOUND_HEADER_SIZE {
+            return;
+        }
+        let rate = (data[0] as u16) << 8 | data[1] as u16;
+        let length = (data[2] as usize) << 16 | (data[3] as usize) << 8 | data[4] as usize;
+        let samples = &data[SOUND_HEADER_SIZE..];
+
+        self.sounds_started += 1;
+        self.sound = Some(DigitisedSound {
+            rate,
+            samples: samples[..length.min(samples.len())].to_vec(),
+            looped,
+            serial: self.sounds_started,
+        });
+    }
+
+    /// Draws one pixel of a sprite in palette color `index`, returning
+    /// whether it landed on the collision color. Pixels off the display and
+    /// transparent ones are skipped.
+    pub(super) fn plot(&mut self, x: usize, y: usize, index: u8) -> bool {
+        self.plot_color(x, y, index, self.palette[index as usize])
+    }
+
+    /// Draws one pixel of a font glyph, which has no colors of its own so
+    /// is drawn in white as the last palette index
+    pub(super) fn plot_glyph(&mut self, x: usize, y: usize) -> bool {
+        self.plot_color(x, y, 255, [255; 3])
+    }
+
+    fn plot_color(&mut self, x: usize, y: usize, index: u8, color: [u8; 3]) -> bool {
+        let (width, height) = (MEGACHIP_WIDTH as usize, MEGACHIP_HEIGHT as usize);
+        if index == 0 || x >= width || y >= height {
+            return false;
+        }
+
+        let pixel = x + y * width;
+        let under = self.indexes[pixel];
+        let collision = under != 0 && under == self.collision_color;
+        self.indexes[pixel] = index;
+
+        let back = &mut self.back[4 * pixel..4 * pixel + 4];
+        for channel in 0..3 {
+            back[channel] = self.blend.apply(color[channel], back[channel]);
+        }
+        collision
+    }
+
+    /// `00E0`: shows what has been drawn and starts the next frame from black
+    pub(super) fn present(&mut self) {
+        for (pixel, back) in self.front.iter_mut().zip(self.back.chunks_exact(4)) {
+            for channel in 0..3 {
+                pixel[channel] = (back[channel] as u16 * self.alpha as u16 / 255) as u8;
+            }
+        }
+        self.back = black(self.indexes.len());
+        self.indexes.fill(0);
+    }
+
+    /// The colors shown by the last `00E0`, row by row
+    pub(super) fn front(&self) -> &[[u8; 3]] {
+        &self.front
+    }
+
+    /// Moves the back buffer by `(dx, dy)` pixels, blanking what scrolls in
+    pub(super) fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (MEGACHIP_WIDTH as isize, MEGACHIP_HEIGHT as isize);
+        let (back, indexes) = (self.back.clone(), self.indexes.clone());
+
+        for y in 0..height {
+            for x in 0..width {
+                let (source_x, source_y) = (x - dx, y - dy);
+                let pixel = (x + y * width) as usize;
+                let (color, index) =
+                    if (0..width).contains(&source_x) && (0..height).contains(&source_y) {
+                        let source = (source_x + source_y * width) as usize;
+                        (&back[4 * source..4 * source + 4], indexes[source])
+                    } else {
+                        (&[0, 0, 0, 255][..], 0)
+                    };
+                self.back[4 * pixel..4 * pixel + 4].copy_from_slice(color);
+                self.indexes[pixel] = index;
+            }
+        }
+    }
+}
+
+fn black(pixels: usize) -> Vec<u8> {
+    [0, 0, 0, 255].repeat(pixels)
+}
@@ -0,0 +1,136 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::instruction_decoder::Instruction;
+
+/// Describes one opcode the decoder understands. Placeholder nibbles in the
+/// pattern (X, Y, N) are operands, hex digits must match exactly.
+pub struct OpcodeInfo {
+    pub pattern: &'static str,
+    pub description: &'static str,
+}
+
+#[rustfmt::skip]
+pub const OPCODE_TABLE: &[OpcodeInfo] = &[
+    OpcodeInfo { pattern: "00E0", description: "Clear the display" },
+    OpcodeInfo { pattern: "00EE", description: "Return from a subroutine" },
+    OpcodeInfo { pattern: "00FA", description: "Report a test result from V0 (extension, 0 = pass)" },
+    OpcodeInfo { pattern: "00F9", description: "Fail the test unless the following 3XNN would skip (extension)" },
+    OpcodeInfo { pattern: "00CN", description: "Scroll the display down by N pixels (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "00DN", description: "Scroll the selected planes up by N pixels (XO-CHIP)" },
+    OpcodeInfo { pattern: "00FB", description: "Scroll the display right by 4 pixels (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "00FC", description: "Scroll the display left by 4 pixels (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "00FD", description: "Exit the interpreter (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "00FE", description: "Switch to the 64x32 display (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "00FF", description: "Switch to the 128x64 display (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "0010", description: "Leave MEGA-CHIP mode for the 64x32 display (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "0011", description: "Enter MEGA-CHIP mode and its 256x192 full color display (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "00BN", description: "Scroll the display up by N pixels (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "01NN", description: "Set I to the 24 bit address made of NN and the next word (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "02A0", description: "Cycle the background color through blue, black, green and red (CHIP-8X)" },
+    OpcodeInfo { pattern: "02NN", description: "Load NN ARGB colors from I into the palette from index 1 (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "03NN", description: "Set the sprite width to NN, 0 = 256 (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "04NN", description: "Set the sprite height to NN, 0 = 256 (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "05NN", description: "Set the brightness of the display to NN (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "060N", description: "Play the digitised sound at I, looped if N = 0 (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "0700", description: "Stop the digitised sound (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "080N", description: "Set the sprite blend mode: normal, 25%, 50%, add or multiply (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "09NN", description: "Set the palette index sprites collide with to NN (MEGA-CHIP)" },
+    OpcodeInfo { pattern: "0NNN", description: "Call a machine code routine (ignored)" },
+    OpcodeInfo { pattern: "1NNN", description: "Jump to NNN" },
+    OpcodeInfo { pattern: "2NNN", description: "Call the subroutine at NNN" },
+    OpcodeInfo { pattern: "3XNN", description: "Skip the next instruction if VX == NN" },
+    OpcodeInfo { pattern: "4XNN", description: "Skip the next instruction if VX != NN" },
+    OpcodeInfo { pattern: "5XY0", description: "Skip the next instruction if VX == VY" },
+    OpcodeInfo { pattern: "5XY1", description: "Add each nibble of VY to the same nibble of VX, modulo 8 (CHIP-8X)" },
+    OpcodeInfo { pattern: "5XY2", description: "Store VX to VY in memory starting at I, leaving I unchanged (XO-CHIP)" },
+    OpcodeInfo { pattern: "5XY3", description: "Load VX to VY from memory starting at I, leaving I unchanged (XO-CHIP)" },
+    OpcodeInfo { pattern: "6XNN", description: "Set VX to NN" },
+    OpcodeInfo { pattern: "7XNN", description: "Add NN to VX without carry" },
+    OpcodeInfo { pattern: "8XY0", description: "Set VX to VY" },
+    OpcodeInfo { pattern: "8XY1", description: "Set VX to VX | VY" },
+    OpcodeInfo { pattern: "8XY2", description: "Set VX to VX & VY" },
+    OpcodeInfo { pattern: "8XY3", description: "Set VX to VX ^ VY" },
+    OpcodeInfo { pattern: "8XY4", description: "Add VY to VX, VF is set on carry" },
+    OpcodeInfo { pattern: "8XY5", description: "Subtract VY from VX, VF is cleared on borrow" },
+    OpcodeInfo { pattern: "8XY6", description: "Shift VX right by one, VF is set to the shifted out bit" },
+    OpcodeInfo { pattern: "8XY7", description: "Set VX to VY - VX, VF is cleared on borrow" },
+    OpcodeInfo { pattern: "8XYE", description: "Shift VX left by one, VF is set to the shifted out bit" },
+    OpcodeInfo { pattern: "9XY0", description: "Skip the next instruction if VX != VY" },
+    OpcodeInfo { pattern: "ANNN", description: "Set I to NNN" },
+    OpcodeInfo { pattern: "BNNN", description: "Jump to NNN + V0. Colors the display in CHIP-8X, where BXY0 sets the 8x4 zones VX and VX+1 describe to color VY, and BXYN sets N rows of the strip at (VX, VX+1)" },
+    OpcodeInfo { pattern: "CXNN", description: "Set VX to a random byte masked with NN" },
+    OpcodeInfo { pattern: "DXYN", description: "Draw the N byte sprite at I to (VX, VY), VF is set on collision. N = 0 draws a 16x16 sprite (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "EX9E", description: "Skip the next instruction if key VX is down" },
+    OpcodeInfo { pattern: "EXA1", description: "Skip the next instruction if key VX is up" },
+    OpcodeInfo { pattern: "EXF2", description: "Skip the next instruction if key VX on the second keypad is down (CHIP-8X)" },
+    OpcodeInfo { pattern: "EXF5", description: "Skip the next instruction if key VX on the second keypad is up (CHIP-8X)" },
+    OpcodeInfo { pattern: "F000", description: "Set I to the 16 bit address in the next word (XO-CHIP)" },
+    OpcodeInfo { pattern: "FN01", description: "Select the bit planes N for drawing, clearing and scrolling (XO-CHIP)" },
+    OpcodeInfo { pattern: "F002", description: "Load the 16 byte audio pattern from memory starting at I (XO-CHIP)" },
+    OpcodeInfo { pattern: "FX07", description: "Set VX to the delay timer" },
+    OpcodeInfo { pattern: "FX0A", description: "Wait for a key press and store it in VX" },
+    OpcodeInfo { pattern: "FX15", description: "Set the delay timer to VX" },
+    OpcodeInfo { pattern: "FX18", description: "Set the sound timer to VX" },
+    OpcodeInfo { pattern: "FX1E", description: "Add VX to I" },
+    OpcodeInfo { pattern: "FX29", description: "Point I at the font sprite for digit VX" },
+    OpcodeInfo { pattern: "FX30", description: "Point I at the large font sprite for digit VX (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "FX33", description: "Store the BCD digits of VX at I, I + 1 and I + 2" },
+    OpcodeInfo { pattern: "FX3A", description: "Set the audio pattern playback rate from VX (XO-CHIP)" },
+    OpcodeInfo { pattern: "FX55", description: "Store V0 to VX in memory starting at I" },
+    OpcodeInfo { pattern: "FX65", description: "Load V0 to VX from memory starting at I" },
+    OpcodeInfo { pattern: "FX75", description: "Store V0 to VX in the RPL user flags, X < 8 (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "FX85", description: "Load V0 to VX from the RPL user flags, X < 8 (SUPER-CHIP)" },
+    OpcodeInfo { pattern: "FXF8", description: "Send VX to the expansion port (CHIP-8X)" },
+    OpcodeInfo { pattern: "FXFB", description: "Read the expansion port into VX (CHIP-8X)" },
+];
+
+impl OpcodeInfo {
+    /// The opcode with every operand nibble zeroed
+    pub fn base_opcode(&self) -> u16 {
+        self.pattern.chars().fold(0, |opcode, c| {
+            (opcode << 4) | c.to_digit(16).unwrap_or(0) as u16
+        })
+    }
+
+    /// The mnemonic as reported by the decoder itself
+    pub fn mnemonic(&self) -> &'static str {
+        Instruction::decode(self.base_opcode()).mnemonic()
+    }
+
+    /// The names of the operands the decoder extracts for this opcode
+    pub fn operands(&self) -> Vec<&'static str> {
+        let instruction = Instruction::decode(self.base_opcode());
+        let mut operands = vec![];
+
+        if instruction.register_x().is_some() {
+            operands.push("x");
+        }
+        if instruction.register_y().is_some() {
+            operands.push("y");
+        }
+        if instruction.address().is_some() {
+            operands.push("nnn");
+        }
+        if instruction.byte().is_some() {
+            operands.push("nn");
+        }
+        if instruction.nibble().is_some() {
+            operands.push("n");
+        }
+
+        operands
+    }
+}
+
+/// The table pattern an opcode belongs to, e.g. `DXYN` for any draw, or `None`
+/// for words the decoder doesn't understand
+pub fn opcode_pattern(opcode: u16) -> Option<&'static str> {
+    let instruction = mem::discriminant(&Instruction::decode(opcode));
+
+    OPCODE_TABLE
+        .iter()
+        .find(|info| mem::discriminant(&Instruction::decode(info.base_opcode())) == instruction)
+        .map(|info| info.pattern)
+}
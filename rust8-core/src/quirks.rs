@@ -0,0 +1,193 @@
+use alloc::format;
+use alloc::string::String;
+use core::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::chip8::{DEFAULT_CYCLES_PER_FRAME, DEFAULT_MEMORY_SIZE, XOCHIP_MEMORY_SIZE};
+use super::megachip::MEGACHIP_MEMORY_SIZE;
+
+/// Behaviours that differ between CHIP-8 interpreters. ROMs were written
+/// against one particular interpreter, so some only run correctly with the
+/// matching set of quirks. The defaults match what this emulator has always done.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    /// `FX55`/`FX65` leave I pointing past the last register accessed
+    /// (I += X + 1), as the original COSMAC VIP interpreter did
+    pub memory_increment_i: bool,
+    /// `BXNN` jumps to XNN + VX rather than NNN + V0, as on the CHIP-48 and
+    /// SUPER-CHIP
+    pub jump_uses_vx: bool,
+    /// `8XY1`/`8XY2`/`8XY3` set VF to 0, as the COSMAC VIP interpreter did
+    pub logic_resets_vf: bool,
+    /// `8XY6`/`8XYE` shift VY into VX rather than shifting VX in place, as
+    /// the COSMAC VIP interpreter did
+    pub shift_uses_vy: bool,
+    /// `DXYN` cuts sprites off at the left and right edges of the display
+    /// instead of wrapping them around to the other side, as the COSMAC VIP
+    /// interpreter did. The starting position still wraps.
+    pub clip_horizontal: bool,
+    /// `DXYN` cuts sprites off at the top and bottom edges of the display.
+    /// Some interpreters clip one way but wrap the other.
+    pub clip_vertical: bool,
+    /// `DXY0` in low resolution draws an 8x16 sprite rather than a 16x16 one,
+    /// as SUPER-CHIP 1.1 did
+    pub lores_tall_sprites: bool,
+    /// `DXYN` waits for the next 60hz tick before drawing, limiting ROMs to
+    /// one sprite per frame as on the COSMAC VIP
+    pub display_wait: bool,
+    /// `FX29` and `FX30` only look at the low nibble of VX, so values past F
+    /// still point I at a digit, as the lookup tables of most interpreters did.
+    /// Without it I can end up anywhere and a diagnostic is reported.
+    pub font_digit_masked: bool,
+    /// `FX1E` sets VF to 1 when I passes 0xFFF and to 0 otherwise, as the
+    /// Amiga interpreter did. Spacefight 2091! relies on it.
+    pub add_i_sets_vf: bool,
+    /// `FX0A` answers as soon as a new key goes down rather than once it is
+    /// released again, as simpler interpreters do. ROMs that read keys in a
+    /// loop can see a single press twice with it.
+    pub key_wait_on_press: bool,
+}
+
+/// Interpreters with a well known set of quirks, so users can pick the one a
+/// ROM was written for instead of setting each quirk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Platform {
+    /// The original interpreter on the RCA COSMAC VIP
+    #[serde(rename = "vip")]
+    CosmacVip,
+    /// CHIP-8X, the COSMAC VIP interpreter for the VP-590 color board
+    #[serde(rename = "chip8x")]
+    Chip8x,
+    /// The ETI-660's interpreter, with a 64x48 display and programs at 0x600
+    #[serde(rename = "eti660")]
+    Eti660,
+    /// CHIP-48 on the HP-48 calculators
+    #[serde(rename = "chip48")]
+    Chip48,
+    /// SUPER-CHIP 1.1
+    #[serde(rename = "schip")]
+    Schip,
+    /// MEGA-CHIP, SUPER-CHIP with a 256x192 full color mode
+    #[serde(rename = "megachip")]
+    MegaChip,
+    /// XO-CHIP, as implemented by Octo
+    #[serde(rename = "xochip")]
+    XoChip,
+}
+
+impl Platform {
+    pub const NAMES: [&'static str; 7] = [
+        "vip", "chip8x", "eti660", "chip48", "schip", "megachip", "xochip",
+    ];
+
+    /// Instructions per 60hz frame, roughly the speed the interpreter ran at
+    /// on its original hardware
+    pub fn cycles_per_frame(self) -> u32 {
+        match self {
+            Platform::CosmacVip | Platform::Chip8x | Platform::Eti660 => DEFAULT_CYCLES_PER_FRAME,
+            Platform::Chip48 => 15,
+            Platform::Schip => 30,
+            Platform::MegaChip => 1000,
+            Platform::XoChip => 100,
+        }
+    }
+
+    /// Where programs are loaded and start, after the interpreter
+    pub fn program_start(self) -> u16 {
+        match self {
+            Platform::Chip8x => 0x300,
+            Platform::Eti660 => 0x600,
+            _ => 0x200,
+        }
+    }
+
+    /// Bytes of memory, which only XO-CHIP and MEGA-CHIP extend past 4K
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => XOCHIP_MEMORY_SIZE,
+            Platform::MegaChip => MEGACHIP_MEMORY_SIZE,
+            _ => DEFAULT_MEMORY_SIZE,
+        }
+    }
+
+    /// The size of the standard display as `(width, height)`
+    pub fn display_size(self) -> (u16, u16) {
+        match self {
+            Platform::Eti660 => (64, 48),
+            _ => (64, 32),
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::CosmacVip => Quirks {
+                memory_increment_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+                shift_uses_vy: true,
+                clip_horizontal: true,
+                clip_vertical: true,
+                lores_tall_sprites: false,
+                display_wait: true,
+                font_digit_masked: true,
+                add_i_sets_vf: false,
+                key_wait_on_press: false,
+            },
+            Platform::Chip8x | Platform::Eti660 => Platform::CosmacVip.quirks(),
+            Platform::Chip48 => Quirks {
+                memory_increment_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                shift_uses_vy: false,
+                clip_horizontal: true,
+                clip_vertical: true,
+                lores_tall_sprites: false,
+                display_wait: false,
+                font_digit_masked: true,
+                add_i_sets_vf: false,
+                key_wait_on_press: false,
+            },
+            Platform::Schip => Quirks {
+                lores_tall_sprites: true,
+                ..Platform::Chip48.quirks()
+            },
+            Platform::MegaChip => Platform::Schip.quirks(),
+            Platform::XoChip => Quirks {
+                memory_increment_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                shift_uses_vy: true,
+                clip_horizontal: false,
+                clip_vertical: false,
+                lores_tall_sprites: false,
+                display_wait: false,
+                font_digit_masked: true,
+                add_i_sets_vf: false,
+                key_wait_on_press: false,
+            },
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" => Ok(Platform::CosmacVip),
+            "chip8x" => Ok(Platform::Chip8x),
+            "eti660" => Ok(Platform::Eti660),
+            "chip48" => Ok(Platform::Chip48),
+            "schip" => Ok(Platform::Schip),
+            "megachip" => Ok(Platform::MegaChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(format!(
+                "Unknown platform '{}', expected one of {}",
+                s,
+                Platform::NAMES.join(", ")
+            )),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::hardware::{parse_number, Instruction};
+
+/// Address the assembled program is loaded at
+const ORIGIN: u16 = 0x200;
+
+/// A source line with comments and labels stripped
+enum Statement<'a> {
+    Bytes(Vec<&'a str>),
+    Instruction(&'a str, Vec<&'a str>),
+    /// The XO-CHIP `LD I, LONG address`, followed by its 16 bit address
+    LongI(&'a str),
+}
+
+impl Statement<'_> {
+    fn size(&self) -> u16 {
+        match self {
+            Statement::Bytes(values) => values.len() as u16,
+            Statement::Instruction(..) => 2,
+            Statement::LongI(_) => 4,
+        }
+    }
+}
+
+/// Assembles source with one instruction per line into a ROM image.
+///
+/// Besides the instruction syntax accepted by `Instruction::from_str`, lines
+/// may start with a `label:`, contain `; comments`, or emit raw bytes with
+/// `DB 0x12, 0x34`. Labels can be used anywhere an address is expected,
+/// including the 16 bit address of `LD I, LONG address`.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+    let mut address = ORIGIN;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut line = line.split(';').next().unwrap_or("").trim();
+
+        if let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                return Err(format!("line {}: invalid label '{}'", line_number, label));
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(format!("line {}: duplicate label '{}'", line_number, label));
+            }
+            line = line[colon + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+        let operands: Vec<&str> = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        let long_address = match operands.as_slice() {
+            [i, long] if mnemonic.eq_ignore_ascii_case("LD") && i.eq_ignore_ascii_case("I") => long
+                .get(..5)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("LONG "))
+                .map(|_| long[5..].trim()),
+            _ => None,
+        };
+
+        let statement = if mnemonic.eq_ignore_ascii_case("DB") {
+            Statement::Bytes(operands)
+        } else if let Some(address) = long_address {
+            Statement::LongI(address)
+        } else {
+            Statement::Instruction(mnemonic, operands)
+        };
+
+        address += statement.size();
+        statements.push((line_number, statement));
+    }
+
+    let mut rom = vec![];
+
+    for (line_number, statement) in statements {
+        match statement {
+            Statement::Bytes(values) => {
+                for value in values {
+                    match parse_number(&value.to_ascii_uppercase()) {
+                        Some(byte) if byte <= 0xFF => rom.push(byte as u8),
+                        _ => return Err(format!("line {}: invalid byte '{}'", line_number, value)),
+                    }
+                }
+            }
+            Statement::Instruction(mnemonic, operands) => {
+                let operands: Vec<String> = operands
+                    .iter()
+                    .map(|operand| match labels.get(operand) {
+                        Some(address) => format!("{:#05X}", address),
+                        None => operand.to_string(),
+                    })
+                    .collect();
+
+                let text = format!("{} {}", mnemonic, operands.join(", "));
+                let instruction: Instruction = text
+                    .parse()
+                    .map_err(|e| format!("line {}: {}", line_number, e))?;
+
+                rom.extend_from_slice(&instruction.encode().to_be_bytes());
+            }
+            Statement::LongI(operand) => {
+                let address = match labels.get(operand) {
+                    Some(&address) => address,
+                    None => parse_number(&operand.to_ascii_uppercase()).ok_or_else(|| {
+                        format!("line {}: invalid address '{}'", line_number, operand)
+                    })?,
+                };
+
+                rom.extend_from_slice(&Instruction::SetILong.encode().to_be_bytes());
+                rom.extend_from_slice(&address.to_be_bytes());
+            }
+        }
+    }
+
+    Ok(rom)
+}
+
+/// Assembles `input` and writes the ROM to `output`, returning the process exit code
+pub fn run_assemble(input: &Path, output: &Path) -> i32 {
+    let source = match fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", input.display(), e);
+            return 2;
+        }
+    };
+
+    let rom = match assemble(&source) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}: {}", input.display(), e);
+            return 1;
+        }
+    };
+
+    if let Err(e) = fs::write(output, &rom) {
+        eprintln!("Failed to write {}: {}", output.display(), e);
+        return 2;
+    }
+
+    println!("Wrote {} bytes to {}", rom.len(), output.display());
+    0
+}
@@ -0,0 +1,413 @@
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+
+use crate::config::AudioConfig;
+use crate::hardware::{AudioPattern, DigitisedSound};
+
+const SAMPLE_RATE: u32 = 44100;
+/// Samples rendered per 60hz frame by `OfflineAudio`
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+/// Length of an XO-CHIP audio pattern in one bit samples
+const PATTERN_BITS: f32 = 128.0;
+
+/// Something that sounds the CHIP-8 beep, given the state of the machine's
+/// sound after each step
+pub trait AudioSink {
+    /// Sounds the beep or pattern while `active`, fading it in and out
+    fn set_active(&self, active: bool);
+
+    /// Plays an XO-CHIP audio pattern in place of the square wave, or goes
+    /// back to the square wave when given `None`
+    fn set_pattern(&self, pattern: Option<AudioPattern>);
+}
+
+/// Plays the CHIP-8 beep while the sound timer is running
+pub struct Buzzer {
+    // Audio stops as soon as the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    active: Arc<AtomicBool>,
+    /// Bits of the `f32` volume, so it can be changed while the tone plays
+    volume: Arc<AtomicU32>,
+    pattern: Arc<SharedPattern>,
+    /// The MEGA-CHIP digitised sound playing and the serial it started as
+    digitised: Option<(u64, Sink)>,
+}
+
+/// An XO-CHIP audio pattern packed into atomics, so the audio thread can
+/// read it without locking
+#[derive(Default)]
+struct SharedPattern {
+    enabled: AtomicBool,
+    /// The 128 samples, first sample in the most significant bit of `high`
+    high: AtomicU64,
+    low: AtomicU64,
+    /// Bits of the `f32` playback rate in samples per second
+    rate: AtomicU32,
+}
+
+impl SharedPattern {
+    fn store(&self, pattern: Option<AudioPattern>) {
+        let pattern = match pattern {
+            Some(pattern) => pattern,
+            None => {
+                self.enabled.store(false, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        let mut high = [0; 8];
+        let mut low = [0; 8];
+        high.copy_from_slice(&pattern.pattern[..8]);
+        low.copy_from_slice(&pattern.pattern[8..]);
+
+        self.high.store(u64::from_be_bytes(high), Ordering::Relaxed);
+        self.low.store(u64::from_be_bytes(low), Ordering::Relaxed);
+        self.rate
+            .store(pattern.playback_rate().to_bits(), Ordering::Relaxed);
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    fn sample(&self, index: u32) -> bool {
+        let (word, bit) = if index < 64 {
+            (&self.high, 63 - index)
+        } else {
+            (&self.low, 127 - index)
+        };
+        word.load(Ordering::Relaxed) & (1 << bit) != 0
+    }
+}
+
+impl Buzzer {
+    /// Opens the default output device, returning `None` if there isn't one
+    pub fn new(config: &AudioConfig) -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default()
+            .map_err(|e| eprintln!("Audio disabled, no output device: {}", e))
+            .ok()?;
+
+        let active = Arc::new(AtomicBool::new(false));
+        let volume = Arc::new(AtomicU32::new(0));
+        let pattern = Arc::new(SharedPattern::default());
+        let tone = Tone::new(config, active.clone(), volume.clone(), pattern.clone());
+
+        handle
+            .play_raw(tone)
+            .map_err(|e| eprintln!("Audio disabled, failed to start playback: {}", e))
+            .ok()?;
+
+        let buzzer = Buzzer {
+            _stream: stream,
+            handle,
+            active,
+            volume,
+            pattern,
+            digitised: None,
+        };
+        buzzer.set_volume(config.volume);
+
+        Some(buzzer)
+    }
+
+    /// Changes the output level, from 0.0 to 1.0
+    pub fn set_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+        if let Some((_, sink)) = &self.digitised {
+            sink.set_volume(volume);
+        }
+    }
+
+    /// Plays a MEGA-CHIP digitised sound alongside the beep, replacing the
+    /// one before, or stops it when given `None`
+    pub fn set_digitised(&mut self, sound: Option<&DigitisedSound>) {
+        let serial = sound.map(|sound| sound.serial);
+        if self.digitised.as_ref().map(|(serial, _)| *serial) == serial {
+            return;
+        }
+        // Dropping the sink stops the sound
+        self.digitised = None;
+
+        let sound = match sound {
+            Some(sound) if !sound.samples.is_empty() => sound,
+            _ => return,
+        };
+        let sink = match Sink::try_new(&self.handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                eprintln!("Failed to play a digitised sound: {}", e);
+                return;
+            }
+        };
+        sink.set_volume(f32::from_bits(self.volume.load(Ordering::Relaxed)));
+
+        let samples: Vec<f32> = sound
+            .samples
+            .iter()
+            .map(|&sample| (sample as f32 - 128.0) / 128.0)
+            .collect();
+        let buffer = SamplesBuffer::new(1, sound.rate.max(1) as u32, samples);
+        if sound.looped {
+            sink.append(buffer.repeat_infinite());
+        } else {
+            sink.append(buffer);
+        }
+        self.digitised = Some((sound.serial, sink));
+    }
+}
+
+impl AudioSink for Buzzer {
+    fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Relaxed);
+    }
+
+    fn set_pattern(&self, pattern: Option<AudioPattern>) {
+        self.pattern.store(pattern);
+    }
+}
+
+/// Renders the beep into memory rather than a sound card, a frame at a
+/// time, so the output can be checked without an audio device
+pub struct OfflineAudio {
+    active: Arc<AtomicBool>,
+    pattern: Arc<SharedPattern>,
+    tone: Tone,
+    pub samples: Vec<f32>,
+}
+
+impl OfflineAudio {
+    pub fn new(config: &AudioConfig) -> Self {
+        let active = Arc::new(AtomicBool::new(false));
+        let volume = Arc::new(AtomicU32::new(config.volume.clamp(0.0, 1.0).to_bits()));
+        let pattern = Arc::new(SharedPattern::default());
+        let tone = Tone::new(config, active.clone(), volume, pattern.clone());
+
+        OfflineAudio {
+            active,
+            pattern,
+            tone,
+            samples: vec![],
+        }
+    }
+
+    /// Renders the next 60hz frame of audio onto the end of `samples`
+    pub fn render_frame(&mut self) {
+        self.samples
+            .extend(self.tone.by_ref().take(SAMPLES_PER_FRAME));
+    }
+}
+
+impl AudioSink for OfflineAudio {
+    fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Relaxed);
+    }
+
+    fn set_pattern(&self, pattern: Option<AudioPattern>) {
+        self.pattern.store(pattern);
+    }
+}
+
+/// An endless square wave, shaped by an attack/release envelope so the tone
+/// fades in and out instead of clicking when the sound timer starts and stops
+struct Tone {
+    active: Arc<AtomicBool>,
+    volume: Arc<AtomicU32>,
+    pattern: Arc<SharedPattern>,
+    phase: f32,
+    /// Position in the audio pattern, in samples of the pattern
+    pattern_position: f32,
+    phase_step: f32,
+    level: f32,
+    attack_step: f32,
+    release_step: f32,
+    /// Smoothing factor of the one pole low-pass filter, if enabled
+    low_pass: Option<f32>,
+    filtered: f32,
+}
+
+impl Tone {
+    fn new(
+        config: &AudioConfig,
+        active: Arc<AtomicBool>,
+        volume: Arc<AtomicU32>,
+        pattern: Arc<SharedPattern>,
+    ) -> Self {
+        let sample_rate = SAMPLE_RATE as f32;
+
+        // Number of samples to ramp over, at least one so the steps stay finite
+        let ramp_step = |ms: f32| 1.0 / (ms / 1000.0 * sample_rate).max(1.0);
+
+        let low_pass = config.low_pass_hz.map(|cutoff| {
+            let rc = 1.0 / (2.0 * PI * cutoff);
+            let dt = 1.0 / sample_rate;
+            dt / (rc + dt)
+        });
+
+        Tone {
+            active,
+            volume,
+            pattern,
+            phase: 0.0,
+            pattern_position: 0.0,
+            phase_step: config.frequency / sample_rate,
+            level: 0.0,
+            attack_step: ramp_step(config.attack_ms),
+            release_step: ramp_step(config.release_ms),
+            low_pass,
+            filtered: 0.0,
+        }
+    }
+}
+
+impl Iterator for Tone {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.active.load(Ordering::Relaxed) {
+            self.level = (self.level + self.attack_step).min(1.0);
+        } else {
+            self.level = (self.level - self.release_step).max(0.0);
+        }
+
+        let high = if self.pattern.enabled.load(Ordering::Relaxed) {
+            let rate = f32::from_bits(self.pattern.rate.load(Ordering::Relaxed));
+            self.pattern_position =
+                (self.pattern_position + rate / SAMPLE_RATE as f32) % PATTERN_BITS;
+            self.pattern.sample(self.pattern_position as u32)
+        } else {
+            self.phase = (self.phase + self.phase_step).fract();
+            self.phase < 0.5
+        };
+        let square = if high { 1.0 } else { -1.0 };
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        let mut sample = square * self.level * volume;
+
+        if let Some(alpha) = self.low_pass {
+            self.filtered += alpha * (sample - self.filtered);
+            sample = self.filtered;
+        }
+
+        Some(sample)
+    }
+}
+
+impl Source for Tone {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::Chip8;
+
+    /// Renders with no fades or filter, so every sample is exactly on or off
+    fn offline_audio() -> OfflineAudio {
+        OfflineAudio::new(&AudioConfig {
+            volume: 1.0,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            low_pass_hz: None,
+            ..AudioConfig::default()
+        })
+    }
+
+    /// Renders a second of a pattern played throughout
+    fn render_pattern(pattern: AudioPattern) -> Vec<f32> {
+        let mut audio = offline_audio();
+        audio.set_pattern(Some(pattern));
+        audio.set_active(true);
+        for _ in 0..60 {
+            audio.render_frame();
+        }
+        audio.samples
+    }
+
+    fn level_changes(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] > 0.0) != (pair[1] > 0.0))
+            .count()
+    }
+
+    #[test]
+    fn beep_lasts_as_long_as_the_sound_timer() {
+        // LD V0, 30; LD ST, V0; JP self
+        let rom = [0x60, 0x1E, 0xF0, 0x18, 0x12, 0x04];
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        let mut audio = offline_audio();
+
+        for _ in 0..40 {
+            for _ in 0..10 {
+                chip8.step(&[false; 16]).unwrap();
+            }
+            audio.set_active(chip8.is_sound_playing());
+            audio.render_frame();
+            chip8.update_timers();
+        }
+
+        let sounding = audio
+            .samples
+            .iter()
+            .filter(|&&sample| sample != 0.0)
+            .count();
+        assert_eq!(sounding, 30 * SAMPLES_PER_FRAME);
+        assert!(audio.samples[..30 * SAMPLES_PER_FRAME]
+            .iter()
+            .all(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn pattern_plays_at_its_pitch() {
+        // Four samples high and four low, so a level change every four
+        let pattern = [0xF0; 16];
+        for &(pitch, rate) in &[(64, 4000.0), (112, 8000.0)] {
+            let samples = render_pattern(AudioPattern { pattern, pitch });
+            let expected = rate / 4.0;
+            let changes = level_changes(&samples) as f32;
+            assert!(
+                (changes - expected).abs() <= expected * 0.01,
+                "pitch {} changed level {} times, expected {}",
+                pitch,
+                changes,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn pattern_sets_the_waveform() {
+        // The first half of the pattern high and the second half low, each
+        // 64 samples or 16ms at pitch 64
+        let mut pattern = [0; 16];
+        pattern[..8].fill(0xFF);
+        let samples = render_pattern(AudioPattern { pattern, pitch: 64 });
+
+        let half = (SAMPLE_RATE as f32 * 0.016) as usize;
+        assert!(samples[..half - 5].iter().all(|&sample| sample == 1.0));
+        assert!(samples[half + 5..2 * half - 5]
+            .iter()
+            .all(|&sample| sample == -1.0));
+        assert!(samples[2 * half + 5..3 * half - 5]
+            .iter()
+            .all(|&sample| sample == 1.0));
+    }
+}
@@ -0,0 +1,46 @@
+/// Share of a lit pixel's brightness that spills onto the pixels beside it
+const EDGE_STRENGTH: f32 = 0.35;
+/// Diagonal neighbours are further away, so receive less
+const CORNER_STRENGTH: f32 = 0.15;
+
+const NEIGHBOURS: [(isize, isize, f32); 8] = [
+    (-1, 0, EDGE_STRENGTH),
+    (1, 0, EDGE_STRENGTH),
+    (0, -1, EDGE_STRENGTH),
+    (0, 1, EDGE_STRENGTH),
+    (-1, -1, CORNER_STRENGTH),
+    (1, -1, CORNER_STRENGTH),
+    (-1, 1, CORNER_STRENGTH),
+    (1, 1, CORNER_STRENGTH),
+];
+
+/// Makes lit pixels glow onto their neighbours, like the phosphor of an old
+/// monitor. Each channel is brightened to the strongest glow reaching it,
+/// so lit pixels themselves are left as they are.
+pub fn apply(frame: &mut [u8], width: usize) {
+    let height = frame.len() / 4 / width;
+    let source = frame.to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = 4 * (x + y * width);
+
+            for channel in 0..3 {
+                let mut glow = 0.0f32;
+
+                for &(dx, dy, strength) in NEIGHBOURS.iter() {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                        continue;
+                    }
+
+                    let neighbour = 4 * (nx as usize + ny as usize * width) + channel;
+                    glow = glow.max(source[neighbour] as f32 * strength);
+                }
+
+                let value = &mut frame[index + channel];
+                *value = (*value).max(glow as u8);
+            }
+        }
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
+
+use crate::hardware::{
+    Font, MemoryAccessPolicy, MemoryInit, Platform, Quirks, UnknownOpcodePolicy, FONT_SIZE,
+    HOST_PAGE_ADDRESS,
+};
+use crate::palette::{self, Palette};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// User settings, read from `config.toml` in the platform config directory.
+/// Anything missing from the file falls back to its default.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub audio: AudioConfig,
+    pub gamepad: GamepadConfig,
+    pub display: DisplayConfig,
+    pub quirks: Quirks,
+    /// Quirk preset used in place of `quirks` when set
+    pub platform: Option<Platform>,
+    /// Use `quirks` even with a platform set, keeping the platform's other
+    /// settings. Set when a quirk is flipped in the settings menu.
+    pub custom_quirks: bool,
+    /// Restart the ROM when a quirk is flipped in the settings menu, for
+    /// quirks that only matter while a game starts up
+    pub reset_on_quirk_change: bool,
+    /// Whether to skip, halt or stop with an error on an unknown opcode
+    pub unknown_opcodes: UnknownOpcodePolicy,
+    /// Whether memory accesses past the end of memory wrap, saturate or fault
+    pub memory_access: MemoryAccessPolicy,
+    /// Instructions run each 60hz frame in place of the platform's speed
+    pub cycles_per_frame: Option<u32>,
+    /// Bytes of memory in place of the platform's, or 4K without one
+    pub memory_size: Option<usize>,
+    /// What memory outside the fonts and ROM holds at power on
+    pub memory_init: MemoryInit,
+    /// Seed for `memory_init = "random"`, so a failure can be reproduced
+    pub memory_seed: u64,
+    /// Levels of subroutine nesting before a stack overflow, in place of 16
+    pub stack_depth: Option<usize>,
+    /// Keeps the SUPER-CHIP flags each ROM stores with `FX75` on disk, for
+    /// games that save progress in them
+    pub persist_flags: bool,
+    /// Maps the host's clock, a frame counter, a random byte and the last key
+    /// pressed into memory for homebrew that knows to look there
+    pub host_page: bool,
+    /// Where the host page starts in place of 0xFF0, for ROMs long enough
+    /// to reach it. The page stays off if it would overwrite the ROM.
+    pub host_page_address: Option<u16>,
+    /// Small font for `FX29`, one of chip48, vip or dream6800, or the path
+    /// to an 80 byte file of your own
+    pub font: Option<String>,
+    /// Where the small font starts in memory, with the SUPER-CHIP font
+    /// after it. Some interpreters put it at 0x50 rather than 0.
+    pub font_address: u16,
+    /// Settings for individual ROMs, keyed by file name
+    pub roms: BTreeMap<String, RomConfig>,
+    /// Named sets of settings picked with `--profile`, each a table laid
+    /// over the rest of the config, e.g. `[profiles.fast-xochip]` with
+    /// `platform = "xochip"`, `cycles_per_frame = 1000` and
+    /// `display = { scale = 5 }`
+    pub profiles: BTreeMap<String, toml::Value>,
+    /// The profile the config was loaded with
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// Command line settings laid over the config for this run
+    #[serde(skip)]
+    overrides: Overrides,
+    /// The config's own values the overrides replaced, saved in their place
+    #[serde(skip)]
+    replaced: Overrides,
+}
+
+/// Settings given on the command line, which last only for the run they
+/// were given to and are never saved to the config file
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub platform: Option<Platform>,
+    pub font: Option<String>,
+    pub frame_skip: Option<u32>,
+}
+
+/// Host keys for keypad 0 to F. Each player has their own set so two people
+/// can share the keypad, as games like Pong expect.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub player_one: [VirtualKeyCode; 16],
+    pub player_two: [VirtualKeyCode; 16],
+}
+
+impl KeyBindings {
+    /// The keys of player one (0) or player two (1)
+    pub fn player_mut(&mut self, player: usize) -> &mut [VirtualKeyCode; 16] {
+        match player {
+            0 => &mut self.player_one,
+            _ => &mut self.player_two,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            // Maps the 4x4 COSMAC VIP keypad onto the left side of a QWERTY keyboard
+            player_one: [
+                VirtualKeyCode::X,
+                VirtualKeyCode::Key1,
+                VirtualKeyCode::Key2,
+                VirtualKeyCode::Key3,
+                VirtualKeyCode::Q,
+                VirtualKeyCode::W,
+                VirtualKeyCode::E,
+                VirtualKeyCode::A,
+                VirtualKeyCode::S,
+                VirtualKeyCode::D,
+                VirtualKeyCode::Z,
+                VirtualKeyCode::C,
+                VirtualKeyCode::Key4,
+                VirtualKeyCode::R,
+                VirtualKeyCode::F,
+                VirtualKeyCode::V,
+            ],
+            player_two: [
+                VirtualKeyCode::Numpad0,
+                VirtualKeyCode::Numpad1,
+                VirtualKeyCode::Numpad2,
+                VirtualKeyCode::Numpad3,
+                VirtualKeyCode::Numpad4,
+                VirtualKeyCode::Numpad5,
+                VirtualKeyCode::Numpad6,
+                VirtualKeyCode::Numpad7,
+                VirtualKeyCode::Numpad8,
+                VirtualKeyCode::Numpad9,
+                VirtualKeyCode::NumpadDivide,
+                VirtualKeyCode::NumpadMultiply,
+                VirtualKeyCode::NumpadSubtract,
+                VirtualKeyCode::NumpadAdd,
+                VirtualKeyCode::NumpadEnter,
+                VirtualKeyCode::NumpadDecimal,
+            ],
+        }
+    }
+}
+
+/// Settings for the beep played while the sound timer is running
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Output level from 0.0 to 1.0
+    pub volume: f32,
+    /// Pitch of the tone in hertz
+    pub frequency: f32,
+    /// Time taken to fade the tone in, which avoids a click when it starts
+    pub attack_ms: f32,
+    /// Time taken to fade the tone out, which avoids a pop when it stops
+    pub release_ms: f32,
+    /// Cutoff of an optional low-pass filter that softens the square wave
+    pub low_pass_hz: Option<f32>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            volume: 0.25,
+            frequency: 440.0,
+            attack_ms: 5.0,
+            release_ms: 10.0,
+            low_pass_hz: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// Pulse the rumble motors of connected gamepads while the sound timer runs
+    pub rumble: bool,
+    /// Rumble intensity from 0.0 to 1.0
+    pub rumble_strength: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            rumble: false,
+            rumble_strength: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Lit pixels glow onto their neighbours, like an old monitor
+    pub bloom: bool,
+    /// Index into `palettes` of the palette in use
+    pub palette: usize,
+    /// Palettes to cycle through with F6 or the settings menu
+    pub palettes: Vec<Palette>,
+    /// Palettes from the palettes directory, cycled through after
+    /// `palettes`. Kept out of the config file since they have their own.
+    #[serde(skip)]
+    pub shared_palettes: Vec<Palette>,
+    /// Presents only every Nth frame, for hosts too slow to draw every one.
+    /// Every frame is still emulated, so games keep their speed. 0 and 1
+    /// present every frame.
+    pub frame_skip: u32,
+    /// Cross-fades from one emulated frame to the next on monitors that
+    /// refresh faster than 60hz, for smoother motion at the cost of up to a
+    /// frame of latency
+    pub interpolate: bool,
+    /// Starting window size as a multiple of the 64x32 display
+    pub scale: u32,
+    /// Opens the window borderless fullscreen on the current monitor
+    pub fullscreen: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            bloom: false,
+            palette: 0,
+            palettes: palette::default_palettes(),
+            shared_palettes: vec![],
+            frame_skip: 1,
+            interpolate: false,
+            scale: 10,
+            fullscreen: false,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// The palette in use, if the index points at one
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palettes
+            .iter()
+            .chain(&self.shared_palettes)
+            .nth(self.palette)
+    }
+
+    /// Whether emulated frame `number` should be presented
+    pub fn presents(&self, number: u64) -> bool {
+        number.is_multiple_of(self.frame_skip.max(1) as u64)
+    }
+
+    /// Moves to the next palette, or the previous one if `forward` is false
+    pub fn cycle_palette(&mut self, forward: bool) {
+        let count = (self.palettes.len() + self.shared_palettes.len()).max(1);
+        let step = if forward { 1 } else { count - 1 };
+        self.palette = (self.palette + step) % count;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomConfig {
+    /// Patches applied in memory after the ROM is loaded, leaving the file
+    /// untouched. Written as `address=value`, like `--set` of the patch subcommand.
+    pub patches: Vec<String>,
+}
+
+/// Directory holding the config file and any other user data
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust8"))
+}
+
+impl Config {
+    /// The quirks of the chosen platform, or the individual quirks if there
+    /// is none or they were customised
+    pub fn quirks(&self) -> Quirks {
+        match self.platform {
+            Some(platform) if !self.custom_quirks => platform.quirks(),
+            _ => self.quirks,
+        }
+    }
+
+    /// Instructions run each 60hz frame, the configured speed or else the
+    /// platform's
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame.unwrap_or_else(|| {
+            self.platform
+                .unwrap_or(Platform::CosmacVip)
+                .cycles_per_frame()
+        })
+    }
+
+    /// Where the host page goes, or `None` with it turned off
+    pub fn host_page(&self) -> Option<u16> {
+        if self.host_page {
+            Some(self.host_page_address.unwrap_or(HOST_PAGE_ADDRESS))
+        } else {
+            None
+        }
+    }
+
+    /// The small font to build machines with, either the bundled one `font`
+    /// names or the contents of the file it points to
+    pub fn font(&self) -> Result<[u8; FONT_SIZE], String> {
+        let name = match &self.font {
+            Some(name) => name,
+            None => return Ok(*Font::default().bytes()),
+        };
+        if let Ok(font) = name.parse::<Font>() {
+            return Ok(*font.bytes());
+        }
+
+        let bytes = fs::read(name).map_err(|e| {
+            format!(
+                "Font {} is neither one of {} nor a readable file: {}",
+                name,
+                Font::NAMES.join(", "),
+                e
+            )
+        })?;
+        bytes.as_slice().try_into().map_err(|_| {
+            format!(
+                "Font {} is {} bytes, expected {}",
+                name,
+                bytes.len(),
+                FONT_SIZE
+            )
+        })
+    }
+
+    /// Lays the command line's settings over the config. Saving keeps the
+    /// config's own values for any still in effect.
+    pub fn apply_overrides(&mut self, overrides: Overrides) {
+        self.replaced = Overrides {
+            platform: self.platform,
+            font: self.font.clone(),
+            frame_skip: Some(self.display.frame_skip),
+        };
+        if overrides.platform.is_some() {
+            self.platform = overrides.platform;
+        }
+        if overrides.font.is_some() {
+            self.font = overrides.font.clone();
+        }
+        if let Some(frame_skip) = overrides.frame_skip {
+            self.display.frame_skip = frame_skip;
+        }
+        self.overrides = overrides;
+    }
+
+    /// Loads the config file, falling back to the defaults if it is missing
+    /// or invalid, along with the palette files
+    pub fn load() -> Self {
+        let mut config = Config::load_file();
+        config.display.shared_palettes = palette::load_palette_files();
+        config
+    }
+
+    /// Loads the config with the named profile laid over it, or as `load`
+    /// does without one. Fails if there is no such profile or the profile
+    /// leaves the config invalid.
+    pub fn load_with_profile(profile: Option<&str>) -> Result<Self, String> {
+        let name = match profile {
+            Some(name) => name,
+            None => return Ok(Config::load()),
+        };
+
+        let path = config_dir()
+            .ok_or("No config directory on this platform")?
+            .join(CONFIG_FILE_NAME);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut table: toml::value::Table = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+
+        let profiles = table.get("profiles").and_then(toml::Value::as_table);
+        let overrides = profiles
+            .and_then(|profiles| profiles.get(name))
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = profiles
+                    .into_iter()
+                    .flat_map(|profiles| profiles.keys())
+                    .map(String::as_str)
+                    .collect();
+                format!(
+                    "No profile '{}' in {}, expected one of: {}",
+                    name,
+                    path.display(),
+                    names.join(", ")
+                )
+            })?;
+        merge_tables(&mut table, overrides);
+
+        let config: Config = toml::Value::Table(table).try_into().map_err(|e| {
+            format!(
+                "Invalid config {} with the profile '{}': {}",
+                path.display(),
+                name,
+                e
+            )
+        })?;
+        let mut config = config.validated();
+        config.display.shared_palettes = palette::load_palette_files();
+        config.profile = Some(name.to_string());
+        Ok(config)
+    }
+
+    fn load_file() -> Self {
+        let path = match config_dir() {
+            Some(dir) => dir.join(CONFIG_FILE_NAME),
+            None => return Config::default(),
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Config::default(),
+        };
+
+        toml::from_str(&contents)
+            .map(Config::validated)
+            .unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid config {}: {}", path.display(), e);
+                Config::default()
+            })
+    }
+
+    /// Drops settings that would leave the machine unable to run, saying
+    /// why, so one bad value doesn't stop the emulator from starting
+    fn validated(mut self) -> Self {
+        let program_start = self
+            .platform
+            .map_or(Platform::CosmacVip.program_start(), Platform::program_start)
+            as usize;
+        let largest = Platform::MegaChip.memory_size();
+        if let Some(size) = self.memory_size {
+            if size <= program_start || size > largest {
+                eprintln!(
+                    "Ignoring memory_size {:#X}, which must be past the program start at {:#X} and at most {:#X}",
+                    size, program_start, largest
+                );
+                self.memory_size = None;
+            }
+        }
+        self
+    }
+
+    /// Writes the config back to the config file, creating its directory if
+    /// needed. Refused while a profile is in use, since its settings would be
+    /// saved over the ones it was laid on.
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(profile) = &self.profile {
+            return Err(format!(
+                "Settings aren't saved while using the profile '{}'",
+                profile
+            ));
+        }
+        let dir = config_dir().ok_or("No config directory on this platform")?;
+        let path = dir.join(CONFIG_FILE_NAME);
+
+        // Going through a `Value` writes each table's plain values before its
+        // subtables, which TOML requires whatever order the fields are in
+        let contents = toml::Value::try_from(self)
+            .and_then(|mut value| {
+                if let toml::Value::Table(table) = &mut value {
+                    self.restore_replaced(table)?;
+                }
+                toml::to_string_pretty(&value)
+            })
+            .map_err(|e| e.to_string())?;
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, contents))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Puts the config's own values back into `table` wherever a command
+    /// line override is still in effect. One changed since, say in the
+    /// settings menu, is kept.
+    fn restore_replaced(&self, table: &mut toml::value::Table) -> Result<(), toml::ser::Error> {
+        let (overrides, replaced) = (&self.overrides, &self.replaced);
+        if overrides.platform.is_some() && self.platform == overrides.platform {
+            set_or_remove(table, "platform", replaced.platform)?;
+        }
+        if overrides.font.is_some() && self.font == overrides.font {
+            set_or_remove(table, "font", replaced.font.as_ref())?;
+        }
+        if overrides.frame_skip == Some(self.display.frame_skip) {
+            if let Some(toml::Value::Table(display)) = table.get_mut("display") {
+                set_or_remove(display, "frame_skip", replaced.frame_skip)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets `key` in `table` to `value`, or removes it for `None`, which TOML
+/// has no way to write
+fn set_or_remove<T: Serialize>(
+    table: &mut toml::value::Table,
+    key: &str,
+    value: Option<T>,
+) -> Result<(), toml::ser::Error> {
+    match value {
+        Some(value) => {
+            table.insert(key.to_string(), toml::Value::try_from(value)?);
+        }
+        None => {
+            table.remove(key);
+        }
+    }
+    Ok(())
+}
+
+/// Lays `overrides` over `base`, merging tables present in both so a profile
+/// can change one setting of a section without repeating the rest
+fn merge_tables(base: &mut toml::value::Table, overrides: toml::value::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => {
+                merge_tables(base, value)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The table `save` would write for `config`
+    fn saved(config: &Config) -> toml::value::Table {
+        let mut table = match toml::Value::try_from(config).unwrap() {
+            toml::Value::Table(table) => table,
+            _ => unreachable!(),
+        };
+        config.restore_replaced(&mut table).unwrap();
+        table
+    }
+
+    #[test]
+    fn command_line_overrides_are_not_saved() {
+        let mut config = Config {
+            font: Some("vip".to_string()),
+            ..Config::default()
+        };
+        config.apply_overrides(Overrides {
+            platform: Some(Platform::XoChip),
+            font: Some("dream6800".to_string()),
+            frame_skip: Some(3),
+        });
+        assert_eq!(config.platform, Some(Platform::XoChip));
+
+        let table = saved(&config);
+        assert!(!table.contains_key("platform"));
+        assert_eq!(table["font"].as_str(), Some("vip"));
+        assert_eq!(table["display"]["frame_skip"].as_integer(), Some(1));
+
+        // A setting changed after the override is the user's to keep
+        config.platform = Some(Platform::Schip);
+        assert_eq!(saved(&config)["platform"].as_str(), Some("schip"));
+    }
+}
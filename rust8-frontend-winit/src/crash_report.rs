@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::hardware::{Chip8, Chip8Error, Instruction};
+
+/// Instructions kept in the trace written with a crash report
+const TRACE_LENGTH: usize = 64;
+
+/// The most recently executed instructions, oldest first
+pub struct TraceTail {
+    entries: VecDeque<(u16, u16)>,
+}
+
+impl TraceTail {
+    pub fn new() -> Self {
+        TraceTail {
+            entries: VecDeque::with_capacity(TRACE_LENGTH),
+        }
+    }
+
+    /// Notes the instruction about to execute
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.entries.len() == TRACE_LENGTH {
+            self.entries.pop_front();
+        }
+        self.entries
+            .push_back((chip8.program_counter(), chip8.opcode()));
+    }
+}
+
+/// Saves the error, the machine state, the trace tail and a raw dump of
+/// memory to the crashes folder of the config directory, returning the path
+/// of the report. The memory dump sits next to it with a `.bin` extension.
+pub fn save(error: &Chip8Error, chip8: &Chip8, trace: &TraceTail) -> Result<PathBuf, String> {
+    let dir = config::config_dir()
+        .ok_or("No config directory on this platform")?
+        .join("crashes");
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let report_path = dir.join(format!("crash-{}.txt", stamp));
+    let memory_path = report_path.with_extension("bin");
+
+    let mut report = format!("{}\n\n{:#?}\n\nLast instructions:\n", error, chip8);
+    for &(address, opcode) in &trace.entries {
+        writeln!(
+            report,
+            "  {:#05X}: {:04X} {}",
+            address,
+            opcode,
+            Instruction::decode(opcode)
+        )
+        .unwrap();
+    }
+
+    fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&report_path, report))
+        .and_then(|_| fs::write(&memory_path, chip8.memory()))
+        .map_err(|e| {
+            format!(
+                "Failed to save crash report {}: {}",
+                report_path.display(),
+                e
+            )
+        })?;
+
+    Ok(report_path)
+}
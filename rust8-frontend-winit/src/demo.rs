@@ -0,0 +1,160 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// First line of every demo file
+const HEADER: &str = "rust8-demo 1";
+
+/// Something that happened at a given instruction count while recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DemoEvent {
+    /// The held keypad keys changed, as a bitmask with key 0 in the lowest bit
+    Keys(u16),
+    /// The 60hz timers ticked
+    TimerTick,
+}
+
+/// Recorded inputs for a ROM. Timer ticks are stored alongside key changes so
+/// playback follows exactly the same path regardless of host speed.
+pub struct Demo {
+    pub seed: u64,
+    events: Vec<(u64, DemoEvent)>,
+}
+
+pub fn key_mask(key_states: &[bool; 16]) -> u16 {
+    key_states
+        .iter()
+        .enumerate()
+        .fold(0, |mask, (i, &held)| mask | (held as u16) << i)
+}
+
+pub fn key_states(mask: u16) -> [bool; 16] {
+    let mut key_states = [false; 16];
+    for (i, held) in key_states.iter_mut().enumerate() {
+        *held = mask & (1 << i) != 0;
+    }
+    key_states
+}
+
+impl Demo {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let invalid = |line: usize| format!("{}:{}: invalid demo file", path.display(), line + 1);
+
+        let mut lines = contents.lines().enumerate();
+
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(invalid(0)),
+        }
+
+        let seed = match lines.next() {
+            Some((i, line)) => line
+                .strip_prefix("seed ")
+                .and_then(|seed| seed.parse().ok())
+                .ok_or_else(|| invalid(i))?,
+            None => return Err(invalid(1)),
+        };
+
+        let mut events = vec![];
+        for (i, line) in lines {
+            let mut parts = line.split_whitespace();
+            let cycle = parts.next().and_then(|cycle| cycle.parse().ok());
+            let event = match (parts.next(), parts.next()) {
+                (Some("tick"), None) => Some(DemoEvent::TimerTick),
+                (Some("keys"), Some(mask)) => {
+                    u16::from_str_radix(mask, 16).ok().map(DemoEvent::Keys)
+                }
+                _ => None,
+            };
+
+            match (cycle, event) {
+                (Some(cycle), Some(event)) => events.push((cycle, event)),
+                _ => return Err(invalid(i)),
+            }
+        }
+
+        Ok(Demo { seed, events })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut contents = format!("{}\nseed {}\n", HEADER, self.seed);
+
+        for (cycle, event) in &self.events {
+            match event {
+                DemoEvent::Keys(mask) => writeln!(contents, "{} keys {:04X}", cycle, mask),
+                DemoEvent::TimerTick => writeln!(contents, "{} tick", cycle),
+            }
+            .unwrap();
+        }
+
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Captures inputs and timer ticks as they happen
+pub struct DemoRecorder {
+    demo: Demo,
+    last_keys: u16,
+}
+
+impl DemoRecorder {
+    /// Starts a recording of a core whose generator was seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        DemoRecorder {
+            demo: Demo {
+                seed,
+                events: vec![],
+            },
+            last_keys: 0,
+        }
+    }
+
+    /// Records the key states used for the instruction about to execute
+    pub fn record_keys(&mut self, cycle: u64, key_states: &[bool; 16]) {
+        let keys = key_mask(key_states);
+        if keys != self.last_keys {
+            self.demo.events.push((cycle, DemoEvent::Keys(keys)));
+            self.last_keys = keys;
+        }
+    }
+
+    pub fn record_timer_tick(&mut self, cycle: u64) {
+        self.demo.events.push((cycle, DemoEvent::TimerTick));
+    }
+
+    pub fn demo(&self) -> &Demo {
+        &self.demo
+    }
+}
+
+/// Replays a demo's events in step with the core's instruction count
+pub struct DemoPlayer {
+    position: usize,
+}
+
+impl Default for DemoPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DemoPlayer {
+    pub fn new() -> Self {
+        DemoPlayer { position: 0 }
+    }
+
+    /// Returns the events due before the instruction at `cycle` executes
+    pub fn events_until<'a>(&mut self, demo: &'a Demo, cycle: u64) -> &'a [(u64, DemoEvent)] {
+        let start = self.position;
+        while self.position < demo.events.len() && demo.events[self.position].0 <= cycle {
+            self.position += 1;
+        }
+        &demo.events[start..self.position]
+    }
+
+    pub fn is_finished(&self, demo: &Demo) -> bool {
+        self.position >= demo.events.len()
+    }
+}
@@ -0,0 +1,64 @@
+//! Turns the core's display into the RGBA frame buffers the window, image
+//! exports and frame sinks show. The core only keeps a palette index for
+//! each pixel and leaves the pixel format to the frontend.
+
+use crate::hardware::Chip8;
+use crate::palette::Palette;
+
+/// Colors of the core's palette indexes, which have a bit for each XO-CHIP
+/// plane. Plane 1 alone is white, so plain CHIP-8 ROMs draw white on a
+/// transparent background.
+const DEFAULT_COLORS: [[u8; 4]; 4] = [
+    [0; 4],
+    [255, 255, 255, 255],
+    [0x55, 0x55, 0x55, 255],
+    [0xAA, 0xAA, 0xAA, 255],
+];
+
+/// Writes the display into `frame`, an RGBA frame buffer of the display's
+/// size, in the default colors or MEGA-CHIP's own
+pub fn present(chip8: &Chip8, frame: &mut [u8]) {
+    fill(chip8, frame, |_, _, index| DEFAULT_COLORS[index as usize]);
+}
+
+/// Like `present`, but in the CHIP-8X color board's colors or else in
+/// `palette`, for showing in the window
+pub fn present_colored(chip8: &Chip8, frame: &mut [u8], palette: Option<&Palette>) {
+    let (width, _) = chip8.display_size();
+    let colors = palette.map_or(DEFAULT_COLORS, |palette| palette.colors.map(opaque));
+
+    match chip8.chip8x_colors() {
+        Some(map) => fill(chip8, frame, |x, y, index| {
+            opaque(map.color(x, y, width, index != 0))
+        }),
+        None => fill(chip8, frame, |_, _, index| colors[index as usize]),
+    }
+}
+
+/// Colors every pixel of `frame` with `color`, given its position and
+/// palette index. MEGA-CHIP's color mode has colors of its own instead. A
+/// frame not yet resized to the display is left alone.
+fn fill(chip8: &Chip8, frame: &mut [u8], color: impl Fn(u16, u16, u8) -> [u8; 4]) {
+    let (width, height) = chip8.display_size();
+    if frame.len() != 4 * width as usize * height as usize {
+        return;
+    }
+
+    let color_frame = chip8.color_frame();
+    for (index, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let rgba = match color_frame {
+            Some(colors) => opaque(colors.get(index).copied().unwrap_or_default()),
+            None => {
+                let (x, y) = (index % width as usize, index / width as usize);
+                let (x, y) = (x as u16, y as u16);
+                color(x, y, chip8.pixel(x, y))
+            }
+        };
+        pixel.copy_from_slice(&rgba);
+    }
+}
+
+/// An RGB color with full alpha
+fn opaque([r, g, b]: [u8; 3]) -> [u8; 4] {
+    [r, g, b, 255]
+}
@@ -0,0 +1,36 @@
+use crate::hardware::{Chip8, Chip8Error};
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Resolution of the error panel, large enough to fit a full register dump
+pub const WIDTH: u16 = 128;
+pub const HEIGHT: u16 = 64;
+
+/// Registers shown on each line of the dump
+const REGISTERS_PER_LINE: usize = 4;
+
+/// Draws the error along with the machine state it left behind into a frame
+/// of `WIDTH * HEIGHT` RGBA pixels
+pub fn draw(frame: &mut [u8], error: &Chip8Error, chip8: &Chip8) {
+    let width = WIDTH as usize;
+    overlay::clear(frame, BACKGROUND_COLOR);
+
+    let mut lines = overlay::wrap_text(&error.to_string(), width);
+    lines.push(String::new());
+
+    for (line, registers) in chip8.registers().chunks(REGISTERS_PER_LINE).enumerate() {
+        let dump: Vec<String> = registers
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("V{:X} {:02X}", line * REGISTERS_PER_LINE + i, value))
+            .collect();
+        lines.push(dump.join(" "));
+    }
+    lines.push(format!("I {:#05X}", chip8.i()));
+
+    lines.push(String::new());
+    lines.push("PRESS R TO RESET".to_string());
+
+    for (i, line) in lines.iter().enumerate() {
+        overlay::draw_text(frame, width, 1, 1 + i * LINE_ADVANCE, line, TEXT_COLOR);
+    }
+}
@@ -0,0 +1,251 @@
+//! Arithmetic over the machine state for watches, e.g. `mem[I+1] * 10 + mem[I+2]`.
+//!
+//! Operands are numbers in any notation the assembler accepts, the registers
+//! `V0` to `VF`, `I`, `PC`, `DT`, `ST` and memory reads `mem[address]`.
+//! Operators follow the usual precedence: `* / %`, then `+ -`, then `& ^ |`.
+
+use std::fmt;
+
+use crate::hardware::{parse_number, Chip8};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Number(i64),
+    Register(usize),
+    I,
+    ProgramCounter,
+    DelayTimer,
+    SoundTimer,
+    Memory(Box<Expression>),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    And,
+    Or,
+    Xor,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Subtract),
+            '*' => Some(Operator::Multiply),
+            '/' => Some(Operator::Divide),
+            '%' => Some(Operator::Remainder),
+            '&' => Some(Operator::And),
+            '|' => Some(Operator::Or),
+            '^' => Some(Operator::Xor),
+            _ => None,
+        }
+    }
+
+    /// Higher binds tighter
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Multiply | Operator::Divide | Operator::Remainder => 3,
+            Operator::Add | Operator::Subtract => 2,
+            Operator::And | Operator::Or | Operator::Xor => 1,
+        }
+    }
+
+    fn apply(self, left: i64, right: i64) -> i64 {
+        match self {
+            Operator::Add => left.wrapping_add(right),
+            Operator::Subtract => left.wrapping_sub(right),
+            Operator::Multiply => left.wrapping_mul(right),
+            // Dividing by zero gives zero rather than taking the emulator down
+            Operator::Divide => left.checked_div(right).unwrap_or(0),
+            Operator::Remainder => left.checked_rem(right).unwrap_or(0),
+            Operator::And => left & right,
+            Operator::Or => left | right,
+            Operator::Xor => left ^ right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExpressionError(String);
+
+impl fmt::Display for ParseExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A number, register or other name, upper cased
+    Word(String),
+    Operator(Operator),
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '$' || c == '#' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '$' || c == '#') {
+                    break;
+                }
+                word.push(c.to_ascii_uppercase());
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '(' => Token::OpenParen,
+                ')' => Token::CloseParen,
+                '[' => Token::OpenBracket,
+                ']' => Token::CloseBracket,
+                c => Token::Operator(
+                    Operator::from_char(c)
+                        .ok_or_else(|| ParseExpressionError(format!("Unexpected '{}'", c)))?,
+                ),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), ParseExpressionError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(ParseExpressionError(format!("Expected {}", what))),
+        }
+    }
+
+    /// Parses operators binding at least as tightly as `min_precedence`
+    fn expression(&mut self, min_precedence: u8) -> Result<Expression, ParseExpressionError> {
+        let mut left = self.operand()?;
+
+        while let Some(&Token::Operator(operator)) = self.peek() {
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.next();
+
+            let right = self.expression(operator.precedence() + 1)?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expression, ParseExpressionError> {
+        match self.next() {
+            Some(Token::OpenParen) => {
+                let inner = self.expression(0)?;
+                self.expect(Token::CloseParen, "')'")?;
+                Ok(inner)
+            }
+            Some(Token::Operator(Operator::Subtract)) => {
+                Ok(Expression::Negate(Box::new(self.operand()?)))
+            }
+            Some(Token::Word(word)) if word == "MEM" => {
+                self.expect(Token::OpenBracket, "'[' after mem")?;
+                let address = self.expression(0)?;
+                self.expect(Token::CloseBracket, "']'")?;
+                Ok(Expression::Memory(Box::new(address)))
+            }
+            Some(Token::Word(word)) => word_operand(&word),
+            _ => Err(ParseExpressionError("Expected a value".to_string())),
+        }
+    }
+}
+
+fn word_operand(word: &str) -> Result<Expression, ParseExpressionError> {
+    match word {
+        "I" => return Ok(Expression::I),
+        "PC" => return Ok(Expression::ProgramCounter),
+        "DT" => return Ok(Expression::DelayTimer),
+        "ST" => return Ok(Expression::SoundTimer),
+        _ => (),
+    }
+
+    if let Some(register) = word.strip_prefix('V') {
+        if register.len() == 1 {
+            if let Ok(register) = usize::from_str_radix(register, 16) {
+                return Ok(Expression::Register(register));
+            }
+        }
+    }
+
+    parse_number(word)
+        .map(|value| Expression::Number(value as i64))
+        .ok_or_else(|| ParseExpressionError(format!("Unknown value '{}'", word)))
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, ParseExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+
+        let expression = parser.expression(0)?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(_) => Err(ParseExpressionError(
+                "Unexpected input after the expression".to_string(),
+            )),
+        }
+    }
+
+    /// Evaluates the expression against the current state of the machine.
+    /// Memory addresses wrap around the 4K address space.
+    pub fn evaluate(&self, chip8: &Chip8) -> i64 {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::Register(register) => chip8.registers()[*register] as i64,
+            Expression::I => chip8.i() as i64,
+            Expression::ProgramCounter => chip8.program_counter() as i64,
+            Expression::DelayTimer => chip8.delay_timer() as i64,
+            Expression::SoundTimer => chip8.sound_timer() as i64,
+            Expression::Memory(address) => {
+                let memory = chip8.memory();
+                let address = address.evaluate(chip8).rem_euclid(memory.len() as i64);
+                memory[address as usize] as i64
+            }
+            Expression::Negate(inner) => inner.evaluate(chip8).wrapping_neg(),
+            Expression::Binary(left, operator, right) => {
+                operator.apply(left.evaluate(chip8), right.evaluate(chip8))
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Keeps the SUPER-CHIP RPL user flags of each ROM on disk, so games that
+//! save progress in them remember it between runs. ROMs are told apart by
+//! SHA-1, so a renamed file keeps its flags.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::config_dir;
+use crate::sha1::sha1;
+
+fn flags_path(rom: &[u8]) -> Option<PathBuf> {
+    let digest: String = sha1(rom)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    config_dir().map(|dir| dir.join("flags").join(format!("{}.bin", digest)))
+}
+
+/// The flags last stored by the ROM, or `None` if it never stored any
+pub fn load(rom: &[u8]) -> Option<Vec<u8>> {
+    fs::read(flags_path(rom)?).ok()
+}
+
+pub fn save(rom: &[u8], flags: &[u8]) -> Result<(), String> {
+    let path = flags_path(rom).ok_or("No config directory on this platform")?;
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, flags))
+        .map_err(|e| format!("Failed to save flags to {}: {}", path.display(), e))
+}
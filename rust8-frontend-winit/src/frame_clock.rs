@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+/// Timing of one emulated frame, shared by everything that runs once a frame
+/// so they all agree on the same clock
+#[derive(Debug, Clone, Copy)]
+pub struct FrameInfo {
+    /// Frames emulated since the clock started, starting from zero
+    pub number: u64,
+    /// Emulated time at the start of this frame
+    pub emulated_time: Duration,
+    /// Instructions executed during the previous frame
+    pub cycles: u64,
+    /// Frames skipped so far because the host fell behind
+    pub dropped: u64,
+}
+
+/// Divides wall-clock time into fixed length emulated frames. When the host
+/// falls behind the missed frames are dropped rather than run back to back.
+pub struct FrameClock {
+    interval: Duration,
+    next_frame: Instant,
+    number: u64,
+    cycles: u64,
+    dropped: u64,
+}
+
+impl FrameClock {
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        FrameClock {
+            interval,
+            next_frame: now + interval,
+            number: 0,
+            cycles: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Starts counting again from frame zero, e.g. when a new ROM is loaded
+    pub fn reset(&mut self, now: Instant) {
+        *self = FrameClock::new(self.interval, now);
+    }
+
+    /// Carries on counting from frame `number`, e.g. when a save state is loaded
+    pub fn restore(&mut self, number: u64, now: Instant) {
+        self.reset(now);
+        self.number = number;
+    }
+
+    /// Number of the next frame to start
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Notes that an instruction was executed during the current frame
+    pub fn count_cycle(&mut self) {
+        self.cycles += 1;
+    }
+
+    /// When the next frame is due
+    pub fn deadline(&self) -> Instant {
+        self.next_frame
+    }
+
+    /// How far through the current frame `now` is, from 0 at its start to 1
+    /// once the next one is due
+    pub fn progress(&self, now: Instant) -> f32 {
+        let remaining = self.next_frame.saturating_duration_since(now);
+        1.0 - (remaining.as_secs_f32() / self.interval.as_secs_f32()).min(1.0)
+    }
+
+    /// Returns the frame that starts now, if one is due
+    pub fn tick(&mut self, now: Instant) -> Option<FrameInfo> {
+        if now < self.next_frame {
+            return None;
+        }
+
+        let late = now - self.next_frame;
+        let missed = (late.as_nanos() / self.interval.as_nanos()) as u64;
+
+        let info = FrameInfo {
+            number: self.number,
+            emulated_time: self.interval * self.number as u32,
+            cycles: self.cycles,
+            dropped: self.dropped + missed,
+        };
+
+        self.number += 1;
+        self.cycles = 0;
+        self.dropped += missed;
+        self.next_frame += self.interval * (missed + 1) as u32;
+
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn frames_start_on_the_interval_grid() {
+        let start = Instant::now();
+        let mut clock = FrameClock::new(INTERVAL, start);
+
+        assert!(clock.tick(start + Duration::from_millis(9)).is_none());
+        let first = clock.tick(start + Duration::from_millis(13)).unwrap();
+        assert_eq!(first.number, 0);
+
+        // Starting the first frame late doesn't push the second one back
+        assert_eq!(clock.deadline(), start + Duration::from_millis(20));
+    }
+
+    #[test]
+    fn missed_frames_are_counted_as_dropped() {
+        let start = Instant::now();
+        let mut clock = FrameClock::new(INTERVAL, start);
+
+        let frame = clock.tick(start + Duration::from_millis(35)).unwrap();
+        assert_eq!(frame.number, 0);
+        assert_eq!(frame.dropped, 2);
+        assert_eq!(clock.deadline(), start + Duration::from_millis(40));
+
+        let frame = clock.tick(start + Duration::from_millis(40)).unwrap();
+        assert_eq!(frame.number, 1);
+        assert_eq!(frame.dropped, 2);
+    }
+
+    #[test]
+    fn cycles_are_reported_for_the_previous_frame() {
+        let start = Instant::now();
+        let mut clock = FrameClock::new(INTERVAL, start);
+
+        clock.count_cycle();
+        clock.count_cycle();
+        assert_eq!(clock.tick(start + INTERVAL).unwrap().cycles, 2);
+        assert_eq!(clock.tick(start + INTERVAL * 2).unwrap().cycles, 0);
+    }
+
+    #[test]
+    fn progress_runs_from_the_start_of_the_frame_to_the_next() {
+        let start = Instant::now();
+        let clock = FrameClock::new(INTERVAL, start);
+
+        assert_eq!(clock.progress(start), 0.0);
+        assert!((clock.progress(start + INTERVAL / 2) - 0.5).abs() < 0.01);
+        assert_eq!(clock.progress(start + INTERVAL * 3), 1.0);
+    }
+}
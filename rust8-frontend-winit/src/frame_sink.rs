@@ -0,0 +1,70 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::png;
+
+/// A completed display frame, as presented once per 60hz tick
+pub struct Frame<'a> {
+    /// Frames since the ROM started
+    pub number: u64,
+    pub width: usize,
+    pub height: usize,
+    /// RGBA pixels, `width * height * 4` bytes
+    pub pixels: &'a [u8],
+    /// Whether the buzzer is sounding during the frame
+    pub sound: bool,
+}
+
+/// Something that consumes every frame, such as a recorder or exporter.
+/// Frontends call each sink once per frame, so captures don't read the frame
+/// buffer at their own times.
+pub trait FrameSink {
+    fn frame(&mut self, frame: &Frame) -> Result<(), String>;
+}
+
+/// Writes every frame to a directory as numbered PNGs, for turning into a
+/// video with an external tool. The frames the buzzer sounded in are listed
+/// in `sound.txt` alongside them.
+pub struct PngSequence {
+    directory: PathBuf,
+    sound: File,
+}
+
+impl PngSequence {
+    pub fn create(directory: &Path) -> Result<Self, String> {
+        let sound_path = directory.join("sound.txt");
+        let sound = fs::create_dir_all(directory)
+            .and_then(|_| File::create(&sound_path))
+            .map_err(|e| format!("Failed to create {}: {}", sound_path.display(), e))?;
+
+        Ok(PngSequence {
+            directory: directory.to_path_buf(),
+            sound,
+        })
+    }
+}
+
+impl FrameSink for PngSequence {
+    fn frame(&mut self, frame: &Frame) -> Result<(), String> {
+        // Transparent pixels are black already, so alpha can be left out
+        let rgb: Vec<u8> = frame
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel[..3].iter().copied())
+            .collect();
+        let image = png::encode_rgb(frame.width as u32, frame.height as u32, &rgb);
+
+        let path = self
+            .directory
+            .join(format!("frame_{:06}.png", frame.number));
+        fs::write(&path, image)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        if frame.sound {
+            writeln!(self.sound, "{}", frame.number)
+                .map_err(|e| format!("Failed to write sound.txt: {}", e))?;
+        }
+        Ok(())
+    }
+}
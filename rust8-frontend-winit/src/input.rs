@@ -0,0 +1,414 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, ErrorKind, Read};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::demo::{self, Demo, DemoEvent, DemoPlayer};
+
+/// A source of keypad input, so a new source only needs to implement this.
+/// The headless runner asks it for the held keys before every instruction,
+/// the window once at the start of each frame, so there a change due partway
+/// through a frame takes effect from the next one.
+pub trait KeyProvider {
+    /// The keys held from the instruction about to execute, `cycle` being the
+    /// number of instructions executed so far
+    fn keys(&mut self, cycle: u64) -> [bool; 16];
+}
+
+/// Fixed key states, such as those read from the keyboard each frame
+impl KeyProvider for [bool; 16] {
+    fn keys(&mut self, _cycle: u64) -> [bool; 16] {
+        *self
+    }
+}
+
+/// The key presses recorded in a demo file. Timer ticks are left to the
+/// frontend, so playback only matches the recording if it ticks at the same rate.
+pub struct ReplayKeys {
+    demo: Demo,
+    player: DemoPlayer,
+    keys: [bool; 16],
+}
+
+impl ReplayKeys {
+    pub fn new(demo: Demo) -> Self {
+        ReplayKeys {
+            demo,
+            player: DemoPlayer::new(),
+            keys: [false; 16],
+        }
+    }
+}
+
+impl KeyProvider for ReplayKeys {
+    fn keys(&mut self, cycle: u64) -> [bool; 16] {
+        for (_, event) in self.player.events_until(&self.demo, cycle) {
+            if let DemoEvent::Keys(mask) = *event {
+                self.keys = demo::key_states(mask);
+            }
+        }
+        self.keys
+    }
+}
+
+/// A key held from one instruction count until another
+struct Press {
+    key: usize,
+    start: u64,
+    end: u64,
+}
+
+/// Keys held over fixed ranges of instructions, written as `KEY@START-END`
+/// with the key in hex, e.g. `5@1000-1200` holds 5 for 200 instructions
+pub struct ScriptedKeys {
+    presses: Vec<Press>,
+}
+
+impl ScriptedKeys {
+    pub fn parse(presses: &[String]) -> Result<Self, String> {
+        let presses = presses
+            .iter()
+            .map(|press| {
+                let invalid = || format!("Invalid key press '{}', expected KEY@START-END", press);
+
+                let (key, range) = press.split_once('@').ok_or_else(invalid)?;
+                let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+
+                let key = usize::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|&key| key < 16)
+                    .ok_or_else(invalid)?;
+                let start = start.parse().map_err(|_| invalid())?;
+                let end = end.parse().map_err(|_| invalid())?;
+
+                Ok(Press { key, start, end })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(ScriptedKeys { presses })
+    }
+}
+
+impl KeyProvider for ScriptedKeys {
+    fn keys(&mut self, cycle: u64) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for press in &self.presses {
+            if (press.start..press.end).contains(&cycle) {
+                keys[press.key] = true;
+            }
+        }
+        keys
+    }
+}
+
+/// A client connected to `NetworkKeys`, with any partial line it has sent
+struct Client {
+    stream: TcpStream,
+    pending: String,
+    mask: u16,
+}
+
+/// Keys sent by TCP clients, one line per change holding the key bitmask in
+/// hex as in demo files (`0020` holds key 5). Keys held by any client count
+/// as held, and a client's keys are released when it disconnects.
+pub struct NetworkKeys {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl NetworkKeys {
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+
+        Ok(NetworkKeys {
+            listener,
+            clients: vec![],
+        })
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, peer)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                eprintln!("Key client connected from {}", peer);
+                self.clients.push(Client {
+                    stream,
+                    pending: String::new(),
+                    mask: 0,
+                });
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Reads whatever the client has sent, returning false once it disconnects
+    fn read(&mut self) -> bool {
+        let mut buffer = [0; 256];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return false,
+                Ok(length) => self
+                    .pending
+                    .push_str(&String::from_utf8_lossy(&buffer[..length])),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+
+        while let Some(newline) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=newline).collect();
+            match u16::from_str_radix(line.trim(), 16) {
+                Ok(mask) => self.mask = mask,
+                Err(_) => eprintln!("Ignoring invalid key mask '{}'", line.trim()),
+            }
+        }
+        true
+    }
+}
+
+impl KeyProvider for NetworkKeys {
+    fn keys(&mut self, _cycle: u64) -> [bool; 16] {
+        self.accept();
+        self.clients.retain_mut(Client::read);
+
+        let mask = self
+            .clients
+            .iter()
+            .fold(0, |mask, client| mask | client.mask);
+        demo::key_states(mask)
+    }
+}
+
+/// A key change read from standard input, with the instruction count it is
+/// due at or `None` to apply it as soon as it is read
+type KeyEvent = (Option<u64>, u16);
+
+/// Parses `CYCLE keys MASK` or `keys MASK`, the mask in hex as in demo files
+fn parse_key_event(line: &str) -> Option<KeyEvent> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let (cycle, mask) = match parts.as_slice() {
+        [cycle, "keys", mask] => (Some(cycle.parse().ok()?), mask),
+        ["keys", mask] => (None, mask),
+        _ => return None,
+    };
+    Some((cycle, u16::from_str_radix(mask, 16).ok()?))
+}
+
+/// Keys piped in on standard input, so a bot in any language can play by
+/// writing lines such as `1200 keys 0020` (hold key 5 from instruction 1200)
+/// or `keys 0000` (release everything now). Lines are read on their own
+/// thread so the frontend never blocks on a quiet bot.
+pub struct StdinKeys {
+    events: Receiver<KeyEvent>,
+    pending: VecDeque<KeyEvent>,
+    /// Wait for the bot rather than running ahead of it, see `spawn`
+    lockstep: bool,
+    closed: bool,
+    mask: u16,
+}
+
+impl StdinKeys {
+    /// Starts reading standard input. With `lockstep` set, `keys` waits until
+    /// the bot has sent an event due after the current instruction or closed
+    /// its end, so headless runs don't depend on how quickly the bot answers.
+    pub fn spawn(lockstep: bool) -> Self {
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_key_event(&line) {
+                    Some(event) => {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    None => eprintln!("Ignoring invalid key event '{}'", line.trim()),
+                }
+            }
+        });
+
+        StdinKeys::new(events, lockstep)
+    }
+
+    fn new(events: Receiver<KeyEvent>, lockstep: bool) -> Self {
+        StdinKeys {
+            events,
+            pending: VecDeque::new(),
+            lockstep,
+            closed: false,
+            mask: 0,
+        }
+    }
+}
+
+impl KeyProvider for StdinKeys {
+    fn keys(&mut self, cycle: u64) -> [bool; 16] {
+        loop {
+            self.pending.extend(self.events.try_iter());
+
+            while let Some(&(due, mask)) = self.pending.front() {
+                if due.is_some_and(|due| due > cycle) {
+                    break;
+                }
+                self.mask = mask;
+                self.pending.pop_front();
+            }
+
+            if !self.lockstep || self.closed || !self.pending.is_empty() {
+                break;
+            }
+            match self.events.recv() {
+                Ok(event) => self.pending.push_back(event),
+                Err(_) => self.closed = true,
+            }
+        }
+
+        demo::key_states(self.mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    use crate::hardware::Chip8;
+
+    /// Steps a ROM that waits for a key into V3 with FX0A then spins,
+    /// returning V3 once the wait is over
+    fn wait_for_key(keys: &mut dyn KeyProvider, cycles: u64) -> Option<u8> {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0xF3, 0x0A, 0x12, 0x02]).unwrap();
+
+        for cycle in 0..cycles {
+            chip8.step(&keys.keys(cycle)).unwrap();
+            if chip8.program_counter() == 0x202 {
+                return Some(chip8.registers()[3]);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn scripted_keys_satisfy_a_key_wait() {
+        let mut keys = ScriptedKeys::parse(&["5@10-20".to_string()]).unwrap();
+        assert_eq!(wait_for_key(&mut keys, 100), Some(5));
+    }
+
+    #[test]
+    fn scripted_keys_reject_keys_past_f() {
+        assert!(ScriptedKeys::parse(&["10@0-5".to_string()]).is_err());
+        assert!(ScriptedKeys::parse(&["5@0".to_string()]).is_err());
+    }
+
+    #[test]
+    fn replayed_keys_satisfy_a_key_wait() {
+        let path = std::env::temp_dir().join("rust8-replay-key-wait.demo");
+        std::fs::write(&path, "rust8-demo 1\nseed 0\n10 keys 0400\n20 keys 0000\n").unwrap();
+        let demo = Demo::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut keys = ReplayKeys::new(demo.unwrap());
+        assert_eq!(wait_for_key(&mut keys, 100), Some(0xA));
+    }
+
+    #[test]
+    fn key_events_parse_with_and_without_a_cycle() {
+        assert_eq!(
+            parse_key_event("1200 keys 0020"),
+            Some((Some(1200), 0x0020))
+        );
+        assert_eq!(parse_key_event("keys 0000"), Some((None, 0)));
+        assert_eq!(parse_key_event("1200 keys"), None);
+        assert_eq!(parse_key_event("keys 0020 now"), None);
+    }
+
+    #[test]
+    fn stdin_keys_apply_events_once_due() {
+        let (sender, events) = mpsc::channel();
+        let mut keys = StdinKeys::new(events, false);
+        sender.send((Some(10), 0x0020)).unwrap();
+        sender.send((Some(20), 0x0000)).unwrap();
+
+        assert!(!keys.keys(9)[5]);
+        assert!(keys.keys(10)[5]);
+        assert!(keys.keys(19)[5]);
+        assert!(!keys.keys(20)[5]);
+
+        // Untimed events apply as soon as they're read
+        sender.send((None, 0x0001)).unwrap();
+        assert!(keys.keys(21)[0]);
+    }
+
+    #[test]
+    fn stdin_keys_satisfy_a_key_wait() {
+        let (sender, events) = mpsc::channel();
+        let mut keys = StdinKeys::new(events, false);
+        sender.send((Some(10), 0x0080)).unwrap();
+        sender.send((Some(20), 0x0000)).unwrap();
+        assert_eq!(wait_for_key(&mut keys, 100), Some(7));
+    }
+
+    #[test]
+    fn lockstep_stdin_keys_wait_for_the_bot() {
+        let (sender, events) = mpsc::channel();
+        let mut keys = StdinKeys::new(events, true);
+
+        // The answer only arrives after `keys` has started waiting for it
+        let bot = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            sender.send((Some(0), 0x0004)).unwrap();
+            sender.send((Some(5), 0x0000)).unwrap();
+        });
+        assert!(keys.keys(0)[2]);
+        bot.join().unwrap();
+
+        // Once the bot hangs up there's nothing left to wait for
+        assert!(!keys.keys(100)[2]);
+    }
+
+    #[test]
+    fn network_keys_satisfy_a_key_wait() {
+        let mut keys = NetworkKeys::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(keys.listener.local_addr().unwrap()).unwrap();
+
+        // The client is only heard from once accepted and read, which the
+        // provider does without blocking, so poll until it takes effect
+        client.write_all(b"0020\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !keys.keys(0)[5] {
+            assert!(Instant::now() < deadline, "key press never arrived");
+            thread::sleep(Duration::from_millis(1));
+        }
+        client.write_all(b"0000\n").unwrap();
+        while keys.keys(0)[5] {
+            assert!(Instant::now() < deadline, "key release never arrived");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Disconnecting releases whatever the client held
+        client.write_all(b"0001\n").unwrap();
+        while !keys.keys(0)[0] {
+            assert!(Instant::now() < deadline, "key press never arrived");
+            thread::sleep(Duration::from_millis(1));
+        }
+        drop(client);
+        while keys.keys(0)[0] {
+            assert!(
+                Instant::now() < deadline,
+                "disconnect never released the key"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::mem;
+
+/// Draws the image partway between two emulated frames, for monitors that
+/// present several times within each one
+pub trait FrameInterpolator {
+    /// Writes into `frame` the image `progress` of the way from `previous`
+    /// to `current`, all RGBA frame buffers of the same size
+    fn interpolate(&mut self, previous: &[u8], current: &[u8], progress: f32, frame: &mut [u8]);
+}
+
+/// Fades linearly from one frame to the next
+pub struct Blend;
+
+impl FrameInterpolator for Blend {
+    fn interpolate(&mut self, previous: &[u8], current: &[u8], progress: f32, frame: &mut [u8]) {
+        for ((pixel, &from), &to) in frame.iter_mut().zip(previous).zip(current) {
+            *pixel = (from as f32 + (to as f32 - from as f32) * progress).round() as u8;
+        }
+    }
+}
+
+/// Keeps the last two images presented and hands them to an interpolator
+/// on every redraw
+pub struct Interpolation {
+    interpolator: Box<dyn FrameInterpolator>,
+    previous: Vec<u8>,
+    current: Vec<u8>,
+    number: u64,
+}
+
+impl Interpolation {
+    pub fn new(interpolator: Box<dyn FrameInterpolator>) -> Self {
+        Interpolation {
+            interpolator,
+            previous: vec![],
+            current: vec![],
+            number: 0,
+        }
+    }
+
+    /// Replaces `frame`, the image as of emulated frame `number`, with the
+    /// image `progress` of the way to it from the image of an earlier frame.
+    /// Returns whether the image is still changing and needs redrawing.
+    pub fn apply(&mut self, frame: &mut [u8], number: u64, progress: f32) -> bool {
+        if number != self.number || self.current.len() != frame.len() {
+            self.previous = mem::replace(&mut self.current, frame.to_vec());
+            self.number = number;
+        } else {
+            // Redrawn within the same frame, e.g. after a resize
+            self.current.copy_from_slice(frame);
+        }
+
+        // Nothing to fade from after a resolution change
+        if progress >= 1.0 || self.previous.len() != frame.len() {
+            return false;
+        }
+
+        self.interpolator
+            .interpolate(&self.previous, &self.current, progress, frame);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_frames_fade_in_from_the_last_one() {
+        let mut interpolation = Interpolation::new(Box::new(Blend));
+
+        let mut frame = [0; 4];
+        assert!(!interpolation.apply(&mut frame, 1, 0.5));
+
+        let mut frame = [200; 4];
+        assert!(interpolation.apply(&mut frame, 2, 0.25));
+        assert_eq!(frame, [50; 4]);
+
+        let mut frame = [200; 4];
+        assert!(!interpolation.apply(&mut frame, 2, 1.0));
+        assert_eq!(frame, [200; 4]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+use crate::rom_file::read_rom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Title,
+    Playing,
+}
+
+/// Cycles through every ROM in a directory, showing a title card before each
+/// game and moving on after a fixed time or once the ROM halts
+pub struct Playlist {
+    roms: Vec<PathBuf>,
+    index: usize,
+    play_time: Duration,
+    title_time: Duration,
+    phase: Phase,
+    phase_started: Instant,
+}
+
+impl Playlist {
+    pub fn from_dir(dir: &Path, play_time: Duration, title_time: Duration) -> Result<Self, String> {
+        let entries =
+            fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+        let mut roms: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        roms.sort();
+
+        if roms.is_empty() {
+            return Err(format!("No ROMs found in {}", dir.display()));
+        }
+
+        Ok(Playlist {
+            roms,
+            index: 0,
+            play_time,
+            title_time,
+            phase: Phase::Title,
+            phase_started: Instant::now(),
+        })
+    }
+
+    /// Display name of the current ROM, taken from its file name
+    pub fn current_name(&self) -> String {
+        let path = &self.roms[self.index];
+        path.file_stem()
+            .unwrap_or_else(|| path.as_os_str())
+            .to_string_lossy()
+            .replace('_', " ")
+    }
+
+    pub fn current_path(&self) -> &Path {
+        &self.roms[self.index]
+    }
+
+    pub fn showing_title(&self) -> bool {
+        self.phase == Phase::Title
+    }
+
+    /// Moves between the title card and the game, returning the ROM to load
+    /// when it is time for the next game to start
+    pub fn update(&mut self, now: Instant, halted: bool) -> Option<Vec<u8>> {
+        let elapsed = now - self.phase_started;
+
+        match self.phase {
+            Phase::Title if elapsed >= self.title_time => {
+                self.phase_started = now;
+
+                match read_rom(&self.roms[self.index]) {
+                    Ok(rom) => {
+                        self.phase = Phase::Playing;
+                        return Some(rom);
+                    }
+                    Err(e) => {
+                        eprintln!("Skipping ROM: {}", e);
+                        self.advance();
+                    }
+                }
+            }
+            Phase::Playing if elapsed >= self.play_time || halted => {
+                self.phase = Phase::Title;
+                self.phase_started = now;
+                self.advance();
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    /// When the current title card or game is due to end
+    pub fn deadline(&self) -> Instant {
+        match self.phase {
+            Phase::Title => self.phase_started + self.title_time,
+            Phase::Playing => self.phase_started + self.play_time,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.index = (self.index + 1) % self.roms.len();
+    }
+
+    /// Draws the attract-style card announcing the upcoming game
+    pub fn draw_title_card(&self, frame: &mut [u8], width: usize) {
+        overlay::clear(frame, BACKGROUND_COLOR);
+
+        let mut lines = vec!["NEXT UP".to_string(), String::new()];
+        lines.extend(
+            overlay::wrap_text(&self.current_name(), width)
+                .into_iter()
+                .take(2),
+        );
+        lines.push(format!("{} OF {}", self.index + 1, self.roms.len()));
+
+        for (i, line) in lines.iter().enumerate() {
+            overlay::draw_text(frame, width, 1, 1 + i * LINE_ADVANCE, line, TEXT_COLOR);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::hardware::{Chip8, Instruction};
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Measures how long a host key event takes to reach the ROM, from the window
+/// event to the first `EX9E`, `EXA1` or `FX0A` that reads the key. Shows how
+/// tick batching and the event loop's sleeps delay input.
+pub struct LatencyMeter {
+    held: [bool; 16],
+    /// When each key last changed state, until an instruction reads it
+    pending: [Option<Instant>; 16],
+    last: Option<Duration>,
+    total: Duration,
+    samples: u32,
+}
+
+impl LatencyMeter {
+    pub fn new() -> Self {
+        LatencyMeter {
+            held: [false; 16],
+            pending: [None; 16],
+            last: None,
+            total: Duration::ZERO,
+            samples: 0,
+        }
+    }
+
+    /// Notes a host key event for keypad `key`. Repeats of a held key are
+    /// ignored so they don't restart the measurement.
+    pub fn key_event(&mut self, key: usize, pressed: bool, now: Instant) {
+        if self.held[key] != pressed {
+            self.held[key] = pressed;
+            self.pending[key] = Some(now);
+        }
+    }
+
+    /// Checks the instruction about to execute, returning true if it read a
+    /// key with a pending event and so took a new sample
+    pub fn observe(&mut self, chip8: &Chip8, keys: &[bool; 16], now: Instant) -> bool {
+        let read = match Instruction::decode(chip8.opcode()) {
+            Instruction::KeyDown(register) | Instruction::KeyUp(register) => {
+                let mut read = [false; 16];
+                read[(chip8.registers()[register as usize] & 0xF) as usize] = true;
+                read
+            }
+            // Only a press that is held when FX0A runs is seen by it
+            Instruction::SetRegKey(_) => *keys,
+            _ => return false,
+        };
+
+        let mut sampled = false;
+        for (pending, _) in self.pending.iter_mut().zip(read).filter(|(_, read)| *read) {
+            if let Some(since) = pending.take() {
+                let latency = now - since;
+                self.last = Some(latency);
+                self.total += latency;
+                self.samples += 1;
+                sampled = true;
+            }
+        }
+        sampled
+    }
+
+    /// Draws the last and average latency in the bottom left of the frame
+    pub fn draw(&self, frame: &mut [u8], width: usize) {
+        let height = frame.len() / 4 / width;
+        let top = height.saturating_sub(2 * LINE_ADVANCE);
+        overlay::clear(&mut frame[4 * top * width..], BACKGROUND_COLOR);
+
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let (last, average) = match self.last {
+            Some(last) => (
+                format!("LAT {:.1}MS", millis(last)),
+                format!("AVG {:.1}MS", millis(self.total) / self.samples as f64),
+            ),
+            None => ("LAT -".to_string(), "AVG -".to_string()),
+        };
+
+        overlay::draw_text(frame, width, 0, top, &last, TEXT_COLOR);
+        overlay::draw_text(frame, width, 0, top + LINE_ADVANCE, &average, TEXT_COLOR);
+    }
+}
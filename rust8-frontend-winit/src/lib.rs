@@ -0,0 +1,1101 @@
+//! The windowed frontend, along with the host side pieces such as config,
+//! demos and key sources that the command line tools share with it
+
+pub mod assembler;
+pub mod audio;
+mod bloom;
+pub mod config;
+mod crash_report;
+pub mod demo;
+pub mod display;
+mod error_screen;
+mod expression;
+mod flag_store;
+mod frame_clock;
+mod frame_sink;
+pub mod input;
+mod interpolation;
+mod kiosk;
+mod latency;
+mod multi;
+mod netplay;
+mod overlay;
+mod palette;
+pub mod patcher;
+pub mod png;
+mod rebind;
+mod rom_database;
+pub mod rom_file;
+mod rumble;
+mod save_states;
+mod search;
+mod session_stats;
+mod settings;
+pub mod sha1;
+mod splash;
+mod watch;
+
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// The interpreter lives in its own crate so other frontends can share it
+use rust8_core as hardware;
+
+pub use kiosk::Playlist;
+pub use multi::run_multi_window;
+pub use netplay::run_netplay;
+pub use watch::{load_watches, Watch};
+
+use audio::{AudioSink, Buzzer};
+use config::{Config, Overrides};
+use crash_report::TraceTail;
+use demo::{Demo, DemoEvent, DemoPlayer, DemoRecorder};
+use frame_clock::{FrameClock, FrameInfo};
+use frame_sink::{Frame, FrameSink, PngSequence};
+use hardware::{Chip8, Chip8Error, Chip8Event, Font, HostTime, Platform};
+use input::{KeyProvider, NetworkKeys, StdinKeys};
+use interpolation::{Blend, Interpolation};
+use latency::LatencyMeter;
+use patcher::Patch;
+use rebind::Rebinding;
+use rom_database::{RomDatabase, RomEntry};
+use rumble::Rumble;
+use save_states::{PickerAction, SaveState, StatePicker, SLOT_COUNT};
+use search::MemorySearch;
+use session_stats::SessionStats;
+use settings::{SettingsAction, SettingsMenu};
+use watch::WatchPanel;
+
+use pixels::wgpu::SwapChainError;
+use pixels::Pixels;
+use pixels::SurfaceTexture;
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Fullscreen, Window, WindowBuilder};
+use winit_input_helper::WinitInputHelper;
+
+/// The window is sized in multiples of the low resolution display
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+const TIMER_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Boots a fresh machine with the ROM loaded and the configured quirks and
+/// patches applied, failing if the ROM doesn't fit in its memory
+fn new_machine(
+    rom: &[u8],
+    config: &Config,
+    patches: &[Patch],
+    strict: bool,
+) -> Result<Chip8, Chip8Error> {
+    // Checked when the window opened, so only a file deleted since fails here
+    let font = config.font().unwrap_or(*Font::default().bytes());
+    let mut chip8 = Chip8::with_font(&font);
+    chip8.set_quirks(config.quirks());
+    chip8.set_cycles_per_frame(config.cycles_per_frame());
+    if let Some(platform) = config.platform {
+        chip8.set_program_start(platform.program_start());
+        let (width, height) = platform.display_size();
+        chip8.set_lores_size(width, height);
+        chip8.set_memory_size(platform.memory_size());
+    }
+    if let Some(size) = config.memory_size {
+        chip8.set_memory_size(size);
+    }
+    if let Some(depth) = config.stack_depth {
+        chip8.set_stack_size(depth);
+    }
+    chip8.set_unknown_opcode_policy(config.unknown_opcodes);
+    chip8.set_memory_access_policy(config.memory_access);
+    chip8.set_chip8x(config.platform == Some(Platform::Chip8x));
+    chip8.set_megachip(config.platform == Some(Platform::MegaChip));
+    chip8.set_font_address(config.font_address);
+    chip8.set_memory_init(config.memory_init, config.memory_seed);
+    chip8.set_strict(strict);
+    chip8.load_rom(rom)?;
+    if !chip8.set_host_page(config.host_page()) {
+        eprintln!(
+            "Leaving the host page off, it would overwrite the ROM or fonts or not fit in memory. \
+             Move it with host_page_address."
+        );
+    }
+    if config.persist_flags {
+        if let Some(flags) = flag_store::load(rom) {
+            chip8.set_flags(&flags);
+        }
+    }
+    for patch in patches {
+        patch.write_to(&mut chip8);
+    }
+    Ok(chip8)
+}
+
+/// A machine running the ROM as `new_machine` boots it, with a frame buffer
+/// sized for it. A ROM that can't be loaded gets the error screen over an
+/// empty machine instead, along with the error.
+fn start_machine(
+    rom: &[u8],
+    config: &Config,
+    patches: &[Patch],
+    strict: bool,
+    window: &Window,
+) -> (Chip8, Pixels<Window>, Option<Chip8Error>) {
+    match new_machine(rom, config, patches, strict) {
+        Ok(chip8) => {
+            let pixels = create_pixels(window, chip8.display_size());
+            (chip8, pixels, None)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            let chip8 = Chip8::new();
+            let mut pixels = create_pixels(window, (error_screen::WIDTH, error_screen::HEIGHT));
+            error_screen::draw(pixels.get_frame(), &e, &chip8);
+            (chip8, pixels, Some(e))
+        }
+    }
+}
+
+/// The window title for a ROM, using its title in the ROM database if it
+/// is listed there
+fn rom_title(database: &RomDatabase, name: &str, rom: &[u8]) -> String {
+    let title = database.lookup(rom).map_or(name, |entry| &entry.title);
+    format!("Rust8 - {}", title)
+}
+
+/// Parses the patches configured for the ROM with the given file name,
+/// listing each one and skipping any that are invalid
+fn rom_patches(config: &Config, name: &str) -> Vec<Patch> {
+    let patches = match config.roms.get(name) {
+        Some(rom) => &rom.patches,
+        None => return vec![],
+    };
+
+    patches
+        .iter()
+        .filter_map(|patch| match Patch::parse(patch) {
+            Ok(parsed) => {
+                eprintln!("Patching {}: {}", name, patch);
+                Some(parsed)
+            }
+            Err(e) => {
+                eprintln!("Ignoring patch for {}: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The time of day in UTC for the host page
+fn host_time() -> HostTime {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() % (24 * 60 * 60));
+    HostTime {
+        hours: (seconds / 3600) as u8,
+        minutes: (seconds / 60 % 60) as u8,
+        seconds: (seconds % 60) as u8,
+    }
+}
+
+/// A window of the configured scale, fullscreen if configured
+fn window_builder(config: &Config) -> WindowBuilder {
+    let scale = config.display.scale.max(1);
+    let size = LogicalSize::new((WIDTH * scale) as f64, (HEIGHT * scale) as f64);
+    WindowBuilder::new()
+        .with_inner_size(size)
+        .with_min_inner_size(size)
+        .with_fullscreen(if config.display.fullscreen {
+            Some(Fullscreen::Borderless(None))
+        } else {
+            None
+        })
+}
+
+/// Starts recording a demo of a machine that was just (re)started, seeding
+/// it so playback draws the same random numbers. A restart starts the
+/// recording over, since demos are timed from the machine's first
+/// instruction.
+fn start_recording(chip8: &mut Chip8) -> DemoRecorder {
+    let seed = rand::random();
+    chip8.seed_rng(seed);
+    DemoRecorder::new(seed)
+}
+
+/// Everything timed from or tied to one run of the ROM, all started over
+/// whenever the machine restarts
+struct Run {
+    /// Inputs of the run so far, if a demo is being recorded
+    recorder: Option<DemoRecorder>,
+    /// Instructions executed in the run, which is what demo events are
+    /// timed against
+    cycle: u64,
+    frame_clock: FrameClock,
+    /// Saved with the machine state when the core faults
+    trace: TraceTail,
+    /// Kept while the prompt is closed so the game can run between filters
+    search: MemorySearch,
+    /// Plays the demo back once nobody has pressed a key for a while
+    attract: Option<DemoPlayer>,
+    last_input: Instant,
+}
+
+impl Run {
+    /// A run of a machine that just started, recording a demo of it if `record` is set
+    fn new(chip8: &mut Chip8, record: bool, time: Instant) -> Self {
+        Run {
+            recorder: if record {
+                Some(start_recording(chip8))
+            } else {
+                None
+            },
+            cycle: 0,
+            frame_clock: FrameClock::new(TIMER_INTERVAL, time),
+            trace: TraceTail::new(),
+            search: MemorySearch::new(),
+            attract: None,
+            last_input: time,
+        }
+    }
+}
+
+/// Boots the ROM afresh as `start_machine` does and starts `run` over with
+/// it, for a reset, a new ROM or settings that only apply from power on
+fn restart(
+    run: &mut Run,
+    rom: &[u8],
+    config: &Config,
+    patches: &[Patch],
+    strict: bool,
+    window: &Window,
+    time: Instant,
+) -> (Chip8, Pixels<Window>, Option<Chip8Error>) {
+    let (mut chip8, pixels, error) = start_machine(rom, config, patches, strict, window);
+    *run = Run::new(&mut chip8, run.recorder.is_some(), time);
+    (chip8, pixels, error)
+}
+
+/// Creates a pixel buffer of the given display resolution, scaled to fill the window.
+/// A minimized window gets a surface of a single pixel until it is resized.
+fn create_pixels(window: &Window, (width, height): (u16, u16)) -> Pixels<Window> {
+    let window_size = window.inner_size();
+    let surface_texture =
+        SurfaceTexture::new(window_size.width.max(1), window_size.height.max(1), window);
+    Pixels::new(width as u32, height as u32, surface_texture).unwrap()
+}
+
+/// Resizes the surface to the window, leaving it as it is while the window is
+/// minimized since a surface can't be empty
+fn resize_surface(pixels: &mut Pixels<Window>, size: PhysicalSize<u32>) {
+    if size.width > 0 && size.height > 0 {
+        pixels.resize(size.width, size.height);
+    }
+}
+
+/// Draws the frame buffer to the window. Nothing is drawn while the window is
+/// minimized, and a surface lost or outdated by moving between monitors is
+/// recreated, skipping the frame. Only errors the window can't recover from
+/// are returned.
+fn render(pixels: &mut Pixels<Window>, window: &Window) -> Result<(), pixels::Error> {
+    let size = window.inner_size();
+    if size.width == 0 || size.height == 0 {
+        return Ok(());
+    }
+
+    match pixels.render() {
+        Err(pixels::Error::Swapchain(SwapChainError::Outdated))
+        | Err(pixels::Error::Swapchain(SwapChainError::Lost)) => {
+            pixels.resize(size.width, size.height);
+            window.request_redraw();
+            Ok(())
+        }
+        Err(pixels::Error::Swapchain(SwapChainError::Timeout)) => {
+            window.request_redraw();
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Where the windowed frontend gets its ROMs from
+pub enum RomSource {
+    /// A single ROM from the command line, along with its file name
+    File {
+        name: String,
+        rom: Vec<u8>,
+    },
+    Playlist(Playlist),
+    /// The built in splash program, until a ROM is dropped onto the window
+    Splash,
+}
+
+/// Full window prompts that pause the game while they are open
+enum Prompt {
+    Rebinding(Rebinding),
+    Settings(SettingsMenu),
+    States(StatePicker),
+    Search,
+}
+
+/// Options for the windowed frontend, gathered from the command line
+pub struct WindowOptions {
+    pub source: RomSource,
+    /// Demo to play back once nobody has pressed a key for `attract_after`,
+    /// overriding the ROM database's for the ROM given on the command line
+    pub demo: Option<Demo>,
+    pub attract_after: Duration,
+    /// Where to save the inputs of this session as a demo on exit
+    pub record_demo: Option<PathBuf>,
+    pub frame_stats: bool,
+    /// Print a `SessionStats` summary on exit
+    pub session_stats: bool,
+    /// Show a `LatencyMeter` over the game
+    pub input_latency: bool,
+    pub watches: Option<Vec<Watch>>,
+    pub strict: bool,
+    /// Address to accept `NetworkKeys` clients on
+    pub listen_keys: Option<String>,
+    /// Take `StdinKeys` as well as the keyboard
+    pub stdin_keys: bool,
+    /// Directory to save every frame to
+    pub capture: Option<PathBuf>,
+    /// Quirk preset overriding the config
+    pub platform: Option<Platform>,
+    /// Font name or file overriding the config
+    pub font: Option<String>,
+    /// Profile to load the config with
+    pub profile: Option<String>,
+    /// Frame skip overriding the config
+    pub frame_skip: Option<u32>,
+}
+
+/// Says over the bottom of the last frame that the program ended with `00FD`
+fn draw_exit_banner(frame: &mut [u8], width: usize) {
+    let height = frame.len() / 4 / width;
+    let top = height.saturating_sub(overlay::LINE_ADVANCE);
+    overlay::clear(&mut frame[4 * top * width..], overlay::BACKGROUND_COLOR);
+    overlay::draw_text(frame, width, 0, top, "EXITED R=RESET", overlay::TEXT_COLOR);
+}
+
+/// Title bar text summarising the frame clock
+fn frame_stats_title(frame: &FrameInfo) -> String {
+    format!(
+        "Rust8 - {:.1}s emulated, {} cycles/frame, {} dropped",
+        frame.emulated_time.as_secs_f64(),
+        frame.cycles,
+        frame.dropped
+    )
+}
+
+pub fn run_window(options: WindowOptions) {
+    let WindowOptions {
+        source,
+        demo,
+        attract_after,
+        record_demo,
+        frame_stats,
+        session_stats,
+        input_latency,
+        watches,
+        strict,
+        listen_keys,
+        stdin_keys,
+        capture,
+        platform,
+        font,
+        profile,
+        frame_skip,
+    } = options;
+
+    // Sources of keys held alongside the keyboard's
+    let mut remote_keys: Vec<Box<dyn KeyProvider>> = vec![];
+    if let Some(address) = &listen_keys {
+        match NetworkKeys::bind(address) {
+            Ok(network_keys) => remote_keys.push(Box::new(network_keys)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
+    if stdin_keys {
+        remote_keys.push(Box::new(StdinKeys::spawn(false)));
+    }
+
+    let mut frame_sinks: Vec<Box<dyn FrameSink>> = vec![];
+    if let Some(directory) = &capture {
+        match PngSequence::create(directory) {
+            Ok(sequence) => frame_sinks.push(Box::new(sequence)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut config = match Config::load_with_profile(profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    config.apply_overrides(Overrides {
+        platform,
+        font,
+        frame_skip,
+    });
+    if let Err(e) = config.font() {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+
+    let database = RomDatabase::load();
+
+    let (mut rom, mut patches, title, mut playlist) = match source {
+        RomSource::File { name, rom } => {
+            let patches = rom_patches(&config, &name);
+            let title = rom_title(&database, &name, &rom);
+            (rom, patches, title, None)
+        }
+        RomSource::Playlist(playlist) => (vec![], vec![], "Rust8".to_string(), Some(playlist)),
+        RomSource::Splash => (
+            splash::rom(),
+            vec![],
+            "Rust8 - drop a ROM onto the window".to_string(),
+            None,
+        ),
+    };
+    let mut demo = demo.or_else(|| database.lookup(&rom).and_then(RomEntry::demo));
+
+    let mut buzzer = Buzzer::new(&config.audio);
+    let mut rumble = Rumble::new(&config.gamepad);
+
+    let event_loop = EventLoop::new();
+    let mut input = WinitInputHelper::new();
+    let window = window_builder(&config)
+        .with_title(&title)
+        .with_window_icon(database.lookup(&rom).and_then(|entry| entry.icon()))
+        .build(&event_loop)
+        .unwrap();
+
+    // Set when the core stops on a fault or the ROM can't be loaded, until
+    // the ROM is reset
+    let (mut chip8, mut pixels, mut error) =
+        start_machine(&rom, &config, &patches, strict, &window);
+
+    let mut run = Run::new(&mut chip8, record_demo.is_some(), Instant::now());
+
+    let mut key_states = [false; 16];
+    let mut demo_keys = [false; 16];
+
+    // While a prompt is open the game is paused, with its frame put aside so
+    // the prompt can be drawn in its place
+    let mut prompt: Option<(Prompt, Vec<u8>)> = None;
+
+    // Frames are only presented when something changed, and then at most
+    // once per emulated frame however fast the monitor refreshes, unless
+    // fading between frames
+    let mut display_dirty = true;
+    // Set when the core's display changed since it was last presented into
+    // the game's frame buffer
+    let mut frame_stale = true;
+    // Fades between frames on faster monitors, redrawing until each fade ends
+    let mut interpolation = if config.display.interpolate {
+        Some(Interpolation::new(Box::new(Blend)))
+    } else {
+        None
+    };
+
+    let mut watch_panel = watches.map(WatchPanel::new);
+
+    let mut save_slots: Vec<Option<SaveState>> = (0..SLOT_COUNT).map(|_| None).collect();
+
+    let mut session_stats = if session_stats {
+        Some(SessionStats::new(Instant::now()))
+    } else {
+        None
+    };
+
+    let mut latency = if input_latency {
+        Some(LatencyMeter::new())
+    } else {
+        None
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        if let Event::RedrawRequested(_) = event {
+            // The palette or CHIP-8X colors, bloom, the watch panel and the
+            // latency meter are drawn over the game only while presenting,
+            // then the game's frame is put back for the core to keep drawing on
+            let visible_panel = watch_panel.as_ref().filter(|panel| panel.visible);
+            let palette = config
+                .display
+                .palette()
+                .filter(|_| !chip8.draws_own_colors());
+            let colors = chip8.chip8x_colors();
+            let decorate = (palette.is_some()
+                || colors.is_some()
+                || config.display.bloom
+                || interpolation.is_some()
+                || visible_panel.is_some()
+                || latency.is_some()
+                || chip8.has_exited())
+                && prompt.is_none()
+                && error.is_none();
+
+            let game_frame = if decorate {
+                let (width, _) = chip8.display_size();
+                let game_frame = pixels.get_frame().to_vec();
+                if colors.is_some() || palette.is_some() {
+                    display::present_colored(&chip8, pixels.get_frame(), palette);
+                }
+                if config.display.bloom {
+                    bloom::apply(pixels.get_frame(), width as usize);
+                }
+                if let Some(interpolation) = &mut interpolation {
+                    let progress = run.frame_clock.progress(Instant::now());
+                    let number = run.frame_clock.number();
+                    if interpolation.apply(pixels.get_frame(), number, progress) {
+                        window.request_redraw();
+                    }
+                }
+                if let Some(panel) = visible_panel {
+                    panel.draw(pixels.get_frame(), width as usize);
+                }
+                if let Some(meter) = &latency {
+                    meter.draw(pixels.get_frame(), width as usize);
+                }
+                if chip8.has_exited() {
+                    draw_exit_banner(pixels.get_frame(), width as usize);
+                }
+                Some(game_frame)
+            } else {
+                None
+            };
+
+            let rendered = render(&mut pixels, &window)
+                .map_err(|e| eprintln!("pixels.render() failed: {:}", e));
+
+            if let Some(game_frame) = game_frame {
+                pixels.get_frame().copy_from_slice(&game_frame);
+            }
+
+            if rendered.is_err() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            if let Some(stats) = &mut session_stats {
+                stats.record_frame();
+            }
+        }
+
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } = event
+        {
+            // Escape closes the prompt rather than being handled by it, see below
+            if let (Some((open, frame)), true) = (&mut prompt, key != VirtualKeyCode::Escape) {
+                let mut close = false;
+
+                match open {
+                    Prompt::Rebinding(rebinding) => {
+                        if rebinding.capture(key) {
+                            *config.keys.player_mut(rebinding.player()) = rebinding.keys();
+                            if let Err(e) = config.save() {
+                                eprintln!("{}", e);
+                            }
+                            close = true;
+                        }
+                    }
+                    Prompt::Settings(menu) => {
+                        let rumble_enabled = config.gamepad.rumble;
+
+                        match menu.handle_key(key, &mut config) {
+                            SettingsAction::None => (),
+                            SettingsAction::Changed => {
+                                if let Some(buzzer) = &buzzer {
+                                    buzzer.set_volume(config.audio.volume);
+                                }
+                                if config.gamepad.rumble != rumble_enabled {
+                                    rumble = Rumble::new(&config.gamepad);
+                                }
+                                chip8.set_cycles_per_frame(config.cycles_per_frame());
+                            }
+                            SettingsAction::Reset => {
+                                if let Some(buzzer) = &buzzer {
+                                    buzzer.set_volume(config.audio.volume);
+                                }
+                                if config.gamepad.rumble != rumble_enabled {
+                                    rumble = Rumble::new(&config.gamepad);
+                                }
+                                (chip8, pixels, error) = restart(
+                                    &mut run,
+                                    &rom,
+                                    &config,
+                                    &patches,
+                                    strict,
+                                    &window,
+                                    Instant::now(),
+                                );
+                                *frame = pixels.get_frame().to_vec();
+                            }
+                            SettingsAction::QuirksChanged if config.reset_on_quirk_change => {
+                                (chip8, pixels, error) = restart(
+                                    &mut run,
+                                    &rom,
+                                    &config,
+                                    &patches,
+                                    strict,
+                                    &window,
+                                    Instant::now(),
+                                );
+                                *frame = pixels.get_frame().to_vec();
+                            }
+                            SettingsAction::QuirksChanged => chip8.set_quirks(config.quirks()),
+                            SettingsAction::Rebind(player) => {
+                                let keys = *config.keys.player_mut(player);
+                                *open = Prompt::Rebinding(Rebinding::new(player, keys));
+                            }
+                        }
+                    }
+                    Prompt::States(picker) => match picker.handle_key(key, &save_slots) {
+                        PickerAction::None => (),
+                        PickerAction::Save(slot) => {
+                            save_slots[slot] = Some(SaveState {
+                                chip8: chip8.clone(),
+                                pixels: frame.clone(),
+                                frame: run.frame_clock.number(),
+                                saved_at: Instant::now(),
+                            });
+                        }
+                        PickerAction::Load(slot) => {
+                            if let Some(state) = &save_slots[slot] {
+                                chip8 = state.chip8.clone();
+                                pixels = create_pixels(&window, chip8.display_size());
+                                *frame = state.pixels.clone();
+                                run.frame_clock.restore(state.frame, Instant::now());
+                                close = true;
+                            }
+                        }
+                    },
+                    Prompt::Search => run.search.handle_key(key, &chip8),
+                }
+
+                if close {
+                    pixels.get_frame().copy_from_slice(frame);
+                    prompt = None;
+                    display_dirty = true;
+                }
+            }
+        }
+
+        match &event {
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } => {
+                run.last_input = Instant::now();
+
+                if let (Some(meter), Some(key)) = (&mut latency, input.virtual_keycode) {
+                    let bindings = &config.keys;
+                    let keypad = (0..16)
+                        .find(|&i| bindings.player_one[i] == key || bindings.player_two[i] == key);
+                    if let Some(keypad) = keypad {
+                        let pressed = input.state == ElementState::Pressed;
+                        meter.key_event(keypad, pressed, run.last_input);
+                    }
+                }
+
+                // Any key ends the demo and hands the ROM back from the start
+                if run.attract.is_some() && error.is_none() {
+                    (chip8, pixels, error) = restart(
+                        &mut run,
+                        &rom,
+                        &config,
+                        &patches,
+                        strict,
+                        &window,
+                        Instant::now(),
+                    );
+                }
+            }
+            // A ROM dropped onto the window replaces the running one, unless
+            // a demo is being recorded or a playlist is in charge
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } if playlist.is_none() && run.recorder.is_none() => match rom_file::read_rom(path) {
+                Ok(dropped) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    rom = dropped;
+                    patches = rom_patches(&config, &name);
+                    (chip8, pixels, error) = restart(
+                        &mut run,
+                        &rom,
+                        &config,
+                        &patches,
+                        strict,
+                        &window,
+                        Instant::now(),
+                    );
+                    window.set_title(&rom_title(&database, &name, &rom));
+                    window.set_window_icon(database.lookup(&rom).and_then(|entry| entry.icon()));
+                    // The old demo was recorded for the ROM being replaced
+                    demo = database.lookup(&rom).and_then(RomEntry::demo);
+                    prompt = None;
+                    display_dirty = true;
+                }
+                Err(e) => eprintln!("{}", e),
+            },
+            Event::LoopDestroyed => {
+                if let (Some(recorder), Some(path)) = (&run.recorder, &record_demo) {
+                    if let Err(e) = recorder.demo().save(path) {
+                        eprintln!("{}", e);
+                    }
+                }
+                if let Some(stats) = &session_stats {
+                    print!("{}", stats.report(Instant::now()));
+                }
+                return;
+            }
+            _ => (),
+        }
+
+        if input.update(&event) {
+            if input.key_pressed(VirtualKeyCode::Escape) && prompt.is_some() {
+                if let Some((_, frame)) = prompt.take() {
+                    pixels.get_frame().copy_from_slice(&frame);
+                    display_dirty = true;
+                }
+            } else if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            // F1 and F2 rebind the keys of player one and two, F3 opens the
+            // settings, F4 the save states and F7 the memory search
+            if prompt.is_none() && error.is_none() {
+                let opened = if input.key_pressed(VirtualKeyCode::F1) {
+                    Some(Prompt::Rebinding(Rebinding::new(0, config.keys.player_one)))
+                } else if input.key_pressed(VirtualKeyCode::F2) {
+                    Some(Prompt::Rebinding(Rebinding::new(1, config.keys.player_two)))
+                } else if input.key_pressed(VirtualKeyCode::F3) {
+                    Some(Prompt::Settings(SettingsMenu::new()))
+                } else if input.key_pressed(VirtualKeyCode::F4) && run.recorder.is_none() {
+                    // Loading a state would desync a demo being recorded
+                    Some(Prompt::States(StatePicker::new()))
+                } else if input.key_pressed(VirtualKeyCode::F7) {
+                    Some(Prompt::Search)
+                } else {
+                    None
+                };
+
+                if let Some(opened) = opened {
+                    prompt = Some((opened, pixels.get_frame().to_vec()));
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::F5) {
+                if let Some(panel) = &mut watch_panel {
+                    panel.visible = !panel.visible;
+                    display_dirty = true;
+                }
+            }
+
+            // F6 cycles through the palettes, kept until the settings are saved
+            if input.key_pressed(VirtualKeyCode::F6) && prompt.is_none() {
+                config.display.cycle_palette(true);
+                display_dirty = true;
+            }
+
+            let bindings = &config.keys;
+            for (i, key_state) in key_states.iter_mut().enumerate() {
+                *key_state = input.key_held(bindings.player_one[i])
+                    || input.key_held(bindings.player_two[i]);
+            }
+
+            if let Some(size) = input.window_resized() {
+                resize_surface(&mut pixels, size);
+                display_dirty = true;
+            }
+        }
+
+        if let Some(stats) = &mut session_stats {
+            stats.set_paused(prompt.is_some(), Instant::now());
+        }
+
+        if let Some((open, _)) = &prompt {
+            let (width, _) = chip8.display_size();
+            match open {
+                Prompt::Rebinding(rebinding) => rebinding.draw(pixels.get_frame(), width as usize),
+                Prompt::Settings(menu) => menu.draw(pixels.get_frame(), width as usize, &config),
+                Prompt::States(picker) => picker.draw(
+                    pixels.get_frame(),
+                    width as usize,
+                    &save_slots,
+                    Instant::now(),
+                ),
+                Prompt::Search => run.search.draw(pixels.get_frame(), width as usize, &chip8),
+            }
+
+            if let Some(buzzer) = &mut buzzer {
+                buzzer.set_active(false);
+                buzzer.set_digitised(None);
+            }
+            if let Some(rumble) = &mut rumble {
+                rumble.set_active(false);
+            }
+
+            // Prompts are redrawn at the emulated frame rate
+            *control_flow = ControlFlow::WaitUntil(Instant::now() + TIMER_INTERVAL);
+            window.request_redraw();
+            return;
+        }
+
+        let time = Instant::now();
+
+        if let Some(playlist) = &mut playlist {
+            if let Some(next_rom) = playlist.update(time, chip8.is_halted()) {
+                rom = next_rom;
+                let name = playlist.current_path().file_name().unwrap_or_default();
+                patches = rom_patches(&config, &name.to_string_lossy());
+                (chip8, pixels, error) =
+                    restart(&mut run, &rom, &config, &patches, strict, &window, time);
+                if error.is_none() {
+                    overlay::clear(pixels.get_frame(), overlay::BACKGROUND_COLOR);
+                }
+                window.set_title(&rom_title(&database, &playlist.current_name(), &rom));
+                window.set_window_icon(database.lookup(&rom).and_then(|entry| entry.icon()));
+                demo = database.lookup(&rom).and_then(RomEntry::demo);
+            }
+
+            // A ROM that failed to load shows the error screen in place of its title
+            if playlist.showing_title() && error.is_none() {
+                let (width, _) = chip8.display_size();
+                playlist.draw_title_card(pixels.get_frame(), width as usize);
+                *control_flow = ControlFlow::WaitUntil(time + TIMER_INTERVAL);
+                window.request_redraw();
+                return;
+            }
+        }
+
+        if error.is_some() {
+            if input.key_pressed(VirtualKeyCode::R) {
+                (chip8, pixels, error) =
+                    restart(&mut run, &rom, &config, &patches, strict, &window, time);
+            } else {
+                if let Some(buzzer) = &mut buzzer {
+                    buzzer.set_active(false);
+                    buzzer.set_digitised(None);
+                }
+                if let Some(rumble) = &mut rumble {
+                    rumble.set_active(false);
+                }
+                // The panel is static, so only wake up for input or the kiosk
+                *control_flow = match &playlist {
+                    Some(playlist) => ControlFlow::WaitUntil(playlist.deadline()),
+                    None => ControlFlow::Wait,
+                };
+                return;
+            }
+        }
+
+        if let Some(demo) = &demo {
+            match &run.attract {
+                Some(player) if player.is_finished(demo) => {
+                    (chip8, pixels, error) =
+                        restart(&mut run, &rom, &config, &patches, strict, &window, time);
+                }
+                None if time - run.last_input >= attract_after => {
+                    (chip8, pixels, error) =
+                        restart(&mut run, &rom, &config, &patches, strict, &window, time);
+                    chip8.seed_rng(demo.seed);
+                    run.attract = Some(DemoPlayer::new());
+                    demo_keys = [false; 16];
+                }
+                _ => (),
+            }
+        }
+
+        // Everything that runs once per 60hz frame hangs off the frame clock,
+        // including the frame's instructions themselves
+        if let Some(frame) = run.frame_clock.tick(time) {
+            let step_keys = match (&mut run.attract, &demo) {
+                (Some(player), Some(demo)) => {
+                    // The core ticks the timers at the end of each frame, which
+                    // is where the demo recorded them
+                    for (_, event) in player.events_until(demo, run.cycle) {
+                        if let DemoEvent::Keys(mask) = *event {
+                            demo_keys = demo::key_states(mask);
+                        }
+                    }
+                    demo_keys
+                }
+                _ => {
+                    chip8.update_host_page(host_time(), &key_states);
+                    let mut step_keys = key_states;
+                    for remote_keys in &mut remote_keys {
+                        let remote = remote_keys.keys(run.cycle);
+                        for (held, remote) in step_keys.iter_mut().zip(remote) {
+                            *held |= remote;
+                        }
+                    }
+                    if let Some(recorder) = &mut run.recorder {
+                        recorder.record_keys(run.cycle, &step_keys);
+                    }
+                    step_keys
+                }
+            };
+
+            let result = chip8.run_frame_with(&step_keys, |chip8| {
+                if let Some(stats) = &mut session_stats {
+                    stats.record_instruction(chip8.opcode());
+                }
+                if let (Some(meter), None) = (&mut latency, &run.attract) {
+                    display_dirty |= meter.observe(chip8, &step_keys, time);
+                }
+                run.trace.record(chip8);
+                run.cycle += 1;
+                run.frame_clock.count_cycle();
+            });
+            match result {
+                Ok(()) => {
+                    if let (Some(recorder), None) = (&mut run.recorder, &run.attract) {
+                        recorder.record_timer_tick(run.cycle);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    match crash_report::save(&e, &chip8, &run.trace) {
+                        Ok(path) => eprintln!("Saved crash report to {}", path.display()),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    pixels = create_pixels(&window, (error_screen::WIDTH, error_screen::HEIGHT));
+                    error_screen::draw(pixels.get_frame(), &e, &chip8);
+                    error = Some(e);
+                    window.request_redraw();
+                }
+            }
+            let changed = chip8.take_display_changed();
+            display_dirty |= changed;
+            frame_stale |= changed;
+
+            while let Some(event) = chip8.poll_event() {
+                match event {
+                    Chip8Event::ResolutionChanged { width, height } => {
+                        pixels = create_pixels(&window, (width, height));
+                    }
+                    Chip8Event::StrictViolation(violation) => eprintln!("{}", violation),
+                    Chip8Event::Diagnostic(diagnostic) => eprintln!("{}", diagnostic),
+                    Chip8Event::UnknownOpcode { address, opcode } => {
+                        eprintln!("{}", Chip8Error::UnknownOpcode { address, opcode })
+                    }
+                    Chip8Event::FlagsStored if config.persist_flags => {
+                        if let Err(e) = flag_store::save(&rom, chip8.flags()) {
+                            eprintln!("{}", e);
+                        }
+                    }
+                    Chip8Event::FlagsStored => (),
+                    Chip8Event::Exited => {
+                        eprintln!("The program exited");
+                        display_dirty = true;
+                    }
+                }
+            }
+
+            if frame_stale && error.is_none() {
+                display::present(&chip8, pixels.get_frame());
+                frame_stale = false;
+            }
+
+            if !frame_sinks.is_empty() {
+                // Sinks get the game in the colors the window shows it in,
+                // without anything drawn over it
+                let (width, height) = chip8.display_size();
+                let palette = config
+                    .display
+                    .palette()
+                    .filter(|_| !chip8.draws_own_colors());
+                let mut colored = vec![0; 4 * width as usize * height as usize];
+                display::present_colored(&chip8, &mut colored, palette);
+                let completed = Frame {
+                    number: frame.number,
+                    width: width as usize,
+                    height: height as usize,
+                    pixels: &colored,
+                    sound: chip8.is_sound_playing(),
+                };
+                // A sink that fails is dropped rather than failing every frame
+                frame_sinks.retain_mut(|sink| {
+                    sink.frame(&completed)
+                        .map_err(|e| eprintln!("{}", e))
+                        .is_ok()
+                });
+            }
+
+            if frame_stats && frame.number % 60 == 0 {
+                window.set_title(&frame_stats_title(&frame));
+            }
+
+            if let Some(panel) = &mut watch_panel {
+                display_dirty |= panel.update(&chip8) && panel.visible;
+            }
+
+            // Skipped frames leave the display dirty for the next one presented
+            if display_dirty && config.display.presents(frame.number) {
+                window.request_redraw();
+                display_dirty = false;
+            }
+        }
+
+        if let Some(buzzer) = &mut buzzer {
+            buzzer.set_pattern(chip8.audio_pattern());
+            buzzer.set_digitised(chip8.digitised_sound());
+            buzzer.set_active(chip8.is_sound_playing());
+        }
+        if let Some(rumble) = &mut rumble {
+            rumble.set_active(chip8.is_sound_playing());
+        }
+
+        if chip8.is_waiting_for_key() && run.attract.is_none() {
+            // Nothing changes until a key arrives, so sleep until the next
+            // event and only wake up early to present the last frame, keep
+            // the timers, kiosk and attract mode running, and ask the remote
+            // key sources, which don't send window events
+            let mut wake_at = if chip8.timers_active() || display_dirty || !remote_keys.is_empty() {
+                Some(run.frame_clock.deadline())
+            } else {
+                None
+            };
+            if let Some(playlist) = &playlist {
+                let deadline = playlist.deadline();
+                wake_at = Some(wake_at.map_or(deadline, |time| time.min(deadline)));
+            }
+            if demo.is_some() {
+                let deadline = run.last_input + attract_after;
+                wake_at = Some(wake_at.map_or(deadline, |time| time.min(deadline)));
+            }
+
+            *control_flow = match wake_at {
+                Some(time) => ControlFlow::WaitUntil(time),
+                None => ControlFlow::Wait,
+            };
+        } else {
+            // Input still wakes the loop early, everything else happens on
+            // the next frame
+            *control_flow = ControlFlow::WaitUntil(run.frame_clock.deadline());
+        }
+    });
+}
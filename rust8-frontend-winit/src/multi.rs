@@ -0,0 +1,262 @@
+//! Runs several ROMs side by side, each in its own window on a shared event
+//! loop and config. Keys go to whichever window has focus. The extras of the
+//! single window frontend, such as prompts and demos, aren't available here.
+
+use std::collections::HashSet;
+use std::process;
+use std::time::Instant;
+
+use pixels::Pixels;
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Window, WindowId};
+
+use crate::audio::{AudioSink, Buzzer};
+use crate::config::{Config, Overrides};
+use crate::display;
+use crate::error_screen;
+use crate::flag_store;
+use crate::frame_clock::FrameClock;
+use crate::hardware::{Chip8, Chip8Error, Chip8Event, Platform};
+use crate::patcher::Patch;
+use crate::{
+    create_pixels, host_time, render, resize_surface, rom_patches, start_machine, window_builder,
+    TIMER_INTERVAL,
+};
+
+/// One ROM running in its own window
+struct Instance {
+    /// File name of the ROM, for messages
+    name: String,
+    rom: Vec<u8>,
+    patches: Vec<Patch>,
+    window: Window,
+    pixels: Pixels<Window>,
+    chip8: Chip8,
+    /// Host keys held while this window had focus
+    held: HashSet<VirtualKeyCode>,
+    frame_clock: FrameClock,
+    error: Option<Chip8Error>,
+    display_dirty: bool,
+}
+
+impl Instance {
+    fn restart(&mut self, config: &Config, strict: bool) {
+        (self.chip8, self.pixels, self.error) =
+            start_machine(&self.rom, config, &self.patches, strict, &self.window);
+        self.frame_clock.reset(Instant::now());
+        self.display_dirty = true;
+    }
+
+    fn key_states(&self, config: &Config) -> [bool; 16] {
+        let mut key_states = [false; 16];
+        for (i, key_state) in key_states.iter_mut().enumerate() {
+            *key_state = self.held.contains(&config.keys.player_one[i])
+                || self.held.contains(&config.keys.player_two[i]);
+        }
+        key_states
+    }
+
+    /// Runs a frame of the machine if one is due
+    fn update(&mut self, config: &Config, time: Instant) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let frame = match self.frame_clock.tick(time) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let key_states = self.key_states(config);
+        self.chip8.update_host_page(host_time(), &key_states);
+        let frame_clock = &mut self.frame_clock;
+        let result = self
+            .chip8
+            .run_frame_with(&key_states, |_| frame_clock.count_cycle());
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            self.pixels = create_pixels(&self.window, (error_screen::WIDTH, error_screen::HEIGHT));
+            error_screen::draw(self.pixels.get_frame(), &e, &self.chip8);
+            self.error = Some(e);
+            self.window.request_redraw();
+        }
+        self.display_dirty |= self.chip8.take_display_changed();
+
+        while let Some(event) = self.chip8.poll_event() {
+            match event {
+                Chip8Event::ResolutionChanged { width, height } => {
+                    self.pixels = create_pixels(&self.window, (width, height));
+                }
+                Chip8Event::StrictViolation(violation) => eprintln!("{}", violation),
+                Chip8Event::Diagnostic(diagnostic) => eprintln!("{}", diagnostic),
+                Chip8Event::UnknownOpcode { address, opcode } => {
+                    eprintln!("{}", Chip8Error::UnknownOpcode { address, opcode })
+                }
+                Chip8Event::FlagsStored if config.persist_flags => {
+                    if let Err(e) = flag_store::save(&self.rom, self.chip8.flags()) {
+                        eprintln!("{}", e);
+                    }
+                }
+                Chip8Event::FlagsStored => (),
+                Chip8Event::Exited => eprintln!("{} exited", self.name),
+            }
+        }
+
+        if self.display_dirty && config.display.presents(frame.number) {
+            self.window.request_redraw();
+            self.display_dirty = false;
+        }
+    }
+}
+
+/// Opens a window for each `(file name, ROM)` and runs them all until every
+/// window is closed
+pub fn run_multi_window(
+    roms: Vec<(String, Vec<u8>)>,
+    strict: bool,
+    platform: Option<Platform>,
+    font: Option<String>,
+    profile: Option<String>,
+) {
+    let mut config = match Config::load_with_profile(profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    config.apply_overrides(Overrides {
+        platform,
+        font,
+        frame_skip: None,
+    });
+    if let Err(e) = config.font() {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+    let buzzer = Buzzer::new(&config.audio);
+    let event_loop = EventLoop::new();
+
+    let mut instances: Vec<Instance> = roms
+        .into_iter()
+        .map(|(name, rom)| {
+            let window = window_builder(&config)
+                .with_title(format!("Rust8 - {}", name))
+                .build(&event_loop)
+                .unwrap();
+
+            let patches = rom_patches(&config, &name);
+            let (chip8, pixels, error) = start_machine(&rom, &config, &patches, strict, &window);
+
+            Instance {
+                name,
+                rom,
+                patches,
+                window,
+                pixels,
+                chip8,
+                held: HashSet::new(),
+                frame_clock: FrameClock::new(TIMER_INTERVAL, Instant::now()),
+                error,
+                display_dirty: true,
+            }
+        })
+        .collect();
+
+    let find = |instances: &[Instance], id: WindowId| {
+        instances
+            .iter()
+            .position(|instance| instance.window.id() == id)
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        // Sleep until the next frame of any running machine, or input
+        let next_frame = instances
+            .iter()
+            .filter(|instance| instance.error.is_none())
+            .map(|instance| instance.frame_clock.deadline())
+            .min();
+        *control_flow = match next_frame {
+            Some(time) => ControlFlow::WaitUntil(time),
+            None => ControlFlow::Wait,
+        };
+
+        match event {
+            Event::RedrawRequested(id) => {
+                if let Some(index) = find(&instances, id) {
+                    let instance = &mut instances[index];
+                    if instance.error.is_none() {
+                        display::present(&instance.chip8, instance.pixels.get_frame());
+                    }
+                    if let Err(e) = render(&mut instance.pixels, &instance.window) {
+                        eprintln!("pixels.render() failed: {:}", e);
+                        instances.remove(index);
+                    }
+                }
+            }
+            Event::WindowEvent { window_id, event } => {
+                let index = match find(&instances, window_id) {
+                    Some(index) => index,
+                    None => return,
+                };
+                let instance = &mut instances[index];
+
+                match event {
+                    WindowEvent::CloseRequested => {
+                        instances.remove(index);
+                    }
+                    WindowEvent::Resized(size) => {
+                        resize_surface(&mut instance.pixels, size);
+                        instance.window.request_redraw();
+                    }
+                    // Keys held when focus moves away would otherwise stay held
+                    WindowEvent::Focused(false) => instance.held.clear(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(key),
+                                state,
+                                ..
+                            },
+                        ..
+                    } => match (state, key) {
+                        (ElementState::Pressed, VirtualKeyCode::Escape) => {
+                            instances.remove(index);
+                        }
+                        (ElementState::Pressed, VirtualKeyCode::R) if instance.error.is_some() => {
+                            instance.restart(&config, strict);
+                        }
+                        (ElementState::Pressed, key) => {
+                            instance.held.insert(key);
+                        }
+                        (ElementState::Released, key) => {
+                            instance.held.remove(&key);
+                        }
+                    },
+                    _ => (),
+                }
+            }
+            Event::MainEventsCleared => {
+                let time = Instant::now();
+                for instance in &mut instances {
+                    instance.update(&config, time);
+                }
+
+                if let Some(buzzer) = &buzzer {
+                    // The first window making a sound picks the pattern
+                    let playing = instances.iter().find(|instance| {
+                        instance.error.is_none() && instance.chip8.is_sound_playing()
+                    });
+                    buzzer.set_pattern(playing.and_then(|instance| instance.chip8.audio_pattern()));
+                    buzzer.set_active(playing.is_some());
+                }
+            }
+            _ => (),
+        }
+
+        if instances.is_empty() {
+            *control_flow = ControlFlow::Exit;
+        }
+    });
+}
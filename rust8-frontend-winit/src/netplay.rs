@@ -0,0 +1,345 @@
+//! Experimental lockstep netplay between two instances over TCP. Each side
+//! sends the keys it holds for a frame a few frames ahead of running it, and
+//! a frame only runs once both sides' keys for it have arrived. With the
+//! same ROM, seed and quirks the two machines then stay identical, so two
+//! players can share the keypad from different computers. Keys held on
+//! either side count as held.
+
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Instant;
+
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+
+use crate::audio::{AudioSink, Buzzer};
+use crate::config::Config;
+use crate::demo;
+use crate::display;
+use crate::error_screen;
+use crate::frame_clock::FrameClock;
+use crate::hardware::{Chip8Error, Chip8Event};
+use crate::rom_file::read_rom;
+use crate::sha1::sha1;
+use crate::{create_pixels, new_machine, render, resize_surface, window_builder, TIMER_INTERVAL};
+
+/// Frames of keys sent ahead of the frame being run, which hides up to this
+/// many frames of round trip before either side has to wait for the other
+const INPUT_DELAY: u64 = 3;
+
+/// Starts the line each side sends on connecting, followed by the SHA-1 of
+/// its ROM and, from the host, the seed for `CXNN`
+const HANDSHAKE: &str = "rust8-netplay 1";
+
+/// A connection to the other instance, and the keys each side holds for the
+/// frames that haven't run yet
+struct Session {
+    stream: TcpStream,
+    lines: Receiver<String>,
+    local: VecDeque<u16>,
+    remote: VecDeque<u16>,
+    /// The next frame to run
+    frame: u64,
+}
+
+impl Session {
+    /// Waits for the other instance to connect to `address`, then agrees on
+    /// the ROM and picks the seed. Returns the session and the seed.
+    fn host(address: &str, rom: &[u8]) -> Result<(Self, u64), String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        eprintln!("Waiting for the other player on {}", address);
+        let (stream, peer) = listener
+            .accept()
+            .map_err(|e| format!("Failed to accept a connection: {}", e))?;
+        eprintln!("Connected to {}", peer);
+
+        let seed = rand::random();
+        let (session, _) = Session::start(stream, rom, Some(seed))?;
+        Ok((session, seed))
+    }
+
+    /// Connects to an instance hosting at `address`, taking its seed
+    fn join(address: &str, rom: &[u8]) -> Result<(Self, u64), String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+        let (session, seed) = Session::start(stream, rom, None)?;
+        let seed = seed.ok_or("The host didn't send a seed")?;
+        Ok((session, seed))
+    }
+
+    /// Swaps handshakes, checking both sides run the same ROM, and sends the
+    /// blank keys that cover the first `INPUT_DELAY` frames. Returns the
+    /// seed the other side sent, if any.
+    fn start(
+        mut stream: TcpStream,
+        rom: &[u8],
+        seed: Option<u64>,
+    ) -> Result<(Self, Option<u64>), String> {
+        let digest: String = sha1(rom)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let mut handshake = format!("{} {}", HANDSHAKE, digest);
+        if let Some(seed) = seed {
+            handshake.push_str(&format!(" {:016x}", seed));
+        }
+
+        writeln!(stream, "{}", handshake)
+            .map_err(|e| format!("Failed to send the handshake: {}", e))?;
+
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read the handshake: {}", e))?;
+        let theirs = line
+            .trim()
+            .strip_prefix(HANDSHAKE)
+            .ok_or("The other side isn't a compatible rust8")?;
+        let mut fields = theirs.split_whitespace();
+        if fields.next() != Some(digest.as_str()) {
+            return Err("The other side is running a different ROM".to_string());
+        }
+        let their_seed = fields
+            .next()
+            .map(|seed| u64::from_str_radix(seed, 16))
+            .transpose()
+            .map_err(|_| "The other side sent an invalid seed")?;
+
+        // Lines are read on their own thread so a quiet peer never blocks
+        // the window
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut session = Session {
+            stream,
+            lines,
+            local: VecDeque::new(),
+            remote: VecDeque::new(),
+            frame: 0,
+        };
+        for frame in 0..INPUT_DELAY {
+            session.local.push_back(0);
+            session.send(&format!("{} {:04x}", frame, 0))?;
+        }
+        Ok((session, their_seed))
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stream, "{}", line).map_err(|e| format!("Lost the other player: {}", e))
+    }
+
+    /// Reads the keys the other side has sent so far
+    fn receive(&mut self) -> Result<(), String> {
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => {
+                    return Err("The other player disconnected".to_string())
+                }
+            };
+
+            let expected = self.frame + self.remote.len() as u64;
+            let parsed = line.split_once(' ').and_then(|(frame, mask)| {
+                Some((
+                    frame.parse::<u64>().ok()?,
+                    u16::from_str_radix(mask, 16).ok()?,
+                ))
+            });
+            match parsed {
+                Some((frame, mask)) if frame == expected => self.remote.push_back(mask),
+                _ => {
+                    return Err(format!(
+                        "Unexpected message '{}' from the other player",
+                        line
+                    ))
+                }
+            }
+        }
+    }
+
+    /// The keys to run the next frame with, once the other side's have
+    /// arrived, sending `held` as this side's keys `INPUT_DELAY` frames on
+    fn advance(&mut self, held: u16) -> Result<Option<[bool; 16]>, String> {
+        self.receive()?;
+        let remote = match self.remote.pop_front() {
+            Some(remote) => remote,
+            None => return Ok(None),
+        };
+        let local = self.local.pop_front().unwrap_or(0);
+
+        self.send(&format!("{} {:04x}", self.frame + INPUT_DELAY, held))?;
+        self.local.push_back(held);
+        self.frame += 1;
+        Ok(Some(demo::key_states(local | remote)))
+    }
+}
+
+/// Runs a ROM in lockstep with another instance, hosting on `host` or
+/// joining the instance at `join`. Settings that change how the ROM runs,
+/// such as quirks and the platform, must match on both sides.
+pub fn run_netplay(path: &Path, host: Option<&str>, join: Option<&str>) {
+    let rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    let connected = match (host, join) {
+        (Some(address), _) => Session::host(address, &rom),
+        (None, Some(address)) => Session::join(address, &rom),
+        (None, None) => Err("Either --host or --join is needed".to_string()),
+    };
+    let (mut session, seed) = match connected {
+        Ok(connected) => connected,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    let mut config = Config::load();
+    // Anything read from the host would differ between the two sides
+    config.persist_flags = false;
+    config.host_page = false;
+    if let Err(e) = config.font() {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+
+    let mut chip8 = match new_machine(&rom, &config, &[], false) {
+        Ok(chip8) => chip8,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    chip8.seed_rng(seed);
+
+    let buzzer = Buzzer::new(&config.audio);
+    let event_loop = EventLoop::new();
+    let window = window_builder(&config)
+        .with_title(format!(
+            "Rust8 - {} (netplay)",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ))
+        .build(&event_loop)
+        .unwrap();
+    let mut pixels = create_pixels(&window, chip8.display_size());
+
+    let mut held: HashSet<VirtualKeyCode> = HashSet::new();
+    let mut frame_clock = FrameClock::new(TIMER_INTERVAL, Instant::now());
+    let mut error: Option<Chip8Error> = None;
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = match error {
+            Some(_) => ControlFlow::Wait,
+            None => ControlFlow::WaitUntil(frame_clock.deadline()),
+        };
+
+        match event {
+            Event::RedrawRequested(_) => {
+                if error.is_none() {
+                    display::present(&chip8, pixels.get_frame());
+                }
+                if let Err(e) = render(&mut pixels, &window) {
+                    eprintln!("pixels.render() failed: {:}", e);
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => {
+                    resize_surface(&mut pixels, size);
+                    window.request_redraw();
+                }
+                WindowEvent::Focused(false) => held.clear(),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state,
+                            ..
+                        },
+                    ..
+                } => match (state, key) {
+                    (ElementState::Pressed, VirtualKeyCode::Escape) => {
+                        *control_flow = ControlFlow::Exit
+                    }
+                    (ElementState::Pressed, key) => {
+                        held.insert(key);
+                    }
+                    (ElementState::Released, key) => {
+                        held.remove(&key);
+                    }
+                },
+                _ => (),
+            },
+            Event::MainEventsCleared => {
+                if error.is_some() || frame_clock.tick(Instant::now()).is_none() {
+                    return;
+                }
+
+                let mut key_states = [false; 16];
+                for (i, key_state) in key_states.iter_mut().enumerate() {
+                    *key_state = held.contains(&config.keys.player_one[i])
+                        || held.contains(&config.keys.player_two[i]);
+                }
+
+                // Without the other side's keys the frame waits for them
+                let key_states = match session.advance(demo::key_mask(&key_states)) {
+                    Ok(Some(key_states)) => key_states,
+                    Ok(None) => return,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                };
+
+                if let Err(e) = chip8.run_frame(&key_states) {
+                    eprintln!("{}", e);
+                    pixels = create_pixels(&window, (error_screen::WIDTH, error_screen::HEIGHT));
+                    error_screen::draw(pixels.get_frame(), &e, &chip8);
+                    error = Some(e);
+                }
+                while let Some(event) = chip8.poll_event() {
+                    match event {
+                        Chip8Event::ResolutionChanged { width, height } => {
+                            pixels = create_pixels(&window, (width, height));
+                        }
+                        Chip8Event::StrictViolation(violation) => eprintln!("{}", violation),
+                        Chip8Event::Diagnostic(diagnostic) => eprintln!("{}", diagnostic),
+                        Chip8Event::UnknownOpcode { address, opcode } => {
+                            eprintln!("{}", Chip8Error::UnknownOpcode { address, opcode })
+                        }
+                        Chip8Event::FlagsStored | Chip8Event::Exited => (),
+                    }
+                }
+                if chip8.take_display_changed() || error.is_some() {
+                    window.request_redraw();
+                }
+
+                if let Some(buzzer) = &buzzer {
+                    buzzer.set_pattern(chip8.audio_pattern());
+                    buzzer.set_active(chip8.is_sound_playing());
+                }
+            }
+            _ => (),
+        }
+    });
+}
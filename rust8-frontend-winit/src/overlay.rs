@@ -0,0 +1,110 @@
+//! Minimal text rendering for frontend overlays such as title cards.
+//!
+//! The CHIP-8 font only covers hex digits, so this carries its own tiny 3x5
+//! font that fits 16 characters across the 64 pixel wide display.
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+/// Horizontal distance between the start of consecutive characters
+pub const CHAR_ADVANCE: usize = GLYPH_WIDTH + 1;
+/// Vertical distance between the start of consecutive lines
+pub const LINE_ADVANCE: usize = GLYPH_HEIGHT + 1;
+
+pub const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+pub const BACKGROUND_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+/// Each glyph is five rows of three bits, the highest bit being the leftmost pixel
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010], // '?'
+    }
+}
+
+/// Fills the whole RGBA frame with a single color
+pub fn clear(frame: &mut [u8], color: [u8; 4]) {
+    for pixel in frame.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&color);
+    }
+}
+
+/// Draws a single line of text into an RGBA frame `width` pixels wide with
+/// its top left corner at `(x, y)`. Anything outside the frame is clipped.
+pub fn draw_text(frame: &mut [u8], width: usize, x: usize, y: usize, text: &str, color: [u8; 4]) {
+    let height = frame.len() / 4 / width;
+
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i * CHAR_ADVANCE;
+
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                let (pixel_x, pixel_y) = (glyph_x + col, y + row);
+                let lit = bits & (0b100 >> col) != 0;
+
+                if lit && pixel_x < width && pixel_y < height {
+                    let index = 4 * (pixel_x + pixel_y * width);
+                    frame[index..index + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+/// Splits text into lines that fit across a frame `width` pixels wide
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let max_chars = (width / CHAR_ADVANCE).max(1);
+    let chars: Vec<char> = text.chars().collect();
+
+    chars
+        .chunks(max_chars)
+        .map(|line| line.iter().collect::<String>().trim().to_string())
+        .collect()
+}
@@ -0,0 +1,145 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+
+/// Directory in the config directory that palette files are loaded from
+const PALETTE_DIRECTORY: &str = "palettes";
+
+/// Colors the display is shown in, used only when presenting so the game's
+/// own frame buffer keeps the default colors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    /// RGB colors of pixels with no plane set, plane 1 set, plane 2 set and
+    /// both planes set. Plain CHIP-8 ROMs only use the first two.
+    pub colors: [[u8; 3]; 4],
+}
+
+impl Palette {
+    fn new(name: &str, colors: [[u8; 3]; 4]) -> Self {
+        Palette {
+            name: name.to_string(),
+            colors,
+        }
+    }
+}
+
+/// A palette as written in a file of the palettes directory. Two colors are
+/// enough for CHIP-8, and the XO-CHIP plane colors are then shades between them.
+#[derive(Debug, Deserialize)]
+struct PaletteFile {
+    /// Shown in the settings menu, the file name if missing
+    name: Option<String>,
+    colors: Vec<[u8; 3]>,
+}
+
+impl PaletteFile {
+    fn into_palette(self, file_name: &str) -> Result<Palette, String> {
+        let name = self.name.unwrap_or_else(|| file_name.to_string());
+        let colors = match self.colors[..] {
+            [off, on] => [off, on, blend(off, on, 1), blend(off, on, 2)],
+            [off, plane_one, plane_two, both] => [off, plane_one, plane_two, both],
+            _ => {
+                return Err(format!(
+                    "expected 2 or 4 colors, found {}",
+                    self.colors.len()
+                ))
+            }
+        };
+        Ok(Palette { name, colors })
+    }
+}
+
+/// The color `thirds` thirds of the way from `from` to `to`
+fn blend(from: [u8; 3], to: [u8; 3], thirds: u16) -> [u8; 3] {
+    let mut color = [0; 3];
+    for (channel, (&from, &to)) in color.iter_mut().zip(from.iter().zip(to.iter())) {
+        *channel = ((from as u16 * (3 - thirds) + to as u16 * thirds) / 3) as u8;
+    }
+    color
+}
+
+/// Palettes shared as `.toml` files in the palettes directory of the config
+/// directory, in file name order. Invalid files are reported and skipped.
+pub fn load_palette_files() -> Vec<Palette> {
+    let dir = match config_dir() {
+        Some(dir) => dir.join(PALETTE_DIRECTORY),
+        None => return vec![],
+    };
+    let mut paths: Vec<_> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml")
+            })
+            .collect(),
+        Err(_) => return vec![],
+    };
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| {
+            let file_name = path.file_stem()?.to_string_lossy();
+            let palette = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| {
+                    toml::from_str::<PaletteFile>(&contents).map_err(|e| e.to_string())
+                })
+                .and_then(|file| file.into_palette(&file_name));
+            match palette {
+                Ok(palette) => Some(palette),
+                Err(e) => {
+                    eprintln!("Ignoring invalid palette {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The palettes available until the config lists its own
+pub fn default_palettes() -> Vec<Palette> {
+    vec![
+        Palette::new(
+            "classic",
+            [
+                [0, 0, 0],
+                [255, 255, 255],
+                [0x55, 0x55, 0x55],
+                [0xAA, 0xAA, 0xAA],
+            ],
+        ),
+        Palette::new(
+            "amber",
+            [
+                [0x1A, 0x0F, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0x80, 0x50, 0x00],
+                [0xFF, 0xE0, 0x90],
+            ],
+        ),
+        Palette::new(
+            "phosphor",
+            [
+                [0x00, 0x14, 0x00],
+                [0x33, 0xFF, 0x33],
+                [0x10, 0x80, 0x10],
+                [0xB0, 0xFF, 0xB0],
+            ],
+        ),
+        // The colors Octo uses for XO-CHIP
+        Palette::new(
+            "octo",
+            [
+                [0x99, 0x66, 0x00],
+                [0xFF, 0xCC, 0x00],
+                [0xFF, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+        ),
+    ]
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+
+use crate::assembler;
+use crate::hardware::{parse_number, Chip8, Instruction, MAX_ROM_SIZE};
+use crate::rom_file::read_rom;
+
+/// Address the first byte of a ROM is loaded at
+const ROM_START: u16 = 0x200;
+
+/// Bytes to write over the ROM at a CHIP-8 address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl Patch {
+    /// Parses `address=value`, where the value is a number or assembly.
+    ///
+    /// Numbers up to 0xFF are written as a byte and larger ones as a big
+    /// endian word, as is hex written with more than two digits, so `0x00E0`
+    /// is the word `00 E0`. Anything else is assembled, e.g. `0x3A4=CLS` or
+    /// `0x3A4=DB 0x12, 0x34`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid patch '{}', expected address=value", s);
+        let (address, value) = s.split_once('=').ok_or_else(invalid)?;
+
+        let address = parse_number(&address.trim().to_ascii_uppercase()).ok_or_else(invalid)?;
+        let value = value.trim();
+        let upper = value.to_ascii_uppercase();
+
+        let bytes = match parse_number(&upper) {
+            Some(number) => {
+                let digits = upper
+                    .strip_prefix("0X")
+                    .or_else(|| upper.strip_prefix('#'))
+                    .or_else(|| upper.strip_prefix('$'))
+                    .map_or(0, str::len);
+
+                if number > 0xFF || digits > 2 {
+                    number.to_be_bytes().to_vec()
+                } else {
+                    vec![number as u8]
+                }
+            }
+            None => assembler::assemble(value).map_err(|e| format!("{}: {}", s, e))?,
+        };
+
+        if bytes.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Patch { address, bytes })
+    }
+
+    /// Writes the patch into the memory of a machine the ROM was loaded into
+    pub fn write_to(&self, chip8: &mut Chip8) {
+        for (offset, &byte) in self.bytes.iter().enumerate() {
+            chip8.write_memory(self.address.wrapping_add(offset as u16), byte);
+        }
+    }
+
+    /// Writes the patch over a ROM, growing it if the patch runs past the end
+    pub fn apply(&self, rom: &mut Vec<u8>) -> Result<(), String> {
+        let start = (self.address as usize)
+            .checked_sub(ROM_START as usize)
+            .ok_or_else(|| format!("{:#05X} is before the start of the ROM", self.address))?;
+        let end = start + self.bytes.len();
+
+        if end > MAX_ROM_SIZE {
+            return Err(format!("{:#05X} is past the end of memory", self.address));
+        }
+
+        if rom.len() < end {
+            rom.resize(end, 0);
+        }
+        rom[start..end].copy_from_slice(&self.bytes);
+        Ok(())
+    }
+}
+
+/// Applies an IPS patch, whose offsets are into the ROM file rather than
+/// CHIP-8 addresses
+pub fn apply_ips(rom: &mut Vec<u8>, ips: &[u8]) -> Result<(), String> {
+    let invalid = || "Invalid IPS patch".to_string();
+    let mut data = ips.strip_prefix(b"PATCH").ok_or_else(invalid)?;
+
+    loop {
+        if data.starts_with(b"EOF") {
+            return Ok(());
+        }
+
+        let (header, rest) = (data.get(..5).ok_or_else(invalid)?, &data[5..]);
+        let offset = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let size = u16::from_be_bytes([header[3], header[4]]) as usize;
+
+        // A size of zero marks a run of one repeated byte
+        let (bytes, rest) = if size == 0 {
+            let run = rest.get(..3).ok_or_else(invalid)?;
+            let length = u16::from_be_bytes([run[0], run[1]]) as usize;
+            (vec![run[2]; length], &rest[3..])
+        } else {
+            (
+                rest.get(..size).ok_or_else(invalid)?.to_vec(),
+                &rest[size..],
+            )
+        };
+
+        let end = offset + bytes.len();
+        if end > MAX_ROM_SIZE {
+            return Err(format!(
+                "IPS record at {:#X} is past the end of memory",
+                offset
+            ));
+        }
+        if rom.len() < end {
+            rom.resize(end, 0);
+        }
+        rom[offset..end].copy_from_slice(&bytes);
+
+        data = rest;
+    }
+}
+
+/// Disassembles the word at a CHIP-8 address, for showing what a patch changed
+fn disassemble(rom: &[u8], address: u16) -> String {
+    let start = address.wrapping_sub(ROM_START) as usize;
+    match rom.get(start..start + 2) {
+        Some(word) => Instruction::decode(u16::from_be_bytes([word[0], word[1]])).to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Applies an optional IPS patch and then each `--set` patch to a ROM,
+/// writing the result to `output`. Returns the process exit code.
+pub fn run_patch(path: &Path, sets: &[String], ips: Option<&Path>, output: &Path) -> i32 {
+    let mut rom = match read_rom(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if let Some(ips) = ips {
+        let result = fs::read(ips)
+            .map_err(|e| format!("Failed to read {}: {}", ips.display(), e))
+            .and_then(|patch| apply_ips(&mut rom, &patch));
+
+        if let Err(e) = result {
+            eprintln!("{}: {}", ips.display(), e);
+            return 1;
+        }
+    }
+
+    for set in sets {
+        let result = Patch::parse(set).and_then(|patch| {
+            let before = disassemble(&rom, patch.address);
+            patch.apply(&mut rom)?;
+            println!(
+                "{:#05X}: {} -> {}",
+                patch.address,
+                before,
+                disassemble(&rom, patch.address)
+            );
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+
+    if let Err(e) = fs::write(output, &rom) {
+        eprintln!("Failed to write {}: {}", output.display(), e);
+        return 2;
+    }
+
+    println!("Wrote {} bytes to {}", rom.len(), output.display());
+    0
+}
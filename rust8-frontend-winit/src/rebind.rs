@@ -0,0 +1,69 @@
+use winit::event::VirtualKeyCode;
+
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Keypad keys in the order they are asked for, row by row as laid out on
+/// the COSMAC VIP keypad
+const PROMPT_ORDER: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Captures a new host key for each keypad key of one player in turn
+pub struct Rebinding {
+    player: usize,
+    keys: [VirtualKeyCode; 16],
+    position: usize,
+}
+
+impl Rebinding {
+    /// Starts rebinding `player` (0 or 1), whose keys are currently `keys`
+    pub fn new(player: usize, keys: [VirtualKeyCode; 16]) -> Self {
+        Rebinding {
+            player,
+            keys,
+            position: 0,
+        }
+    }
+
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Assigns the pressed key to the keypad key being asked for, returning
+    /// true once every key has been bound
+    pub fn capture(&mut self, key: VirtualKeyCode) -> bool {
+        self.keys[PROMPT_ORDER[self.position]] = key;
+        self.position += 1;
+        self.is_finished()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= PROMPT_ORDER.len()
+    }
+
+    pub fn keys(&self) -> [VirtualKeyCode; 16] {
+        self.keys
+    }
+
+    /// Draws the prompt for the next key
+    pub fn draw(&self, frame: &mut [u8], width: usize) {
+        overlay::clear(frame, BACKGROUND_COLOR);
+
+        let lines = [
+            format!("PLAYER {} KEYS", self.player + 1),
+            String::new(),
+            "PRESS KEY FOR".to_string(),
+            format!(
+                "KEYPAD {:X}  {}/{}",
+                PROMPT_ORDER[self.position.min(PROMPT_ORDER.len() - 1)],
+                self.position + 1,
+                PROMPT_ORDER.len()
+            ),
+            "ESC TO CANCEL".to_string(),
+        ];
+
+        for (i, line) in lines.iter().enumerate() {
+            overlay::draw_text(frame, width, 1, 1 + i * LINE_ADVANCE, line, TEXT_COLOR);
+        }
+    }
+}
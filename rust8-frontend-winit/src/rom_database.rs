@@ -0,0 +1,111 @@
+//! Titles, icons and attract mode demos for known ROMs, read from `roms.toml` in the config
+//! directory. ROMs are looked up by SHA-1, so they are recognised however
+//! the file is named.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use winit::window::Icon;
+
+use crate::config::config_dir;
+use crate::demo::Demo;
+use crate::sha1::sha1;
+
+const DATABASE_FILE_NAME: &str = "roms.toml";
+
+/// Icon pixels drawn for each pixel of the sprite
+const ICON_SCALE: usize = 4;
+
+const ICON_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// What the database knows about a ROM
+#[derive(Debug, Deserialize)]
+pub struct RomEntry {
+    pub title: String,
+    /// A sprite from the game in hex, one byte per row of 8 pixels, or two
+    /// per row of 16 for a 32 byte SUPER-CHIP sprite
+    pub icon: Option<String>,
+    /// Demo file played when the ROM is left idle, relative to the config
+    /// directory unless absolute
+    pub demo: Option<PathBuf>,
+}
+
+impl RomEntry {
+    /// The sprite as a window icon, or `None` if there is none or it isn't
+    /// valid hex
+    pub fn icon(&self) -> Option<Icon> {
+        let hex: String = self.icon.as_ref()?.split_whitespace().collect();
+        if hex.is_empty() {
+            return None;
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let bytes_per_row = if bytes.len() == 32 { 2 } else { 1 };
+        let width = 8 * bytes_per_row * ICON_SCALE;
+        let height = bytes.len() / bytes_per_row * ICON_SCALE;
+
+        let mut rgba = vec![0u8; 4 * width * height];
+        for (pixel, color) in rgba.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (pixel % width / ICON_SCALE, pixel / width / ICON_SCALE);
+            let byte = bytes[y * bytes_per_row + x / 8];
+            if byte & (0x80 >> (x % 8)) != 0 {
+                color.copy_from_slice(&ICON_COLOR);
+            }
+        }
+
+        Icon::from_rgba(rgba, width as u32, height as u32).ok()
+    }
+
+    /// The attract mode demo, or `None` if there is none or it can't be loaded
+    pub fn demo(&self) -> Option<Demo> {
+        let path = self.demo.as_ref()?;
+        let path = match config_dir() {
+            Some(dir) => dir.join(path),
+            None => path.clone(),
+        };
+        Demo::load(&path).map_err(|e| eprintln!("{}", e)).ok()
+    }
+}
+
+/// Known ROMs keyed by the hex SHA-1 of the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct RomDatabase {
+    roms: BTreeMap<String, RomEntry>,
+}
+
+impl RomDatabase {
+    /// Loads the database, or an empty one if it is missing or invalid
+    pub fn load() -> Self {
+        let path = match config_dir() {
+            Some(dir) => dir.join(DATABASE_FILE_NAME),
+            None => return RomDatabase::default(),
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return RomDatabase::default(),
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid ROM database {}: {}", path.display(), e);
+            RomDatabase::default()
+        })
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomEntry> {
+        let digest: String = sha1(rom)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.roms
+            .iter()
+            .find(|(hash, _)| hash.eq_ignore_ascii_case(&digest))
+            .map(|(_, entry)| entry)
+    }
+}
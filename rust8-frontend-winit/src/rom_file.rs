@@ -0,0 +1,21 @@
+use std::fs;
+use std::path::Path;
+
+use crate::hardware::MEGACHIP_MAX_ROM_SIZE;
+
+/// Reads a ROM no larger than the largest memory of any platform. Loading it
+/// checks it fits the platform the machine is set up as.
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, String> {
+    let rom = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    if rom.len() > MEGACHIP_MAX_ROM_SIZE {
+        return Err(format!(
+            "{} is {} bytes, larger than the {} bytes available",
+            path.display(),
+            rom.len(),
+            MEGACHIP_MAX_ROM_SIZE
+        ));
+    }
+
+    Ok(rom)
+}
@@ -0,0 +1,100 @@
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks};
+use gilrs::{EventType, GamepadId, Gilrs};
+
+use crate::config::GamepadConfig;
+
+/// How long each pulse of the motors lasts
+const PULSE_MS: u32 = 80;
+/// Gap between pulses, so a long beep feels like a buzz rather than a constant hum
+const PULSE_GAP_MS: u32 = 40;
+
+/// Pulses the rumble motors of any connected gamepads while the sound timer runs
+pub struct Rumble {
+    gilrs: Gilrs,
+    strength: f32,
+    effect: Option<Effect>,
+    active: bool,
+}
+
+impl Rumble {
+    /// Returns `None` if rumble is disabled or gamepads aren't available
+    pub fn new(config: &GamepadConfig) -> Option<Self> {
+        if !config.rumble {
+            return None;
+        }
+
+        let gilrs = Gilrs::new()
+            .map_err(|e| eprintln!("Rumble disabled, gamepads unavailable: {}", e))
+            .ok()?;
+
+        let mut rumble = Rumble {
+            gilrs,
+            strength: config.rumble_strength.clamp(0.0, 1.0),
+            effect: None,
+            active: false,
+        };
+        rumble.rebuild_effect();
+
+        Some(rumble)
+    }
+
+    /// Starts or stops rumbling, picking up any gamepads that were connected
+    /// or disconnected since the last call
+    pub fn set_active(&mut self, active: bool) {
+        let mut gamepads_changed = false;
+        while let Some(event) = self.gilrs.next_event() {
+            if let EventType::Connected | EventType::Disconnected = event.event {
+                gamepads_changed = true;
+            }
+        }
+
+        if gamepads_changed {
+            self.rebuild_effect();
+            self.active = false;
+        }
+
+        if active == self.active {
+            return;
+        }
+        self.active = active;
+
+        if let Some(effect) = &self.effect {
+            let result = if active { effect.play() } else { effect.stop() };
+            if let Err(e) = result {
+                eprintln!("Failed to update rumble: {}", e);
+            }
+        }
+    }
+
+    fn rebuild_effect(&mut self) {
+        let gamepads: Vec<GamepadId> = self
+            .gilrs
+            .gamepads()
+            .filter(|(_, gamepad)| gamepad.is_ff_supported())
+            .map(|(id, _)| id)
+            .collect();
+
+        if gamepads.is_empty() {
+            self.effect = None;
+            return;
+        }
+
+        let magnitude = (u16::MAX as f32 * self.strength) as u16;
+        let pulse = BaseEffect {
+            kind: BaseEffectType::Strong { magnitude },
+            scheduling: Replay {
+                play_for: Ticks::from_ms(PULSE_MS),
+                with_delay: Ticks::from_ms(PULSE_GAP_MS),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        self.effect = EffectBuilder::new()
+            .add_effect(pulse)
+            .gamepads(&gamepads)
+            .finish(&mut self.gilrs)
+            .map_err(|e| eprintln!("Failed to create rumble effect: {}", e))
+            .ok();
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use winit::event::VirtualKeyCode;
+
+use crate::hardware::Chip8;
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Number of save slots, laid out in a single row of thumbnails
+pub const SLOT_COUNT: usize = 4;
+
+/// Outline of the slots that aren't selected
+const UNSELECTED_COLOR: [u8; 4] = [96, 96, 96, 255];
+
+/// A snapshot of the running machine
+pub struct SaveState {
+    pub chip8: Chip8,
+    /// The display at the time of saving, at the machine's resolution
+    pub pixels: Vec<u8>,
+    /// Frame number of the frame clock, so frame counts carry on after loading
+    pub frame: u64,
+    pub saved_at: Instant,
+}
+
+/// What the frontend needs to do after the picker handled a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerAction {
+    None,
+    /// Restore the state in this slot and return to the game
+    Load(usize),
+    /// Overwrite this slot with the current state
+    Save(usize),
+}
+
+/// A grid of save slots showing a thumbnail of each, navigated with the
+/// arrow keys. Enter loads the selected slot and S overwrites it.
+pub struct StatePicker {
+    selected: usize,
+}
+
+impl StatePicker {
+    pub fn new() -> Self {
+        StatePicker { selected: 0 }
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode, slots: &[Option<SaveState>]) -> PickerAction {
+        match key {
+            VirtualKeyCode::Left => {
+                self.selected = (self.selected + SLOT_COUNT - 1) % SLOT_COUNT;
+                PickerAction::None
+            }
+            VirtualKeyCode::Right => {
+                self.selected = (self.selected + 1) % SLOT_COUNT;
+                PickerAction::None
+            }
+            VirtualKeyCode::Return if slots[self.selected].is_some() => {
+                PickerAction::Load(self.selected)
+            }
+            VirtualKeyCode::S => PickerAction::Save(self.selected),
+            _ => PickerAction::None,
+        }
+    }
+
+    /// Draws the row of thumbnails with the details of the selected slot below
+    pub fn draw(&self, frame: &mut [u8], width: usize, slots: &[Option<SaveState>], now: Instant) {
+        overlay::clear(frame, BACKGROUND_COLOR);
+
+        // Each cell holds an outlined thumbnail with a pixel of space either side
+        let cell_width = width / SLOT_COUNT;
+        let thumbnail_width = cell_width - 4;
+        let thumbnail_height = thumbnail_width / 2;
+
+        for (index, slot) in slots.iter().enumerate() {
+            let x = index * cell_width + 1;
+            let color = if index == self.selected {
+                TEXT_COLOR
+            } else {
+                UNSELECTED_COLOR
+            };
+
+            draw_outline(
+                frame,
+                width,
+                x,
+                1,
+                thumbnail_width + 2,
+                thumbnail_height + 2,
+                color,
+            );
+            if let Some(state) = slot {
+                draw_thumbnail(frame, width, x + 1, 2, thumbnail_width, state);
+            }
+        }
+
+        let lines = match &slots[self.selected] {
+            Some(state) => vec![
+                format!("SLOT {}", self.selected + 1),
+                format!("{} AGO", format_age(now - state.saved_at)),
+                format!("FRAME {}", state.frame),
+            ],
+            None => vec![format!("SLOT {}", self.selected + 1), "EMPTY".to_string()],
+        };
+
+        let top = thumbnail_height + 5;
+        for (i, line) in lines.iter().enumerate() {
+            overlay::draw_text(frame, width, 1, top + i * LINE_ADVANCE, line, TEXT_COLOR);
+        }
+    }
+}
+
+/// Draws a one pixel rectangle around an area `width` by `height` pixels,
+/// whose top left corner is at `(x, y)`
+fn draw_outline(
+    frame: &mut [u8],
+    frame_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    color: [u8; 4],
+) {
+    let mut plot = |px: usize, py: usize| {
+        let index = 4 * (px + py * frame_width);
+        frame[index..index + 4].copy_from_slice(&color);
+    };
+
+    for px in x..x + width {
+        plot(px, y);
+        plot(px, y + height - 1);
+    }
+    for py in y..y + height {
+        plot(x, py);
+        plot(x + width - 1, py);
+    }
+}
+
+/// Draws the saved display scaled down to `width` pixels across, keeping its
+/// aspect ratio, with nearest neighbour sampling
+fn draw_thumbnail(
+    frame: &mut [u8],
+    frame_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    state: &SaveState,
+) {
+    let (source_width, source_height) = state.chip8.display_size();
+    let (source_width, source_height) = (source_width as usize, source_height as usize);
+    let height = width * source_height / source_width;
+
+    for ty in 0..height {
+        for tx in 0..width {
+            let sx = tx * source_width / width;
+            let sy = ty * source_height / height;
+            let source = 4 * (sx + sy * source_width);
+            let index = 4 * (x + tx + (y + ty) * frame_width);
+            frame[index..index + 4].copy_from_slice(&state.pixels[source..source + 4]);
+        }
+    }
+}
+
+/// Formats how long ago a state was saved in the largest unit that fits
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..=59 => format!("{}S", seconds),
+        60..=3599 => format!("{}M", seconds / 60),
+        _ => format!("{}H", seconds / 3600),
+    }
+}
@@ -0,0 +1,229 @@
+//! Finds where a game keeps a value such as its lives or score, the way cheat
+//! finders do: snapshot the registers and memory, play on, then keep only the
+//! locations equal to a value or that changed a certain way since the last
+//! snapshot. A handful of filters usually leaves the one location, ready for
+//! a watch file or a patch.
+
+use std::fmt;
+
+use winit::event::VirtualKeyCode;
+
+use crate::hardware::Chip8;
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Registers come first in a snapshot, followed by memory
+const REGISTER_COUNT: usize = 16;
+
+/// Locations printed to stderr once the search is narrowed down this far
+const PRINT_LIMIT: usize = 16;
+
+/// Somewhere a game can keep a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Register(u8),
+    Memory(u32),
+}
+
+impl Location {
+    /// The location of an index into a snapshot
+    fn from_index(index: u32) -> Self {
+        match index.checked_sub(REGISTER_COUNT as u32) {
+            Some(address) => Location::Memory(address),
+            None => Location::Register(index as u8),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Register(register) => write!(f, "V{:X}", register),
+            Location::Memory(address) => write!(f, "{:03X}", address),
+        }
+    }
+}
+
+/// Which locations a filter keeps, comparing each one's value in the last
+/// snapshot with its value now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl Filter {
+    fn keeps(self, previous: u8, current: u8) -> bool {
+        match self {
+            Filter::Equal(value) => current == value,
+            Filter::Changed => current != previous,
+            Filter::Unchanged => current == previous,
+            Filter::Increased => current > previous,
+            Filter::Decreased => current < previous,
+        }
+    }
+}
+
+/// Every register followed by every byte of memory
+fn snapshot(chip8: &Chip8) -> Vec<u8> {
+    let mut values = chip8.registers().to_vec();
+    values.extend_from_slice(chip8.memory());
+    values
+}
+
+/// The search, kept while the game runs between filters. Keys while it is
+/// open: N starts a new search, digits and Enter keep the locations equal to
+/// a decimal value, and C, U, I and D keep those that changed, stayed the
+/// same, increased or decreased.
+pub struct MemorySearch {
+    /// Values as of the last filter, empty until a search is started
+    snapshot: Vec<u8>,
+    /// Indexes into `snapshot` that passed every filter, or `None` while
+    /// nothing has been filtered out
+    candidates: Option<Vec<u32>>,
+    /// Digits of the value being typed
+    typed: String,
+}
+
+impl MemorySearch {
+    pub fn new() -> Self {
+        MemorySearch {
+            snapshot: vec![],
+            candidates: None,
+            typed: String::new(),
+        }
+    }
+
+    /// Starts over with every location a candidate
+    fn start(&mut self, chip8: &Chip8) {
+        self.snapshot = snapshot(chip8);
+        self.candidates = None;
+    }
+
+    fn remaining(&self) -> usize {
+        self.candidates
+            .as_ref()
+            .map_or(self.snapshot.len(), Vec::len)
+    }
+
+    /// Keeps the candidates that pass `filter` and takes a new snapshot. A
+    /// machine whose memory has changed size since, after a reset to another
+    /// platform, starts a new search instead.
+    fn filter(&mut self, filter: Filter, chip8: &Chip8) {
+        let current = snapshot(chip8);
+        if current.len() != self.snapshot.len() {
+            self.start(chip8);
+            if let Filter::Equal(_) = filter {
+                // A value is a fine first filter, with nothing to compare
+                self.filter(filter, chip8);
+            }
+            return;
+        }
+
+        let previous = &self.snapshot;
+        let keeps = |&index: &u32| {
+            let index = index as usize;
+            filter.keeps(previous[index], current[index])
+        };
+        let candidates = match self.candidates.take() {
+            Some(candidates) => candidates.into_iter().filter(keeps).collect(),
+            None => (0..current.len() as u32).filter(keeps).collect(),
+        };
+        self.candidates = Some(candidates);
+        self.snapshot = current;
+
+        if self.remaining() <= PRINT_LIMIT {
+            self.print();
+        }
+    }
+
+    /// Lists the candidates left on stderr, so they can be copied into a
+    /// watch file or a patch
+    fn print(&self) {
+        eprintln!("{} locations left:", self.remaining());
+        for &index in self.candidates.iter().flatten() {
+            eprintln!(
+                "  {} = {}",
+                Location::from_index(index),
+                self.snapshot[index as usize]
+            );
+        }
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode, chip8: &Chip8) {
+        let digit = match key {
+            VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some('0'),
+            VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some('1'),
+            VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some('2'),
+            VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some('3'),
+            VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some('4'),
+            VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some('5'),
+            VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some('6'),
+            VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some('7'),
+            VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some('8'),
+            VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some('9'),
+            _ => None,
+        };
+        if let Some(digit) = digit {
+            let typed = format!("{}{}", self.typed, digit);
+            if typed.parse::<u8>().is_ok() {
+                self.typed = typed;
+            }
+            return;
+        }
+
+        match key {
+            VirtualKeyCode::Back => {
+                self.typed.pop();
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                if let Ok(value) = self.typed.parse() {
+                    self.filter(Filter::Equal(value), chip8);
+                    self.typed.clear();
+                }
+            }
+            VirtualKeyCode::N => self.start(chip8),
+            VirtualKeyCode::C => self.filter(Filter::Changed, chip8),
+            VirtualKeyCode::U => self.filter(Filter::Unchanged, chip8),
+            VirtualKeyCode::I => self.filter(Filter::Increased, chip8),
+            VirtualKeyCode::D => self.filter(Filter::Decreased, chip8),
+            _ => (),
+        }
+    }
+
+    /// Draws the number of candidates left and as many of them as fit, with
+    /// their current values, above the value being typed
+    pub fn draw(&self, frame: &mut [u8], width: usize, chip8: &Chip8) {
+        overlay::clear(frame, BACKGROUND_COLOR);
+        let rows = frame.len() / 4 / width / LINE_ADVANCE;
+
+        let header = if self.snapshot.is_empty() {
+            "N NEW SEARCH".to_string()
+        } else {
+            format!("{} LEFT", self.remaining())
+        };
+        overlay::draw_text(frame, width, 0, 0, &header, TEXT_COLOR);
+
+        let current = snapshot(chip8);
+        let shown = self
+            .candidates
+            .iter()
+            .flatten()
+            .filter(|&&index| (index as usize) < current.len())
+            .take(rows.saturating_sub(2));
+        for (row, &index) in shown.enumerate() {
+            let line = format!(
+                "{} {}",
+                Location::from_index(index),
+                current[index as usize]
+            );
+            overlay::draw_text(frame, width, 0, (row + 1) * LINE_ADVANCE, &line, TEXT_COLOR);
+        }
+
+        let footer = format!("={}_ C U I D", self.typed);
+        let y = rows.saturating_sub(1) * LINE_ADVANCE;
+        overlay::draw_text(frame, width, 0, y, &footer, TEXT_COLOR);
+    }
+}
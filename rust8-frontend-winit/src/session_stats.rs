@@ -0,0 +1,110 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::hardware::opcode_pattern;
+
+/// Width of the longest bar in the opcode histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Counts what happened over a windowed session, for a summary printed on
+/// exit. Time spent paused is left out of the per second rates.
+pub struct SessionStats {
+    started: Instant,
+    instructions: u64,
+    frames: u64,
+    draws: u64,
+    paused: Duration,
+    paused_since: Option<Instant>,
+    /// Instructions executed per opcode table pattern
+    opcodes: HashMap<&'static str, u64>,
+}
+
+impl SessionStats {
+    pub fn new(now: Instant) -> Self {
+        SessionStats {
+            started: now,
+            instructions: 0,
+            frames: 0,
+            draws: 0,
+            paused: Duration::ZERO,
+            paused_since: None,
+            opcodes: HashMap::new(),
+        }
+    }
+
+    /// Counts an instruction, called with its opcode before it is executed
+    pub fn record_instruction(&mut self, opcode: u16) {
+        let pattern = opcode_pattern(opcode).unwrap_or("????");
+        if pattern == "DXYN" {
+            self.draws += 1;
+        }
+
+        self.instructions += 1;
+        *self.opcodes.entry(pattern).or_default() += 1;
+    }
+
+    pub fn record_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Starts or stops the pause clock, ignoring calls that don't change it
+    pub fn set_paused(&mut self, paused: bool, now: Instant) {
+        match (paused, self.paused_since) {
+            (true, None) => self.paused_since = Some(now),
+            (false, Some(since)) => {
+                self.paused += now - since;
+                self.paused_since = None;
+            }
+            _ => (),
+        }
+    }
+
+    /// Totals, rates and a histogram of the opcodes executed, most frequent first
+    pub fn report(&self, now: Instant) -> String {
+        let paused = self.paused
+            + self
+                .paused_since
+                .map_or(Duration::ZERO, |since| now - since);
+        let elapsed = now - self.started;
+        let running = elapsed.saturating_sub(paused).as_secs_f64();
+        let per_second = |count: u64| {
+            if running > 0.0 {
+                count as f64 / running
+            } else {
+                0.0
+            }
+        };
+
+        let mut report = format!(
+            "Session lasted {:.1}s, {:.1}s of it paused\n\
+             {} instructions executed, {:.0} per second\n\
+             {} frames rendered\n\
+             {} draws, {:.1} per second\n",
+            elapsed.as_secs_f64(),
+            paused.as_secs_f64(),
+            self.instructions,
+            per_second(self.instructions),
+            self.frames,
+            self.draws,
+            per_second(self.draws)
+        );
+
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by_key(|(pattern, &count)| (Reverse(count), *pattern));
+        let most = opcodes.first().map_or(1, |(_, &count)| count);
+
+        for (pattern, &count) in opcodes {
+            let bar = (count * HISTOGRAM_WIDTH as u64 / most).max(1) as usize;
+            report.push_str(&format!(
+                "{:<6}{:>12}{:>7.2}% {}\n",
+                pattern,
+                count,
+                count as f64 * 100.0 / self.instructions as f64,
+                "#".repeat(bar)
+            ));
+        }
+
+        report
+    }
+}
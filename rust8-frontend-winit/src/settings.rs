@@ -0,0 +1,364 @@
+use std::mem;
+
+use winit::event::VirtualKeyCode;
+
+use crate::config::Config;
+use crate::hardware::Quirks;
+use crate::overlay::{
+    self, BACKGROUND_COLOR, CHAR_ADVANCE, GLYPH_HEIGHT, LINE_ADVANCE, TEXT_COLOR,
+};
+use crate::palette::Palette;
+
+/// How much one press of left or right changes the volume
+const VOLUME_STEP: f32 = 0.05;
+
+/// One press of left or right changes the speed by this fraction of itself,
+/// so slow and fast platforms both step sensibly
+const SPEED_DIVISOR: u32 = 10;
+
+/// Picks one quirk out of a set
+type QuirkField = fn(&mut Quirks) -> &mut bool;
+
+/// The quirks that can be flipped from the menu, with names short enough to
+/// fit on the 64 pixel wide display
+const QUIRKS: [(&str, QuirkField); 11] = [
+    ("MEM I", |quirks| &mut quirks.memory_increment_i),
+    ("JUMP VX", |quirks| &mut quirks.jump_uses_vx),
+    ("LOGIC VF", |quirks| &mut quirks.logic_resets_vf),
+    ("SHIFT VY", |quirks| &mut quirks.shift_uses_vy),
+    ("CLIP X", |quirks| &mut quirks.clip_horizontal),
+    ("CLIP Y", |quirks| &mut quirks.clip_vertical),
+    ("TALL SPR", |quirks| &mut quirks.lores_tall_sprites),
+    ("DISP WAIT", |quirks| &mut quirks.display_wait),
+    ("FONT MASK", |quirks| &mut quirks.font_digit_masked),
+    ("ADD I VF", |quirks| &mut quirks.add_i_sets_vf),
+    ("KEY PRESS", |quirks| &mut quirks.key_wait_on_press),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    Volume,
+    Speed,
+    Rumble,
+    Bloom,
+    Palette,
+    /// Index into `QUIRKS`
+    Quirk(usize),
+    QuirkReset,
+    KeysPlayerOne,
+    KeysPlayerTwo,
+    Save,
+    RestoreDefaults,
+}
+
+const ITEMS: [Item; 21] = [
+    Item::Volume,
+    Item::Speed,
+    Item::Rumble,
+    Item::Bloom,
+    Item::Palette,
+    Item::Quirk(0),
+    Item::Quirk(1),
+    Item::Quirk(2),
+    Item::Quirk(3),
+    Item::Quirk(4),
+    Item::Quirk(5),
+    Item::Quirk(6),
+    Item::Quirk(7),
+    Item::Quirk(8),
+    Item::Quirk(9),
+    Item::Quirk(10),
+    Item::QuirkReset,
+    Item::KeysPlayerOne,
+    Item::KeysPlayerTwo,
+    Item::Save,
+    Item::RestoreDefaults,
+];
+
+/// What the frontend needs to do after the menu handled a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsAction {
+    None,
+    /// The config changed and should be applied to the running emulator
+    Changed,
+    /// A quirk was flipped and should be applied to the running machine,
+    /// restarting it if `reset_on_quirk_change` is set
+    QuirksChanged,
+    /// Every setting went back to its default, so the machine should restart
+    /// with them
+    Reset,
+    /// The user asked to rebind the keys of a player (0 or 1)
+    Rebind(usize),
+}
+
+/// A menu of runtime settings, navigated with the arrow keys and enter.
+/// Changes apply immediately and are written to the config file on save.
+pub struct SettingsMenu {
+    selected: usize,
+    /// Result of the last save or restore, shown in place of the title
+    status: Option<String>,
+}
+
+impl SettingsMenu {
+    pub fn new() -> Self {
+        SettingsMenu {
+            selected: 0,
+            status: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode, config: &mut Config) -> SettingsAction {
+        let item = ITEMS[self.selected];
+
+        match key {
+            VirtualKeyCode::Up => {
+                self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+                SettingsAction::None
+            }
+            VirtualKeyCode::Down => {
+                self.selected = (self.selected + 1) % ITEMS.len();
+                SettingsAction::None
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Right => {
+                let direction = if key == VirtualKeyCode::Left {
+                    -1.0
+                } else {
+                    1.0
+                };
+                match item {
+                    Item::Volume => {
+                        let volume = config.audio.volume + direction * VOLUME_STEP;
+                        config.audio.volume = (volume.clamp(0.0, 1.0) * 100.0).round() / 100.0;
+                        SettingsAction::Changed
+                    }
+                    Item::Speed => {
+                        let cycles = config.cycles_per_frame();
+                        let step = (cycles / SPEED_DIVISOR).max(1);
+                        config.cycles_per_frame = Some(if key == VirtualKeyCode::Left {
+                            cycles.saturating_sub(step).max(1)
+                        } else {
+                            cycles + step
+                        });
+                        SettingsAction::Changed
+                    }
+                    Item::Rumble => {
+                        config.gamepad.rumble = !config.gamepad.rumble;
+                        SettingsAction::Changed
+                    }
+                    Item::Bloom => {
+                        config.display.bloom = !config.display.bloom;
+                        SettingsAction::Changed
+                    }
+                    Item::Palette => {
+                        config.display.cycle_palette(key == VirtualKeyCode::Right);
+                        SettingsAction::Changed
+                    }
+                    Item::Quirk(index) => self.toggle_quirk(index, config),
+                    Item::QuirkReset => {
+                        config.reset_on_quirk_change = !config.reset_on_quirk_change;
+                        SettingsAction::None
+                    }
+                    _ => SettingsAction::None,
+                }
+            }
+            VirtualKeyCode::Return => match item {
+                Item::Rumble => {
+                    config.gamepad.rumble = !config.gamepad.rumble;
+                    SettingsAction::Changed
+                }
+                Item::Bloom => {
+                    config.display.bloom = !config.display.bloom;
+                    SettingsAction::Changed
+                }
+                Item::Palette => {
+                    config.display.cycle_palette(true);
+                    SettingsAction::Changed
+                }
+                Item::Quirk(index) => self.toggle_quirk(index, config),
+                Item::QuirkReset => {
+                    config.reset_on_quirk_change = !config.reset_on_quirk_change;
+                    SettingsAction::None
+                }
+                Item::KeysPlayerOne => SettingsAction::Rebind(0),
+                Item::KeysPlayerTwo => SettingsAction::Rebind(1),
+                Item::Save => {
+                    self.status = Some(match config.save() {
+                        Ok(()) => "SAVED".to_string(),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            "SAVE FAILED".to_string()
+                        }
+                    });
+                    SettingsAction::None
+                }
+                Item::RestoreDefaults => {
+                    let shared_palettes = mem::take(&mut config.display.shared_palettes);
+                    let profiles = mem::take(&mut config.profiles);
+                    let profile = config.profile.take();
+                    *config = Config::default();
+                    config.display.shared_palettes = shared_palettes;
+                    config.profiles = profiles;
+                    config.profile = profile;
+                    self.status = Some("DEFAULTS SET".to_string());
+                    SettingsAction::Reset
+                }
+                Item::Volume | Item::Speed => SettingsAction::None,
+            },
+            _ => SettingsAction::None,
+        }
+    }
+
+    /// Flips a quirk, starting from the platform's quirks if they were in
+    /// use, and confirms the change in the title
+    fn toggle_quirk(&mut self, index: usize, config: &mut Config) -> SettingsAction {
+        let (name, quirk) = QUIRKS[index];
+        config.quirks = config.quirks();
+        config.custom_quirks = true;
+
+        let enabled = quirk(&mut config.quirks);
+        *enabled = !*enabled;
+        self.status = Some(format!("{} {}", name, on_off(*enabled)));
+        SettingsAction::QuirksChanged
+    }
+
+    fn label(item: Item, config: &Config) -> String {
+        match item {
+            Item::Volume => format!("VOLUME {:.0}%", config.audio.volume * 100.0),
+            Item::Speed => format!("SPEED {}", config.cycles_per_frame()),
+            Item::Rumble => format!("RUMBLE {}", on_off(config.gamepad.rumble)),
+            Item::Bloom => format!("BLOOM {}", on_off(config.display.bloom)),
+            Item::Palette => match config.display.palette() {
+                Some(palette) => format!("PALETTE {}", palette.name),
+                None => "PALETTE NONE".to_string(),
+            },
+            Item::Quirk(index) => {
+                let (name, quirk) = QUIRKS[index];
+                format!("{} {}", name, on_off(*quirk(&mut config.quirks())))
+            }
+            Item::QuirkReset => format!("AUTO RESET {}", on_off(config.reset_on_quirk_change)),
+            Item::KeysPlayerOne => "P1 KEYS".to_string(),
+            Item::KeysPlayerTwo => "P2 KEYS".to_string(),
+            Item::Save => "SAVE".to_string(),
+            Item::RestoreDefaults => "DEFAULTS".to_string(),
+        }
+    }
+
+    /// Draws the title followed by as many items as fit, scrolled to keep
+    /// the selected item visible
+    pub fn draw(&self, frame: &mut [u8], width: usize, config: &Config) {
+        overlay::clear(frame, BACKGROUND_COLOR);
+
+        let height = frame.len() / 4 / width;
+        let visible = (height / LINE_ADVANCE).saturating_sub(1).max(1);
+        let first = (self.selected + 1).saturating_sub(visible);
+
+        let title = self.status.as_deref().unwrap_or("SETTINGS");
+        overlay::draw_text(frame, width, 1, 1, title, TEXT_COLOR);
+
+        for (row, index) in (first..ITEMS.len()).take(visible).enumerate() {
+            let marker = if index == self.selected { ">" } else { " " };
+            let line = format!("{} {}", marker, SettingsMenu::label(ITEMS[index], config));
+            let y = 1 + (row + 1) * LINE_ADVANCE;
+            overlay::draw_text(frame, width, 1, y, &line, TEXT_COLOR);
+
+            if let (Item::Palette, Some(palette)) = (ITEMS[index], config.display.palette()) {
+                draw_swatches(frame, width, y, palette);
+            }
+        }
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+/// Draws a square of each palette color at the right end of a line, in the
+/// order of the plane combinations they are used for: none, plane 1, plane 2
+/// and both planes
+fn draw_swatches(frame: &mut [u8], width: usize, y: usize, palette: &Palette) {
+    let left = width.saturating_sub(palette.colors.len() * CHAR_ADVANCE);
+
+    for (i, color) in palette.colors.iter().enumerate() {
+        let x = left + i * CHAR_ADVANCE;
+        for row in y..y + GLYPH_HEIGHT {
+            for col in x..(x + CHAR_ADVANCE - 1).min(width) {
+                let index = 4 * (col + row * width);
+                if let Some(pixel) = frame.get_mut(index..index + 4) {
+                    pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::Platform;
+
+    /// A menu with `item` selected
+    fn menu_at(item: Item) -> SettingsMenu {
+        let mut menu = SettingsMenu::new();
+        menu.selected = ITEMS.iter().position(|&other| other == item).unwrap();
+        menu
+    }
+
+    #[test]
+    fn toggling_a_quirk_starts_from_the_platform_quirks() {
+        let mut config = Config::default();
+        config.platform = Some(Platform::Chip48);
+        // JUMP VX, which CHIP-48 turns on
+        let mut menu = menu_at(Item::Quirk(1));
+
+        let action = menu.handle_key(VirtualKeyCode::Return, &mut config);
+        assert_eq!(action, SettingsAction::QuirksChanged);
+        assert!(config.custom_quirks);
+        assert!(!config.quirks().jump_uses_vx);
+        // The platform's other quirks carry over
+        assert!(!config.quirks().memory_increment_i);
+        assert_eq!(menu.status.as_deref(), Some("JUMP VX OFF"));
+
+        menu.handle_key(VirtualKeyCode::Right, &mut config);
+        assert!(config.quirks().jump_uses_vx);
+        assert_eq!(menu.status.as_deref(), Some("JUMP VX ON"));
+    }
+
+    #[test]
+    fn saving_is_refused_while_a_profile_is_active() {
+        let mut config = Config::default();
+        config.profile = Some("fast".to_string());
+        let mut menu = menu_at(Item::Save);
+
+        let action = menu.handle_key(VirtualKeyCode::Return, &mut config);
+        assert_eq!(action, SettingsAction::None);
+        assert_eq!(menu.status.as_deref(), Some("SAVE FAILED"));
+    }
+
+    #[test]
+    fn restoring_defaults_keeps_the_active_profile() {
+        let mut config = Config::default();
+        config.profile = Some("fast".to_string());
+        config.custom_quirks = true;
+        let mut menu = menu_at(Item::RestoreDefaults);
+
+        let action = menu.handle_key(VirtualKeyCode::Return, &mut config);
+        assert_eq!(action, SettingsAction::Reset);
+        assert!(!config.custom_quirks);
+        assert_eq!(config.profile.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn selection_wraps_around_the_menu() {
+        let mut config = Config::default();
+        let mut menu = SettingsMenu::new();
+
+        menu.handle_key(VirtualKeyCode::Up, &mut config);
+        assert_eq!(ITEMS[menu.selected], Item::RestoreDefaults);
+        menu.handle_key(VirtualKeyCode::Down, &mut config);
+        assert_eq!(ITEMS[menu.selected], Item::Volume);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::assembler;
+
+/// Bounces a RUST8 logo above the words DROP A ROM, drawn from its own 4x5
+/// letters since the built in font only has hex digits. The logo is erased
+/// by drawing it again rather than clearing, so the text is drawn only once.
+const PROGRAM: &str = "
+        CLS
+        LD V0, 7
+        LD V1, 25
+        LD V3, 5
+        LD I, text
+        LD V2, 10
+text_loop:
+        DRW V0, V1, 5
+        ADD V0, 5
+        ADD I, V3
+        ADD V2, 0xFF
+        SE V2, 0
+        JP text_loop
+
+        LD V5, 4            ; logo row
+        LD V6, 1            ; logo direction, 0xFF going up
+bounce: CALL logo
+        LD V4, 4
+        LD DT, V4
+wait:   LD V4, DT
+        SE V4, 0
+        JP wait
+        CALL logo
+        ADD V5, V6
+        SNE V5, 14
+        LD V6, 0xFF
+        SNE V5, 4
+        LD V6, 1
+        JP bounce
+
+logo:   LD V0, 20
+        LD I, letters
+        LD V2, 5
+logo_loop:
+        DRW V0, V5, 5
+        ADD V0, 5
+        ADD I, V3
+        ADD V2, 0xFF
+        SE V2, 0
+        JP logo_loop
+        RET
+
+letters:
+        DB 0xE0, 0x90, 0xE0, 0xA0, 0x90 ; R
+        DB 0x90, 0x90, 0x90, 0x90, 0x60 ; U
+        DB 0x70, 0x80, 0x60, 0x10, 0xE0 ; S
+        DB 0xE0, 0x40, 0x40, 0x40, 0x40 ; T
+        DB 0x60, 0x90, 0x60, 0x90, 0x60 ; 8
+text:
+        DB 0xE0, 0x90, 0x90, 0x90, 0xE0 ; D
+        DB 0xE0, 0x90, 0xE0, 0xA0, 0x90 ; R
+        DB 0x60, 0x90, 0x90, 0x90, 0x60 ; O
+        DB 0xE0, 0x90, 0xE0, 0x80, 0x80 ; P
+        DB 0x00, 0x00, 0x00, 0x00, 0x00 ; space
+        DB 0x60, 0x90, 0xF0, 0x90, 0x90 ; A
+        DB 0x00, 0x00, 0x00, 0x00, 0x00 ; space
+        DB 0xE0, 0x90, 0xE0, 0xA0, 0x90 ; R
+        DB 0x60, 0x90, 0x90, 0x90, 0x60 ; O
+        DB 0x88, 0xD8, 0xA8, 0x88, 0x88 ; M
+";
+
+/// The program shown when the emulator is started without a ROM
+pub fn rom() -> Vec<u8> {
+    assembler::assemble(PROGRAM).expect("the splash program assembles")
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::Path;
+
+use crate::expression::Expression;
+use crate::hardware::{parse_number, Chip8};
+use crate::overlay::{self, BACKGROUND_COLOR, LINE_ADVANCE, TEXT_COLOR};
+
+/// Color of values that changed during the last frame
+const CHANGED_COLOR: [u8; 4] = [255, 200, 0, 255];
+
+/// Something shown in the watch panel under a name, such as a game's score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    pub name: String,
+    pub target: Target,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// An address or range of memory
+    Memory { address: u16, length: u16 },
+    /// Arithmetic over registers and memory, see `Expression`
+    Expression(Expression),
+}
+
+impl Watch {
+    /// Reads the current value, single bytes in decimal and ranges as hex
+    fn read(&self, chip8: &Chip8) -> String {
+        match &self.target {
+            Target::Memory { address, length } => {
+                let memory = chip8.memory();
+                let bytes: Vec<u8> = (0..*length)
+                    .map(|offset| memory[address.wrapping_add(offset) as usize % memory.len()])
+                    .collect();
+
+                match bytes.as_slice() {
+                    [byte] => byte.to_string(),
+                    bytes => bytes.iter().map(|byte| format!("{:02X}", byte)).collect(),
+                }
+            }
+            Target::Expression(expression) => expression.evaluate(chip8).to_string(),
+        }
+    }
+}
+
+/// Loads watches from a file with one watch per line, either
+/// `name address [length]` for memory, e.g. `score 0x3F0` or `board 0x300 4`,
+/// or `name = expression`, e.g. `score = mem[I+1] * 10 + mem[I+2]`.
+/// Text after `#` is ignored.
+pub fn load_watches(path: &Path) -> Result<Vec<Watch>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let invalid = |line: usize| format!("{}:{}: invalid watch", path.display(), line + 1);
+
+    let mut watches = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+
+        if let Some((name, source)) = line.split_once('=') {
+            let expression = Expression::parse(source)
+                .map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?;
+            watches.push(Watch {
+                name: name.trim().to_uppercase(),
+                target: Target::Expression(expression),
+            });
+            continue;
+        }
+
+        let parts: Vec<String> = line.split_whitespace().map(str::to_uppercase).collect();
+
+        let watch = match parts.as_slice() {
+            [] => continue,
+            [name, address] => parse_number(address).map(|address| (name, address, 1)),
+            [name, address, length] => parse_number(address)
+                .zip(parse_number(length))
+                .map(|(address, length)| (name, address, length)),
+            _ => None,
+        };
+
+        match watch {
+            Some((name, address, length)) if length > 0 => watches.push(Watch {
+                name: name.clone(),
+                target: Target::Memory { address, length },
+            }),
+            _ => return Err(invalid(i)),
+        }
+    }
+
+    Ok(watches)
+}
+
+/// Shows the live values of the watches over the game, highlighting any that
+/// changed since the previous frame
+pub struct WatchPanel {
+    watches: Vec<Watch>,
+    values: Vec<String>,
+    changed: Vec<bool>,
+    pub visible: bool,
+}
+
+impl WatchPanel {
+    pub fn new(watches: Vec<Watch>) -> Self {
+        let count = watches.len();
+        WatchPanel {
+            watches,
+            values: vec![String::new(); count],
+            changed: vec![false; count],
+            visible: true,
+        }
+    }
+
+    /// Reads the watches, returning true if the panel needs redrawing
+    /// because a value changed now or was highlighted for changing last frame
+    pub fn update(&mut self, chip8: &Chip8) -> bool {
+        let was_highlighted = self.changed.iter().any(|&changed| changed);
+
+        for (i, watch) in self.watches.iter().enumerate() {
+            let value = watch.read(chip8);
+            self.changed[i] = value != self.values[i];
+            self.values[i] = value;
+        }
+
+        was_highlighted || self.changed.iter().any(|&changed| changed)
+    }
+
+    /// Draws one line per watch from the top of the frame
+    pub fn draw(&self, frame: &mut [u8], width: usize) {
+        for (i, watch) in self.watches.iter().enumerate() {
+            let color = if self.changed[i] {
+                CHANGED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+
+            // Blank out the game behind the line so the text stays readable
+            let y = i * LINE_ADVANCE;
+            let start = (4 * y * width).min(frame.len());
+            let end = (4 * (y + LINE_ADVANCE) * width).min(frame.len());
+            overlay::clear(&mut frame[start..end], BACKGROUND_COLOR);
+
+            let line = format!("{} {}", watch.name, self.values[i]);
+            overlay::draw_text(frame, width, 0, y, &line, color);
+        }
+    }
+}
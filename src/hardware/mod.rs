@@ -1,4 +0,0 @@
-mod chip8;
-mod instruction_decoder;
-
-pub use chip8::Chip8;